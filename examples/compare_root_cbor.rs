@@ -29,11 +29,11 @@ fn main() {
     println!("DagSize: {:?}", go_root.dag_size);
     println!(
         "ClassicMerkleRoot: {:?}",
-        go_root.classic_merkle_root.as_ref().map(|r| hex::encode(r))
+        go_root.classic_merkle_root.as_ref().map(hex::encode)
     );
     println!(
         "ContentHash: {:?}",
-        go_root.content_hash.as_ref().map(|h| hex::encode(h))
+        go_root.content_hash.as_ref().map(hex::encode)
     );
     println!("Content: {:?}", go_root.content.as_ref().map(|c| c.len()));
     println!("AdditionalData: {:?}", go_root.additional_data);
@@ -53,14 +53,11 @@ fn main() {
     println!("DagSize: {:?}", rust_root.dag_size);
     println!(
         "ClassicMerkleRoot: {:?}",
-        rust_root
-            .classic_merkle_root
-            .as_ref()
-            .map(|r| hex::encode(r))
+        rust_root.classic_merkle_root.as_ref().map(hex::encode)
     );
     println!(
         "ContentHash: {:?}",
-        rust_root.content_hash.as_ref().map(|h| hex::encode(h))
+        rust_root.content_hash.as_ref().map(hex::encode)
     );
     println!("Content: {:?}", rust_root.content.as_ref().map(|c| c.len()));
     println!("AdditionalData: {:?}", rust_root.additional_data);
@@ -72,7 +69,7 @@ fn main() {
         println!("❌ ClassicMerkleRoot differs!");
     }
 
-    if &go_root.links[..5] == &rust_root.links[..5] {
+    if go_root.links[..5] == rust_root.links[..5] {
         println!("✅ First 5 links match");
     } else {
         println!("❌ Links differ!");
@@ -12,7 +12,7 @@ fn main() {
     println!("Root: {}", dag.root);
 
     // Find and analyze the first chunk
-    for (_hash, leaf) in &dag.leaves {
+    for leaf in dag.leaves.values() {
         if leaf.leaf_type == scionic_merkle_tree_rs::LeafType::Chunk {
             println!("\nFirst chunk found:");
             println!("  Hash: {}", leaf.hash);
@@ -23,7 +23,7 @@ fn main() {
             );
             println!(
                 "  ContentHash: {:?}",
-                leaf.content_hash.as_ref().map(|h| hex::encode(h))
+                leaf.content_hash.as_ref().map(hex::encode)
             );
             println!("  CurrentLinkCount: {}", leaf.current_link_count);
             println!(
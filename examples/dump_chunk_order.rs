@@ -13,7 +13,7 @@ fn main() {
     println!("DAG Root: {}", dag.root);
 
     // Find file leaf
-    for (_hash, leaf) in &dag.leaves {
+    for leaf in dag.leaves.values() {
         if leaf.leaf_type == scionic_merkle_tree_rs::LeafType::File {
             println!("\nFile leaf: {}", leaf.hash);
             println!("Links array (in order):");
@@ -28,7 +28,7 @@ fn main() {
     println!();
 
     // Find file leaf
-    for (_hash, leaf) in &dag.leaves {
+    for leaf in dag.leaves.values() {
         if leaf.leaf_type == scionic_merkle_tree_rs::LeafType::File {
             println!("File leaf:");
             println!("  Hash: {}", leaf.hash);
@@ -39,7 +39,7 @@ fn main() {
     }
 
     // Show first chunk
-    for (_hash, leaf) in &dag.leaves {
+    for leaf in dag.leaves.values() {
         if leaf.leaf_type == scionic_merkle_tree_rs::LeafType::Chunk {
             println!("\nFirst chunk:");
             println!("  Hash: {}", leaf.hash);
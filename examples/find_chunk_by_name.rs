@@ -12,7 +12,7 @@ fn main() {
     let dag = Dag::load_from_file(&args[1]).expect("Failed to load DAG");
     let target_name = &args[2];
 
-    for (_hash, leaf) in &dag.leaves {
+    for leaf in dag.leaves.values() {
         if leaf.item_name == *target_name {
             println!("Found chunk: {}", target_name);
             println!("  Hash: {}", leaf.hash);
@@ -23,7 +23,7 @@ fn main() {
             );
             println!(
                 "  ContentHash: {:?}",
-                leaf.content_hash.as_ref().map(|h| hex::encode(h))
+                leaf.content_hash.as_ref().map(hex::encode)
             );
 
             match leaf.verify_leaf() {
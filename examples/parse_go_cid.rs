@@ -12,7 +12,7 @@ fn main() {
 
             // Try converting back
             println!("\nConverting back to string:");
-            println!("Default: {}", cid.to_string());
+            println!("Default: {}", cid);
             println!(
                 "Base32Upper: {}",
                 cid.to_string_of_base(multibase::Base::Base32Upper).unwrap()
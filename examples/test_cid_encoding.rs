@@ -12,7 +12,7 @@ fn main() {
     let mh = Multihash::<64>::wrap(0x12, &hash).unwrap();
     let cid = Cid::new_v1(0x71, mh);
 
-    println!("Default to_string(): {}", cid.to_string());
+    println!("Default to_string(): {}", cid);
     println!(
         "Base32Upper: {}",
         cid.to_string_of_base(multibase::Base::Base32Upper).unwrap()
@@ -48,7 +48,7 @@ fn main() {
         root_leaf
             .classic_merkle_root
             .as_ref()
-            .map(|r| hex::encode(r))
+            .map(hex::encode)
             .unwrap_or_else(|| "None".to_string())
     );
 
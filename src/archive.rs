@@ -0,0 +1,146 @@
+//! Writing DAGs out as tar archives
+//!
+//! Entries are laid out like [`Dag::create_directory`]: a directory root
+//! contributes its children at the top of the archive, a file root a single
+//! entry under its own name.
+//!
+//! The DAG doesn't record permissions or modification times, so directories
+//! get mode `0755`, files `0644`, symlinks `0777`, and every entry an mtime of zero.
+
+use crate::error::{Result, ScionicError};
+use crate::types::{Dag, DagLeaf, LeafType};
+use std::io::{self, Read, Write};
+use tar::{Builder, EntryType, Header};
+
+const DIR_MODE: u32 = 0o755;
+const FILE_MODE: u32 = 0o644;
+const LINK_MODE: u32 = 0o777;
+
+impl Dag {
+    /// Stream the DAG's files and directories into a tar archive
+    ///
+    /// Chunked files are read chunk by chunk as they're written, so no file
+    /// is reassembled in memory. The archive is finished before returning.
+    pub fn to_tar<W: Write>(&self, w: W) -> Result<()> {
+        let root = self.leaf(&self.root)?;
+        let mut builder = Builder::new(w);
+
+        match root.leaf_type {
+            LeafType::Directory => self.append_children(&mut builder, root)?,
+            LeafType::File => self.append_file(&mut builder, root)?,
+            LeafType::Symlink => append_symlink(&mut builder, root)?,
+            LeafType::Chunk => {
+                return Err(ScionicError::InvalidDag(
+                    "Root cannot be a chunk".to_string(),
+                ))
+            }
+        }
+
+        builder.into_inner()?;
+        Ok(())
+    }
+
+    fn append_children<W: Write>(&self, builder: &mut Builder<W>, dir: &DagLeaf) -> Result<()> {
+        // Child item_names are relative to root, so they're the archive paths
+        for child in self.linked_leaves(dir)? {
+            match child.leaf_type {
+                LeafType::Directory => {
+                    let mut header = new_header(EntryType::Directory, DIR_MODE, 0);
+                    builder.append_data(&mut header, &child.item_name, io::empty())?;
+                    self.append_children(builder, child)?;
+                }
+                LeafType::File => self.append_file(builder, child)?,
+                LeafType::Symlink => append_symlink(builder, child)?,
+                LeafType::Chunk => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append_file<W: Write>(&self, builder: &mut Builder<W>, file: &DagLeaf) -> Result<()> {
+        let parts = self.content_parts(file)?;
+        let size = parts.iter().map(|part| part.len() as u64).sum();
+
+        let mut header = new_header(EntryType::Regular, FILE_MODE, size);
+        builder.append_data(&mut header, &file.item_name, PartsReader::new(parts))?;
+        Ok(())
+    }
+
+    /// The file's content as slices of its chunks (or its inline content)
+    fn content_parts<'a>(&'a self, file: &'a DagLeaf) -> Result<Vec<&'a [u8]>> {
+        if file.links.is_empty() {
+            return Ok(file.content.as_deref().into_iter().collect());
+        }
+
+        self.linked_leaves(file)?
+            .into_iter()
+            .map(|chunk| {
+                chunk
+                    .content
+                    .as_deref()
+                    .ok_or_else(|| ScionicError::InvalidLeaf("Chunk has no content".to_string()))
+            })
+            .collect()
+    }
+
+    fn leaf(&self, hash: &str) -> Result<&DagLeaf> {
+        self.leaves
+            .get(hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))
+    }
+}
+
+fn append_symlink<W: Write>(builder: &mut Builder<W>, link: &DagLeaf) -> Result<()> {
+    let mut header = new_header(EntryType::Symlink, LINK_MODE, 0);
+    builder.append_link(&mut header, &link.item_name, link.symlink_target()?)?;
+    Ok(())
+}
+
+fn new_header(entry_type: EntryType, mode: u32, size: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_mode(mode);
+    header.set_size(size);
+    header.set_mtime(0);
+    header
+}
+
+/// Reads a sequence of byte slices as one stream
+struct PartsReader<'a> {
+    parts: Vec<&'a [u8]>,
+    index: usize,
+}
+
+impl<'a> PartsReader<'a> {
+    fn new(parts: Vec<&'a [u8]>) -> Self {
+        Self { parts, index: 0 }
+    }
+}
+
+impl Read for PartsReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(part) = self.parts.get_mut(self.index) {
+            if part.is_empty() {
+                self.index += 1;
+                continue;
+            }
+            return part.read(buf);
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts_reader() {
+        let parts: Vec<&[u8]> = vec![b"ab", b"", b"cde", b"f"];
+        let mut out = Vec::new();
+        PartsReader::new(parts).read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"abcdef");
+    }
+}
@@ -0,0 +1,120 @@
+//! Assembling a DAG from a skeleton and a stream of transmission packets
+//!
+//! A client can first fetch a partial DAG holding the directory structure,
+//! then stream in the file and chunk leaves. [`PacketAssembler`] checks
+//! every packet against the leaves it already holds, so a leaf is only
+//! accepted if its parent's Merkle root covers it, and reports when nothing
+//! is missing any more.
+
+use crate::error::{Result, ScionicError};
+use crate::types::{Dag, DagKind, TransmissionPacket};
+use std::collections::HashSet;
+
+/// Grows a verified skeleton into a full DAG, one packet at a time
+#[derive(Debug, Clone)]
+pub struct PacketAssembler {
+    dag: Dag,
+    /// Links of present leaves whose leaves haven't arrived yet
+    missing: HashSet<String>,
+}
+
+impl PacketAssembler {
+    /// Start from `skeleton`, a full or partial DAG that must verify
+    pub fn new(skeleton: Dag) -> Result<Self> {
+        skeleton.verify()?;
+
+        let missing = skeleton
+            .leaves
+            .values()
+            .flat_map(|leaf| &leaf.links)
+            .filter(|link| !skeleton.leaves.contains_key(*link))
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            dag: skeleton,
+            missing,
+        })
+    }
+
+    /// Verify and add the leaf of `packet`, returning whether the DAG is now
+    /// complete
+    ///
+    /// Parents must arrive before their children: the packet's parent has
+    /// to be present and link the leaf. The leaf's CID and content are
+    /// checked, which covers its links. Packets for leaves
+    /// already present are ignored if they match.
+    pub fn apply(&mut self, packet: TransmissionPacket) -> Result<bool> {
+        let hash = packet.leaf.hash.clone();
+        if hash != self.dag.root {
+            let parent = self.dag.leaves.get(&packet.parent_hash).ok_or_else(|| {
+                ScionicError::MissingLeaf(format!(
+                    "Parent {} of {} not received yet",
+                    packet.parent_hash, hash
+                ))
+            })?;
+            if !parent.has_link(&hash) {
+                return Err(ScionicError::MissingLink(format!(
+                    "{} doesn't link {}",
+                    packet.parent_hash, hash
+                )));
+            }
+        }
+
+        packet.leaf.verify_content_fields()?;
+        packet.leaf.verify_content_hash()?;
+        let links = packet.leaf.links.clone();
+        self.dag.apply_and_verify_transmission_packet(packet)?;
+
+        self.missing.remove(&hash);
+        self.missing.extend(
+            links
+                .into_iter()
+                .filter(|link| !self.dag.leaves.contains_key(link)),
+        );
+        Ok(self.is_complete())
+    }
+
+    /// Whether every linked leaf is present
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Hashes of the linked leaves still missing, sorted
+    pub fn missing(&self) -> Vec<String> {
+        let mut missing: Vec<String> = self.missing.iter().cloned().collect();
+        missing.sort();
+        missing
+    }
+
+    /// The assembled DAG, full if complete and partial otherwise
+    pub fn into_dag(self) -> Dag {
+        let kind = if self.is_complete() {
+            DagKind::Full
+        } else {
+            DagKind::Partial
+        };
+
+        Dag {
+            kind: Some(kind),
+            ..self.dag
+        }
+    }
+}
+
+impl Dag {
+    /// Assemble a DAG from a skeleton and the packets for the rest of it
+    ///
+    /// See [`PacketAssembler`]; the result is partial if packets were
+    /// missing, which [`Dag::kind`] reports.
+    pub fn combine_partials_streaming(
+        skeleton: Dag,
+        packets: impl IntoIterator<Item = TransmissionPacket>,
+    ) -> Result<Dag> {
+        let mut assembler = PacketAssembler::new(skeleton)?;
+        for packet in packets {
+            assembler.apply(packet)?;
+        }
+        Ok(assembler.into_dag())
+    }
+}
@@ -0,0 +1,274 @@
+//! In-memory DAG construction
+//!
+//! [`InMemoryDagBuilder`] assembles a DAG from paths and byte buffers, and
+//! [`create_dag_from_entries`] from paths and readers, instead of reading
+//! the filesystem. The result is identical to building the same tree from
+//! disk with [`create_dag_with_config`](crate::create_dag_with_config).
+
+use crate::dag::{
+    build_directory_leaf, build_file_leaf, build_file_leaf_from_reader, finish_dag, DagBuilder,
+};
+use crate::error::{Result, ScionicError};
+use crate::types::{Dag, DagBuilderConfig, DagLeaf};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+/// A node of the tree being assembled, with files already built
+enum Node {
+    File(Box<DagLeaf>),
+    Dir(BTreeMap<String, Node>),
+}
+
+/// Directory tree of built file leaves, turned into a DAG once complete
+struct TreeAssembler<'a> {
+    root: BTreeMap<String, Node>,
+    builder: DagBuilder,
+    config: &'a DagBuilderConfig,
+}
+
+impl<'a> TreeAssembler<'a> {
+    fn new(config: &'a DagBuilderConfig) -> Self {
+        Self {
+            root: BTreeMap::new(),
+            builder: DagBuilder::with_capacity(config.leaf_capacity_hint.unwrap_or(0)),
+            config,
+        }
+    }
+
+    fn add_file(&mut self, path: &str, data: Vec<u8>) -> Result<()> {
+        let components = path_components(path)?;
+        let rel_path = components.join("/");
+        self.builder
+            .count_file(Path::new(&rel_path), data.len() as u64, self.config)?;
+        let leaf = build_file_leaf(&rel_path, data, &mut self.builder, self.config)?;
+        insert_entry(&mut self.root, &components, Some(leaf))
+    }
+
+    fn add_reader<R: Read>(&mut self, path: &str, reader: R) -> Result<()> {
+        let components = path_components(path)?;
+        let leaf = build_file_leaf_from_reader(
+            &components.join("/"),
+            reader,
+            &mut self.builder,
+            self.config,
+        )?;
+        insert_entry(&mut self.root, &components, Some(leaf))
+    }
+
+    fn add_dir(&mut self, path: &str) -> Result<()> {
+        insert_entry(&mut self.root, &path_components(path)?, None)
+    }
+
+    fn finish(mut self) -> Result<Dag> {
+        let name = self.config.root_name.as_deref().unwrap_or("root");
+        let root_leaf = build_node(
+            name,
+            Node::Dir(self.root),
+            true,
+            &mut self.builder,
+            self.config,
+        )?;
+
+        finish_dag(root_leaf, self.builder, self.config)
+    }
+}
+
+/// Fluent builder for DAGs assembled from in-memory content
+///
+/// Paths use `/` as the separator and are relative to the root directory.
+/// Parent directories are created implicitly.
+pub struct InMemoryDagBuilder {
+    config: DagBuilderConfig,
+    entries: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl InMemoryDagBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: DagBuilderConfig::default(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Set the root directory's item name (defaults to `root`)
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.config.root_name = Some(name.into());
+        self
+    }
+
+    /// Use the given build configuration
+    pub fn with_config(mut self, config: DagBuilderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.config.chunk_size = Some(size);
+        self
+    }
+
+    pub fn without_chunking(mut self) -> Self {
+        self.config.chunk_size = Some(0);
+        self
+    }
+
+    /// Add a file with the given content
+    pub fn add_file(mut self, path: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        self.entries.push((path.into(), Some(data.into())));
+        self
+    }
+
+    /// Add a directory, which may stay empty
+    pub fn add_dir(mut self, path: impl Into<String>) -> Self {
+        self.entries.push((path.into(), None));
+        self
+    }
+
+    /// Build the DAG
+    pub fn build(self) -> Result<Dag> {
+        let mut tree = TreeAssembler::new(&self.config);
+        for (path, data) in self.entries {
+            match data {
+                Some(data) => tree.add_file(&path, data)?,
+                None => tree.add_dir(&path)?,
+            }
+        }
+
+        tree.finish()
+    }
+}
+
+impl Default for InMemoryDagBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dag {
+    /// Start building a DAG from in-memory content
+    pub fn builder() -> InMemoryDagBuilder {
+        InMemoryDagBuilder::new()
+    }
+}
+
+/// Build a DAG from (path, reader) entries, reading each one as it comes
+///
+/// Each reader is chunked and hashed while it is consumed, so only the
+/// built leaves are kept. The config's size limits are enforced as the
+/// readers are consumed. Paths use `/` as the separator, are relative to
+/// the root directory, and create parent directories implicitly. The root is
+/// named by the config's `root_name` (defaults to `root`).
+pub fn create_dag_from_entries<I>(entries: I, config: DagBuilderConfig) -> Result<Dag>
+where
+    I: Iterator<Item = (String, Box<dyn Read>)>,
+{
+    let mut tree = TreeAssembler::new(&config);
+    for (path, reader) in entries {
+        tree.add_reader(&path, reader)?;
+    }
+
+    tree.finish()
+}
+
+/// Split a path into its components, rejecting empty and escaping paths
+fn path_components(path: &str) -> Result<Vec<&str>> {
+    let components: Vec<&str> = path
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+
+    if components.is_empty() {
+        return Err(ScionicError::InvalidDag(format!("Empty path: {:?}", path)));
+    }
+    if components.contains(&"..") {
+        return Err(ScionicError::InvalidDag(format!(
+            "Path escapes the root: {}",
+            path
+        )));
+    }
+
+    Ok(components)
+}
+
+/// Insert a file (`Some`) or directory (`None`) into the tree
+fn insert_entry(
+    root: &mut BTreeMap<String, Node>,
+    components: &[&str],
+    file: Option<DagLeaf>,
+) -> Result<()> {
+    let Some((name, parents)) = components.split_last() else {
+        return Err(ScionicError::InvalidDag("Empty path".to_string()));
+    };
+    let path = components.join("/");
+
+    let mut dir = root;
+    for parent in parents {
+        let node = dir
+            .entry(parent.to_string())
+            .or_insert_with(|| Node::Dir(BTreeMap::new()));
+
+        dir = match node {
+            Node::Dir(children) => children,
+            Node::File(_) => {
+                return Err(ScionicError::InvalidDag(format!(
+                    "{} is a file, not a directory",
+                    parent
+                )))
+            }
+        };
+    }
+
+    match (dir.get(*name), file) {
+        (None, Some(leaf)) => {
+            dir.insert(name.to_string(), Node::File(Box::new(leaf)));
+        }
+        (None, None) => {
+            dir.insert(name.to_string(), Node::Dir(BTreeMap::new()));
+        }
+        (Some(Node::Dir(_)), None) => {}
+        (Some(_), _) => {
+            return Err(ScionicError::InvalidDag(format!(
+                "Duplicate path: {}",
+                path
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the leaf for a node, adding all descendants to the builder
+///
+/// Item names are paths relative to the root, so the root's children are
+/// named without the root's own name as a prefix.
+fn build_node(
+    item_name: &str,
+    node: Node,
+    is_root: bool,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<DagLeaf> {
+    match node {
+        Node::File(leaf) => Ok(*leaf),
+        Node::Dir(children) => {
+            let mut child_hashes = Vec::with_capacity(children.len());
+
+            for (name, child) in children {
+                let child_path = if is_root {
+                    name
+                } else {
+                    format!("{}/{}", item_name, name)
+                };
+
+                let child_leaf = build_node(&child_path, child, false, builder, config)?;
+                builder
+                    .leaves
+                    .insert(child_leaf.hash.clone(), child_leaf.clone());
+                child_hashes.push(child_leaf.hash);
+            }
+
+            build_directory_leaf(item_name.to_string(), child_hashes, builder, config)
+        }
+    }
+}
@@ -0,0 +1,535 @@
+//! CAR (Content Addressable aRchive) export and import
+//!
+//! Writes and reads CARv1 archives: a varint-prefixed DAG-CBOR header naming
+//! the root CID, followed by varint-prefixed `(CID, block)` sections. Each
+//! block is a leaf serialized with [`DagLeaf::to_block_bytes`].
+//!
+//! A block is the whole leaf record, not the bytes its CID is a hash of
+//! (those leave out the content and links), so generic CAR and IPFS tooling
+//! can't check blocks by rehashing them. Readers here recompute each leaf's
+//! CID from its fields instead.
+
+use crate::error::{Result, ScionicError};
+use crate::leaf::parse_cid;
+use crate::types::{Dag, DagLeaf, LeafType};
+use cid::Cid;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Write an unsigned LEB128 varint
+pub(crate) fn write_varint<W: Write>(w: &mut W, mut value: u64) -> Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    w.write_all(&buf[..len])?;
+    Ok(())
+}
+
+/// Read an unsigned LEB128 varint, returning None on a clean EOF
+pub(crate) fn read_varint<R: Read>(r: &mut R) -> Result<Option<u64>> {
+    let mut value: u64 = 0;
+    let mut byte = [0u8; 1];
+
+    for i in 0..10 {
+        if r.read(&mut byte)? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+            return Err(ScionicError::Deserialization(
+                "Truncated varint in CAR".to_string(),
+            ));
+        }
+
+        value |= ((byte[0] & 0x7f) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    Err(ScionicError::Deserialization(
+        "Varint too long in CAR".to_string(),
+    ))
+}
+
+/// Largest header accepted; it only names the root, so anything bigger is
+/// malformed
+const MAX_HEADER_LEN: u64 = 1024;
+
+/// Read a section of `len` bytes, a length taken from the archive
+///
+/// The buffer grows as data arrives rather than being allocated up front,
+/// so a corrupt length can't allocate more than the archive holds.
+fn read_section<R: Read>(r: &mut R, len: u64) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    r.take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(ScionicError::Deserialization(format!(
+            "CAR section of {} bytes is truncated at {}",
+            len,
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+/// Encode the CARv1 header `{"roots": [root], "version": 1}` as DAG-CBOR
+fn encode_header(root: &Cid) -> Vec<u8> {
+    // CIDs in DAG-CBOR are tag 42 over a byte string prefixed with 0x00
+    let mut cid_bytes = vec![0u8];
+    cid_bytes.extend_from_slice(&root.to_bytes());
+
+    let mut header = vec![0xa2]; // map(2)
+    header.push(0x65); // text(5)
+    header.extend_from_slice(b"roots");
+    header.push(0x81); // array(1)
+    header.extend_from_slice(&[0xd8, 0x2a]); // tag(42)
+    match cid_bytes.len() {
+        len if len < 24 => header.push(0x40 | len as u8),
+        len if len < 256 => header.extend_from_slice(&[0x58, len as u8]),
+        len => {
+            header.push(0x59);
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    header.extend_from_slice(&cid_bytes);
+    header.push(0x67); // text(7)
+    header.extend_from_slice(b"version");
+    header.push(0x01);
+
+    header
+}
+
+/// Decode a CARv1 header and return its single root
+fn decode_header(data: &[u8]) -> Result<Cid> {
+    let value: serde_cbor::Value =
+        serde_cbor::from_slice(data).map_err(|e| ScionicError::Deserialization(e.to_string()))?;
+
+    let serde_cbor::Value::Map(map) = value else {
+        return Err(ScionicError::Deserialization(
+            "CAR header is not a map".to_string(),
+        ));
+    };
+
+    let version = map.get(&serde_cbor::Value::Text("version".to_string()));
+    if version != Some(&serde_cbor::Value::Integer(1)) {
+        return Err(ScionicError::Deserialization(
+            "Unsupported CAR version".to_string(),
+        ));
+    }
+
+    let roots = match map.get(&serde_cbor::Value::Text("roots".to_string())) {
+        Some(serde_cbor::Value::Array(roots)) => roots,
+        _ => {
+            return Err(ScionicError::Deserialization(
+                "CAR header has no roots".to_string(),
+            ))
+        }
+    };
+
+    match roots.as_slice() {
+        [serde_cbor::Value::Bytes(bytes)] if bytes.first() == Some(&0) => {
+            Cid::try_from(&bytes[1..]).map_err(|e| ScionicError::InvalidCid(e.to_string()))
+        }
+        _ => Err(ScionicError::Deserialization(
+            "CAR header must have exactly one root CID".to_string(),
+        )),
+    }
+}
+
+/// Write the CARv1 header for `root`
+pub(crate) fn write_header<W: Write>(w: &mut W, root: &str) -> Result<()> {
+    let header = encode_header(&parse_cid(root)?);
+    write_varint(w, header.len() as u64)?;
+    w.write_all(&header)?;
+    Ok(())
+}
+
+/// Write one `(CID, block)` section for a leaf
+pub(crate) fn write_block<W: Write>(w: &mut W, leaf: &DagLeaf) -> Result<()> {
+    let cid_bytes = parse_cid(&leaf.hash)?.to_bytes();
+    let data = leaf.to_block_bytes()?;
+
+    write_varint(w, (cid_bytes.len() + data.len()) as u64)?;
+    w.write_all(&cid_bytes)?;
+    w.write_all(&data)?;
+    Ok(())
+}
+
+/// Read the CARv1 header, returning the root CID
+pub(crate) fn read_header<R: Read>(r: &mut R) -> Result<Cid> {
+    let header_len =
+        read_varint(r)?.ok_or_else(|| ScionicError::Deserialization("Empty CAR".to_string()))?;
+
+    if header_len > MAX_HEADER_LEN {
+        return Err(ScionicError::Deserialization(format!(
+            "CAR header of {} bytes is over the {} byte limit",
+            header_len, MAX_HEADER_LEN
+        )));
+    }
+
+    let header = read_section(r, header_len)?;
+    decode_header(&header)
+}
+
+/// Read the next `(CID, block)` section, returning None at the end of the archive
+pub(crate) fn read_block<R: Read>(r: &mut R) -> Result<Option<(Cid, Vec<u8>)>> {
+    let Some(section_len) = read_varint(r)? else {
+        return Ok(None);
+    };
+
+    let section = read_section(r, section_len)?;
+
+    let mut cursor = std::io::Cursor::new(&section);
+    let cid = Cid::read_bytes(&mut cursor).map_err(|e| ScionicError::InvalidCid(e.to_string()))?;
+    let data = section[cursor.position() as usize..].to_vec();
+
+    Ok(Some((cid, data)))
+}
+
+impl Dag {
+    /// Stream the DAG as a CARv1 archive, root block first
+    ///
+    /// Blocks are serialized and written one at a time, so the archive is
+    /// never held in memory as a whole.
+    pub fn write_car<W: Write>(&self, mut w: W) -> Result<()> {
+        write_header(&mut w, &self.root)?;
+
+        for hash in self.hashes_root_first() {
+            write_block(&mut w, &self.leaves[&hash])?;
+        }
+
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Serialize the DAG as a CARv1 archive
+    pub fn to_car(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.write_car(&mut data)?;
+        Ok(data)
+    }
+
+    /// Serialize a partial DAG for `leaf_hashes` as a CARv1 archive
+    ///
+    /// The requested leaves are included with their paths to the root and
+    /// Merkle proofs on every multi-link parent, and the header names this
+    /// DAG's root, so a recipient can verify the blocks against the root it
+    /// already trusts.
+    pub fn partial_car(&self, leaf_hashes: &[String]) -> Result<Vec<u8>> {
+        self.get_partial(leaf_hashes, false)?.to_car()
+    }
+
+    /// Every leaf's CID with its block, root first
+    ///
+    /// The in-memory analog of [`write_car`](Self::write_car), for stores
+    /// that take blocks in a batch. A block is the whole leaf record as
+    /// CBOR ([`DagLeaf::to_block_bytes`]), content and links included, not
+    /// the bytes its CID is a hash of, so it doesn't hash to the CID; see
+    /// the [module docs](self).
+    pub fn to_blocks(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        self.hashes_root_first()
+            .into_iter()
+            .map(|hash| {
+                let data = self.leaves[&hash].to_block_bytes()?;
+                Ok((hash, data))
+            })
+            .collect()
+    }
+
+    /// Reassemble a DAG from `(CID, block)` pairs, as produced by
+    /// [`to_blocks`](Self::to_blocks)
+    ///
+    /// Each block is a whole leaf record as CBOR, read with
+    /// [`DagLeaf::from_block_bytes`], and each leaf is checked on ingest:
+    /// its CID is recomputed from its fields, its links must make up its
+    /// merkle root and its content must match its content hash, so a block
+    /// stored under the wrong CID or altered in storage is rejected. Whether the DAG is full or partial is inferred,
+    /// as for [`read_car`](Self::read_car).
+    pub fn from_blocks(
+        root: &str,
+        blocks: impl IntoIterator<Item = (String, Vec<u8>)>,
+    ) -> Result<Self> {
+        let root_cid = parse_cid(root)?;
+        let mut root = root.to_string();
+        let mut leaves = HashMap::new();
+        for (cid, data) in blocks {
+            let is_root = parse_cid(&cid)? == root_cid;
+            let leaf = verified_leaf(&cid, &data, is_root)?;
+            if is_root {
+                root = leaf.hash.clone();
+            }
+            leaves.insert(leaf.hash.clone(), leaf);
+        }
+
+        Self::from_parts(root, leaves, None)
+    }
+
+    /// Read a DAG from a CARv1 archive, checking each block is stored under its CID
+    ///
+    /// Each leaf is checked as by [`from_blocks`](Self::from_blocks).
+    /// The archive holds binary CIDs, so leaves keep the multibase their
+    /// hashes were written in.
+    pub fn read_car<R: Read>(mut r: R) -> Result<Self> {
+        let root_cid = read_header(&mut r)?;
+
+        let mut root = None;
+        let mut leaves = HashMap::new();
+        while let Some((cid, data)) = read_block(&mut r)? {
+            let leaf = verified_leaf(&cid.to_string(), &data, cid == root_cid)?;
+            if cid == root_cid {
+                root = Some(leaf.hash.clone());
+            }
+            leaves.insert(leaf.hash.clone(), leaf);
+        }
+
+        let root = root.ok_or_else(|| {
+            ScionicError::MissingLeaf(format!("Root {} not found in CAR", root_cid))
+        })?;
+
+        Ok(Dag {
+            root,
+            leaves,
+            labels: None,
+            kind: None,
+        })
+    }
+
+    /// Deserialize a DAG from CARv1 bytes
+    pub fn from_car(data: &[u8]) -> Result<Self> {
+        Self::read_car(data)
+    }
+}
+
+/// Deserialize the leaf stored under `cid`, recomputing its CID (as a root
+/// leaf if `is_root`) and checking its content against its content hash
+fn verified_leaf(cid: &str, data: &[u8], is_root: bool) -> Result<DagLeaf> {
+    let leaf = DagLeaf::from_block_bytes(cid, data)?;
+    if is_root {
+        leaf.verify_root_leaf()?;
+    } else {
+        leaf.verify_leaf()?;
+    }
+    leaf.verify_content_fields()?;
+    leaf.verify_content_hash()?;
+    Ok(leaf)
+}
+
+/// Location of a block's data within a CAR file
+#[derive(Debug, Clone, Copy)]
+struct BlockLocation {
+    offset: u64,
+    len: u64,
+}
+
+/// A DAG read lazily out of a CAR archive
+///
+/// Opening only indexes where each block lives; leaves are read and verified
+/// when requested, so the archive is never loaded as a whole.
+pub struct CarDag<R> {
+    reader: R,
+    root: String,
+    root_cid: Cid,
+    index: HashMap<Cid, BlockLocation>,
+}
+
+impl<R: Read + Seek> CarDag<R> {
+    /// Index the blocks of a CARv1 archive
+    pub fn open(mut reader: R) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let archive_end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        let root_cid = read_header(&mut reader)?;
+
+        let mut index = HashMap::new();
+        while let Some(section_len) = read_varint(&mut reader)? {
+            let start = reader.stream_position()?;
+            let cid = Cid::read_bytes(&mut reader)
+                .map_err(|e| ScionicError::InvalidCid(e.to_string()))?;
+            let offset = reader.stream_position()?;
+
+            let cid_len = offset - start;
+            let len = section_len.checked_sub(cid_len).ok_or_else(|| {
+                ScionicError::Deserialization("CAR section shorter than its CID".to_string())
+            })?;
+            let end = offset
+                .checked_add(len)
+                .filter(|end| *end <= archive_end)
+                .ok_or_else(|| {
+                    ScionicError::Deserialization(format!(
+                        "CAR section of {} bytes runs past the end of the archive",
+                        section_len
+                    ))
+                })?;
+
+            index.insert(cid, BlockLocation { offset, len });
+            reader.seek(SeekFrom::Start(end))?;
+        }
+
+        if !index.contains_key(&root_cid) {
+            return Err(ScionicError::MissingLeaf(format!(
+                "Root {} not found in CAR",
+                root_cid
+            )));
+        }
+
+        // The root's hash in the multibase the DAG was built with, taken
+        // from its block; it is verified when read
+        let mut car = Self {
+            reader,
+            root: root_cid.to_string(),
+            root_cid,
+            index,
+        };
+        car.root = car.read_leaf(&car.root.clone(), &root_cid)?.hash;
+        Ok(car)
+    }
+
+    /// Root CID of the archive
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Number of blocks in the archive
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Whether the archive holds a block for `hash`, in any multibase
+    pub fn contains(&self, hash: &str) -> bool {
+        parse_cid(hash).is_ok_and(|cid| self.index.contains_key(&cid))
+    }
+
+    /// Read a leaf and verify it hashes to its CID
+    pub fn get_leaf(&mut self, hash: &str) -> Result<DagLeaf> {
+        let cid = parse_cid(hash)?;
+        let leaf = self.read_leaf(hash, &cid)?;
+        if cid == self.root_cid {
+            leaf.verify_root_leaf()?;
+        } else {
+            leaf.verify_leaf()?;
+        }
+        leaf.verify_content_fields()?;
+        leaf.verify_content_hash()?;
+
+        Ok(leaf)
+    }
+
+    /// Read the block stored under `cid`, unverified
+    fn read_leaf(&mut self, hash: &str, cid: &Cid) -> Result<DagLeaf> {
+        let location = *self
+            .index
+            .get(cid)
+            .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
+
+        self.reader.seek(SeekFrom::Start(location.offset))?;
+        let data = read_section(&mut self.reader, location.len)?;
+
+        DagLeaf::from_block_bytes(hash, &data)
+    }
+
+    /// Read a file's whole content, reading and verifying each chunk
+    pub fn read_file(&mut self, hash: &str) -> Result<Vec<u8>> {
+        let leaf = self.get_leaf(hash)?;
+        if leaf.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "{} is not a file leaf",
+                hash
+            )));
+        }
+
+        if leaf.links.is_empty() {
+            return Ok(leaf.content.unwrap_or_default());
+        }
+
+        // Chunks in order, looking through link groups
+        let mut content = Vec::new();
+        let mut pending: Vec<String> = leaf.links.iter().rev().cloned().collect();
+        while let Some(link) = pending.pop() {
+            let chunk = self.get_leaf(&link)?;
+            if chunk.is_link_group() {
+                pending.extend(chunk.links.iter().rev().cloned());
+                continue;
+            }
+            let chunk_content = chunk
+                .content
+                .ok_or_else(|| ScionicError::InvalidLeaf("Chunk has no content".to_string()))?;
+            content.extend_from_slice(&chunk_content);
+        }
+
+        Ok(content)
+    }
+}
+
+impl Dag {
+    /// Open a CAR file for lazy, verified leaf reads
+    pub fn open_from_car(path: impl AsRef<Path>) -> Result<CarDag<BufReader<File>>> {
+        CarDag::open(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DagBuilderConfig;
+
+    #[test]
+    fn test_varint_roundtrip() -> Result<()> {
+        for value in [0u64, 1, 127, 128, 300, 16384, u32::MAX as u64, u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value)?;
+            assert_eq!(read_varint(&mut buf.as_slice())?, Some(value));
+        }
+
+        assert_eq!(read_varint(&mut [].as_slice())?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversize_lengths_are_rejected() -> Result<()> {
+        let dag = crate::create_dag_from_bytes("file.txt", b"content", DagBuilderConfig::new())?;
+        let car = dag.to_car()?;
+
+        // A header claiming far more than it holds
+        let mut huge_header = Vec::new();
+        write_varint(&mut huge_header, u64::MAX >> 1)?;
+        huge_header.extend_from_slice(&car[1..]);
+        assert!(matches!(
+            Dag::from_car(&huge_header),
+            Err(ScionicError::Deserialization(_))
+        ));
+
+        // A section claiming far more than the archive holds
+        // (the header is short enough for a one-byte length)
+        let mut huge_section = Vec::new();
+        let header_len = car[0] as usize + 1;
+        huge_section.extend_from_slice(&car[..header_len]);
+        write_varint(&mut huge_section, u64::MAX >> 1)?;
+        huge_section.extend_from_slice(&dag.root_cid()?.to_bytes());
+        assert!(matches!(
+            Dag::from_car(&huge_section),
+            Err(ScionicError::Deserialization(_))
+        ));
+        assert!(matches!(
+            CarDag::open(std::io::Cursor::new(huge_section)),
+            Err(ScionicError::Deserialization(_))
+        ));
+
+        Ok(())
+    }
+}
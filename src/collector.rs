@@ -0,0 +1,203 @@
+//! Collecting a file's chunks as they arrive
+//!
+//! [`ChunkCollector`] checks each chunk against its content hash as soon as
+//! it lands, so chunks can be downloaded concurrently and in any order, and
+//! hands out the file once every chunk is in.
+
+use crate::error::{Result, ScionicError};
+use crate::types::{Dag, HashAlgorithm, LeafType};
+use std::io::{self, Read};
+
+/// Verifies and gathers the chunks of one file leaf
+#[derive(Debug, Clone)]
+pub struct ChunkCollector {
+    /// Expected content hash and hash algorithm of each chunk, in order
+    expected: Vec<(Vec<u8>, HashAlgorithm)>,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+impl ChunkCollector {
+    /// Collector for the file leaf `file_hash` of `dag`
+    ///
+    /// Only the chunk leaves' content hashes are needed, not their content.
+    /// A file stored inline counts as a single chunk.
+    pub fn new(dag: &Dag, file_hash: &str) -> Result<Self> {
+        let file = dag
+            .leaves
+            .get(file_hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(file_hash.to_string()))?;
+        if file.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is not a file leaf",
+                file_hash
+            )));
+        }
+
+        let leaves = if file.links.is_empty() {
+            vec![file]
+        } else {
+            dag.linked_leaves(file)?
+        };
+
+        let expected = leaves
+            .into_iter()
+            .map(|leaf| {
+                let content_hash = leaf.content_hash.clone().ok_or_else(|| {
+                    ScionicError::InvalidLeaf(format!("{} has no content hash", leaf.hash))
+                })?;
+                Ok((content_hash, leaf.hash_algorithm()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            chunks: vec![None; expected.len()],
+            expected,
+            received: 0,
+        })
+    }
+
+    /// Number of chunks the file has
+    pub fn len(&self) -> usize {
+        self.expected.len()
+    }
+
+    /// Whether the file has no chunks at all
+    pub fn is_empty(&self) -> bool {
+        self.expected.is_empty()
+    }
+
+    /// Verify and store the chunk at `index`
+    ///
+    /// A chunk that doesn't match its content hash is rejected with
+    /// `ContentHashMismatch` and can be retried. Adding a chunk again is a
+    /// no-op.
+    pub fn add(&mut self, index: usize, data: Vec<u8>) -> Result<()> {
+        let (content_hash, algorithm) = self.expected.get(index).ok_or_else(|| {
+            ScionicError::InvalidDag(format!(
+                "Chunk index {} out of range for {} chunks",
+                index,
+                self.expected.len()
+            ))
+        })?;
+
+        if algorithm.digest(&data) != *content_hash {
+            return Err(ScionicError::ContentHashMismatch);
+        }
+
+        if self.chunks[index].is_none() {
+            self.chunks[index] = Some(data);
+            self.received += 1;
+        }
+        Ok(())
+    }
+
+    /// Whether every chunk has been received
+    pub fn is_complete(&self) -> bool {
+        self.received == self.expected.len()
+    }
+
+    /// Indices of the chunks still missing, in order
+    pub fn missing(&self) -> Vec<usize> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The assembled file, once every chunk is in
+    pub fn into_reader(self) -> Result<impl Read> {
+        if !self.is_complete() {
+            return Err(ScionicError::InvalidDag(format!(
+                "{} of {} chunks missing",
+                self.expected.len() - self.received,
+                self.expected.len()
+            )));
+        }
+
+        Ok(ChunkReader {
+            chunks: self.chunks.into_iter().flatten().collect(),
+            index: 0,
+            offset: 0,
+        })
+    }
+}
+
+/// Reads owned chunks back to back
+struct ChunkReader {
+    chunks: Vec<Vec<u8>>,
+    index: usize,
+    offset: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(chunk) = self.chunks.get(self.index) {
+            let remaining = &chunk[self.offset..];
+            if remaining.is_empty() {
+                self.index += 1;
+                self.offset = 0;
+                continue;
+            }
+
+            let n = remaining.len().min(buf.len());
+            buf[..n].copy_from_slice(&remaining[..n]);
+            self.offset += n;
+            return Ok(n);
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_chunks_out_of_order() -> Result<()> {
+        let content: Vec<u8> = (0..100u8).collect();
+        let dag = Dag::builder()
+            .with_chunk_size(16)
+            .add_file("data.bin", content.clone())
+            .build()?;
+        let file_hash = dag
+            .leaves
+            .values()
+            .find(|leaf| leaf.item_name == "data.bin")
+            .map(|leaf| leaf.hash.clone())
+            .unwrap();
+
+        let mut collector = ChunkCollector::new(&dag, &file_hash)?;
+        assert_eq!(collector.len(), 7);
+
+        let chunks: Vec<Vec<u8>> = content.chunks(16).map(<[u8]>::to_vec).collect();
+        for index in [6, 2, 0, 5, 3, 1] {
+            collector.add(index, chunks[index].clone())?;
+        }
+
+        // A corrupted chunk is rejected and leaves the collector incomplete
+        let mut corrupted = chunks[4].clone();
+        corrupted[0] ^= 0xff;
+        assert!(matches!(
+            collector.add(4, corrupted),
+            Err(ScionicError::ContentHashMismatch)
+        ));
+        assert!(collector.add(9, chunks[4].clone()).is_err());
+        assert!(!collector.is_complete());
+        assert_eq!(collector.missing(), vec![4]);
+        assert!(collector.clone().into_reader().is_err());
+
+        collector.add(4, chunks[4].clone())?;
+        collector.add(4, chunks[4].clone())?;
+        assert!(collector.is_complete());
+
+        let mut assembled = Vec::new();
+        collector.into_reader()?.read_to_end(&mut assembled)?;
+        assert_eq!(assembled, content);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,26 @@
 use crate::error::{Result, ScionicError};
-use crate::types::{Dag, DagBuilderConfig, DagLeaf, DagLeafBuilder, LeafType, DEFAULT_CHUNK_SIZE};
-use std::collections::HashMap;
+use crate::leaf::parse_cid;
+use crate::merkle_tree::{merkle_root_for_links_with, verify_proof_with};
+use crate::types::{
+    ChunkNaming, ChunkParams, CreateDirectoryMode, Dag, DagBuilderConfig, DagKind, DagLeaf,
+    DagLeafBuilder, LeafType, SymlinkMode, VerifyOptions, VerifyProgress, CHUNK_NAMING_KEY,
+    CHUNK_SIZE_KEY, COALESCE_SMALL_TAIL_KEY, CRLF_FILES_KEY, DEFAULT_CHUNK_SIZE, ENTRY_COUNT_KEY,
+    FILE_SIZE_KEY, LINE_ENDINGS_KEY, LINK_GROUP_KEY, TIMESTAMP_KEY,
+};
+use cid::Cid;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Bytes read at a time from a reader that is read in full, so the size
+/// limits are checked as it goes
+const READ_BLOCK_SIZE: u64 = 64 * 1024;
 
 /// Create a DAG from a file or directory
 pub fn create_dag(path: impl AsRef<Path>, timestamp_root: bool) -> Result<Dag> {
@@ -15,7 +33,7 @@ pub fn create_dag(path: impl AsRef<Path>, timestamp_root: bool) -> Result<Dag> {
         let timestamp = chrono::Utc::now().to_rfc3339();
         config
             .additional_data
-            .insert("timestamp".to_string(), timestamp);
+            .insert(TIMESTAMP_KEY.to_string(), timestamp);
     }
 
     create_dag_with_config(path, config)
@@ -29,18 +47,108 @@ pub fn create_dag_with_config(path: impl AsRef<Path>, config: DagBuilderConfig)
         return Err(ScionicError::PathNotFound(path.display().to_string()));
     }
 
+    if config.enable_parallel && config.max_workers > 0 {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.max_workers)
+            .build()
+            .map_err(|e| ScionicError::Io(std::io::Error::other(e)))?;
+        return pool.install(|| build_from_path(path, &config));
+    }
+
+    build_from_path(path, &config)
+}
+
+/// Create one DAG per path, building them concurrently over a shared
+/// thread pool
+///
+/// Results are returned in the order of `paths`, so one failing input
+/// doesn't affect the others. The pool has `max_workers` threads if set,
+/// otherwise one per CPU, and also runs each build's own parallel work when
+/// `enable_parallel` is on.
+pub fn create_dags<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    config: DagBuilderConfig,
+) -> Vec<Result<Dag>> {
+    let build = |path: &P| {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(ScionicError::PathNotFound(path.display().to_string()));
+        }
+        build_from_path(path, &config)
+    };
+
+    match ThreadPoolBuilder::new()
+        .num_threads(config.max_workers)
+        .build()
+    {
+        Ok(pool) => pool.install(|| paths.par_iter().map(build).collect()),
+        Err(e) => paths
+            .iter()
+            .map(|_| Err(ScionicError::Io(std::io::Error::other(e.to_string()))))
+            .collect(),
+    }
+}
+
+/// Create a DAG for a single file from content already in memory
+///
+/// Chunked as [`create_dag_with_config`] would a file named `name` on disk,
+/// so the root matches for the same name, content and config.
+pub fn create_dag_from_bytes(name: &str, data: &[u8], config: DagBuilderConfig) -> Result<Dag> {
+    if name.is_empty() {
+        return Err(ScionicError::InvalidName("Empty file name".to_string()));
+    }
+
     let mut builder = DagBuilder::new();
+    builder.count_file(Path::new(name), data.len() as u64, &config)?;
+    let leaf = build_file_leaf(name, data.to_vec(), &mut builder, &config)?;
+    finish_dag(leaf, builder, &config)
+}
+
+fn build_from_path(path: &Path, config: &DagBuilderConfig) -> Result<Dag> {
+    let mut builder = DagBuilder::with_capacity(config.leaf_capacity_hint.unwrap_or(0));
     let metadata = fs::metadata(path)?;
 
     let root_leaf = if metadata.is_dir() {
-        process_directory(path, path, &mut builder, true, &config)?
+        process_directory(path, path, &mut builder, true, config)?
     } else {
-        process_file(path, path, &mut builder, true, &config)?
+        process_file(path, path, &mut builder, true, config)?
     };
 
+    finish_dag(root_leaf, builder, config)
+}
+
+/// Rebuild the top-level leaf as the root and assemble the DAG
+pub(crate) fn finish_dag(
+    root_leaf: DagLeaf,
+    mut builder: DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<Dag> {
+    let (root_builder, additional_data) =
+        root_leaf_builder(root_leaf, config, &builder.crlf_files());
+    let root = root_builder.build_root_leaf(&builder.leaves, additional_data)?;
+
+    builder.leaves.insert(root.hash.clone(), root.clone());
+
+    Ok(Dag {
+        root: root.hash,
+        leaves: builder.leaves,
+        labels: None,
+        kind: Some(DagKind::Full),
+    })
+}
+
+/// Builder for the root leaf rebuilt from the top-level leaf, with the root's
+/// additional data
+pub(crate) fn root_leaf_builder(
+    root_leaf: DagLeaf,
+    config: &DagBuilderConfig,
+    crlf_files: &BTreeSet<String>,
+) -> (DagLeafBuilder, Option<BTreeMap<String, String>>) {
     // Build root leaf with metadata
-    let root_builder =
-        DagLeafBuilder::new(root_leaf.item_name.clone()).set_type(root_leaf.leaf_type.clone());
+    let root_builder = DagLeafBuilder::new(root_leaf.item_name.clone())
+        .set_type(root_leaf.leaf_type.clone())
+        .set_hash_algorithm(config.hash_algorithm)
+        .set_cid_base(config.cid_base);
 
     let root_builder = if let Some(content) = root_leaf.content {
         root_builder.set_data(content)
@@ -53,21 +161,84 @@ pub fn create_dag_with_config(path: impl AsRef<Path>, config: DagBuilderConfig)
         .iter()
         .fold(root_builder, |builder, link| builder.add_link(link.clone()));
 
-    let additional_data = if config.additional_data.is_empty() {
-        None
+    // Leaf-level metadata (e.g. entry counts) is kept alongside the config's,
+    // and wins over user data under the same key
+    let mut additional_data = config.additional_data.clone();
+    additional_data.extend(root_leaf.additional_data.unwrap_or_default());
+    if config.record_chunk_params {
+        let params = ChunkParams::from_config(config);
+        additional_data.insert(CHUNK_SIZE_KEY.to_string(), params.chunk_size.to_string());
+        additional_data.insert(
+            COALESCE_SMALL_TAIL_KEY.to_string(),
+            params.coalesce_small_tail.to_string(),
+        );
+    }
+    // A naming scheme other than the default is always recorded, since
+    // chunk names can't be told apart from the leaves alone
+    if config.record_chunk_params || config.chunk_naming != ChunkNaming::default() {
+        additional_data.insert(
+            CHUNK_NAMING_KEY.to_string(),
+            config.chunk_naming.as_str().to_string(),
+        );
+    }
+    if config.normalize_line_endings {
+        additional_data.insert(LINE_ENDINGS_KEY.to_string(), "lf".to_string());
+    }
+
+    (
+        root_builder,
+        with_crlf_files(Some(additional_data), crlf_files),
+    )
+}
+
+/// Copy of the root's `additional_data` listing `crlf_files`, or without
+/// the list if it's empty
+pub(crate) fn with_crlf_files(
+    additional_data: Option<BTreeMap<String, String>>,
+    crlf_files: &BTreeSet<String>,
+) -> Option<BTreeMap<String, String>> {
+    let mut data = additional_data.unwrap_or_default();
+    if crlf_files.is_empty() {
+        data.remove(CRLF_FILES_KEY);
     } else {
-        Some(config.additional_data.clone())
-    };
+        let list = serde_json::to_string(crlf_files).expect("string lists serialize");
+        data.insert(CRLF_FILES_KEY.to_string(), list);
+    }
 
-    let root = root_builder.build_root_leaf(&builder.leaves, additional_data)?;
+    if data.is_empty() {
+        None
+    } else {
+        Some(data)
+    }
+}
 
-    builder.leaves.insert(root.hash.clone(), root.clone());
+/// Item name for the root: the configured override, else the path's file name
+pub(crate) fn root_item_name(
+    path: &Path,
+    config: &DagBuilderConfig,
+    fallback: &str,
+) -> Result<String> {
+    match (&config.root_name, path.file_name()) {
+        (Some(name), _) => Ok(name.clone()),
+        (None, Some(name)) => utf8_name(name, path, config),
+        (None, None) => Ok(fallback.to_string()),
+    }
+}
 
-    Ok(Dag {
-        root: root.hash,
-        leaves: builder.leaves,
-        labels: None,
-    })
+/// A file name as an item name
+///
+/// Fails with the raw bytes of a name that isn't UTF-8 unless the config
+/// allows lossy names.
+pub(crate) fn utf8_name(name: &OsStr, path: &Path, config: &DagBuilderConfig) -> Result<String> {
+    match name.to_str() {
+        Some(name) => Ok(name.to_string()),
+        None if config.require_utf8_names => Err(ScionicError::InvalidName(format!(
+            "{} (raw bytes: b\"{}\")",
+            path.display(),
+            name.as_encoded_bytes().escape_ascii()
+        ))),
+        None => Ok(name.to_string_lossy().into_owned()),
+    }
 }
 
 /// Process a directory and create a DAG leaf
@@ -76,13 +247,10 @@ fn process_directory(
     base_path: &Path,
     builder: &mut DagBuilder,
     is_root: bool,
-    _config: &DagBuilderConfig,
+    config: &DagBuilderConfig,
 ) -> Result<DagLeaf> {
     let rel_path = if is_root {
-        path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("root")
-            .to_string()
+        root_item_name(path, config, "root")?
     } else {
         path.strip_prefix(base_path)
             .map_err(|_| ScionicError::InvalidDag("Invalid path".to_string()))?
@@ -90,44 +258,212 @@ fn process_directory(
             .to_string()
     };
 
-    let mut leaf_builder = DagLeafBuilder::new(rel_path).set_type(LeafType::Directory);
-
     // Read directory entries
     let mut entries: Vec<_> = fs::read_dir(path)?.filter_map(|e| e.ok()).collect();
 
-    // Sort for deterministic ordering
+    // Sort for deterministic ordering by the raw names (byte order on Unix),
+    // which for UTF-8 names is the same as sorting the item names
     entries.sort_by_key(|e| e.file_name());
 
+    let mut classified = Vec::with_capacity(entries.len());
     for entry in entries {
         let entry_path = entry.path();
-        let metadata = entry.metadata()?;
-
-        // IMPORTANT: Keep base_path constant for all recursion
-        let child_leaf = if metadata.is_dir() {
-            process_directory(
-                &entry_path,
-                if is_root { path } else { base_path },
-                builder,
-                false,
-                _config,
-            )?
-        } else {
-            process_file(
-                &entry_path,
-                if is_root { path } else { base_path },
-                builder,
-                false,
-                _config,
-            )?
-        };
+        if let Some(kind) = classify_entry(&entry_path, config)? {
+            // Item names should be UTF-8; a lossy conversion doesn't
+            // round-trip and could give two distinct entries the same name
+            utf8_name(&entry.file_name(), &entry_path, config)?;
+            classified.push((entry_path, kind));
+        }
+    }
+    let entries = classified;
+
+    // IMPORTANT: Keep base_path constant for all recursion
+    let child_base = if is_root { path } else { base_path };
+    let child_leaves = if config.enable_parallel {
+        // Each entry builds into its own leaf map; the maps and leaves are
+        // merged back in entry order, so scheduling can't affect the result
+        entries
+            .par_iter()
+            .map(|(entry_path, kind)| {
+                let mut local = builder.fork();
+                let leaf = process_entry(entry_path, kind, child_base, &mut local, config)?;
+                Ok((leaf, local))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .map(|(leaf, local)| {
+                builder.leaves.extend(local.leaves);
+                leaf
+            })
+            .collect()
+    } else {
+        entries
+            .iter()
+            .map(|(entry_path, kind)| process_entry(entry_path, kind, child_base, builder, config))
+            .collect::<Result<Vec<_>>>()?
+    };
 
+    let mut child_hashes = Vec::with_capacity(child_leaves.len());
+    for child_leaf in child_leaves {
         builder
             .leaves
             .insert(child_leaf.hash.clone(), child_leaf.clone());
-        leaf_builder = leaf_builder.add_link(child_leaf.hash);
+        child_hashes.push(child_leaf.hash);
+    }
+
+    build_directory_leaf(rel_path, child_hashes, builder, config)
+}
+
+/// Recreate a symlink leaf at `path`
+fn create_symlink(leaf: &DagLeaf, path: &Path) -> Result<()> {
+    let target = leaf.symlink_target()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, path)?;
+    #[cfg(windows)]
+    {
+        // Windows needs to know the kind of target; resolve it from the link's
+        // directory, defaulting to a file link for dangling targets
+        let resolved = path.parent().unwrap_or(Path::new("")).join(target);
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, path)?;
+        } else {
+            std::os::windows::fs::symlink_file(target, path)?;
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    return Err(ScionicError::InvalidLeaf(format!(
+        "Symlinks are not supported on this platform: {}",
+        leaf.item_name
+    )));
+
+    #[cfg(any(unix, windows))]
+    Ok(())
+}
+
+/// What a directory entry is stored as
+pub(crate) enum EntryKind {
+    Directory,
+    File,
+    /// A symlink stored as a link, with its target
+    Symlink(String),
+}
+
+/// Classify a directory entry under the config's symlink mode, or `None` if
+/// it's left out
+pub(crate) fn classify_entry(path: &Path, config: &DagBuilderConfig) -> Result<Option<EntryKind>> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.file_type().is_symlink() {
+        return Ok(Some(if metadata.is_dir() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        }));
+    }
+
+    match config.symlink_mode {
+        SymlinkMode::Skip => Ok(None),
+        SymlinkMode::StoreAsLink => {
+            let target = fs::read_link(path)?;
+            Ok(Some(EntryKind::Symlink(utf8_name(
+                target.as_os_str(),
+                path,
+                config,
+            )?)))
+        }
+        SymlinkMode::Follow => {
+            if !fs::metadata(path)?.is_dir() {
+                return Ok(Some(EntryKind::File));
+            }
+
+            // Following a link to an enclosing directory would never end
+            let target = fs::canonicalize(path)?;
+            if let Some(parent) = path.parent() {
+                if fs::canonicalize(parent)?.starts_with(&target) {
+                    return Err(ScionicError::InvalidDag(format!(
+                        "Symlink {} points to an enclosing directory",
+                        path.display()
+                    )));
+                }
+            }
+            Ok(Some(EntryKind::Directory))
+        }
+    }
+}
+
+/// Process one directory entry, a file, a subdirectory or a stored symlink
+fn process_entry(
+    entry_path: &Path,
+    kind: &EntryKind,
+    base_path: &Path,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<DagLeaf> {
+    match kind {
+        EntryKind::Directory => process_directory(entry_path, base_path, builder, false, config),
+        EntryKind::File => process_file(entry_path, base_path, builder, false, config),
+        EntryKind::Symlink(target) => {
+            let rel_path = entry_path
+                .strip_prefix(base_path)
+                .map_err(|_| ScionicError::InvalidDag("Invalid path".to_string()))?
+                .to_string_lossy()
+                .to_string();
+            build_symlink_leaf(rel_path, target, config)
+        }
     }
+}
+
+/// Build a symlink leaf whose content is the link's target path
+pub(crate) fn build_symlink_leaf(
+    rel_path: String,
+    target: &str,
+    config: &DagBuilderConfig,
+) -> Result<DagLeaf> {
+    DagLeafBuilder::new(rel_path)
+        .set_type(LeafType::Symlink)
+        .set_hash_algorithm(config.hash_algorithm)
+        .set_cid_base(config.cid_base)
+        .set_data(target.as_bytes().to_vec())
+        .build_leaf(None)
+}
 
-    leaf_builder.build_leaf(None)
+/// Build a directory leaf linking to already-built children, adding any
+/// link groups to the builder
+pub(crate) fn build_directory_leaf(
+    rel_path: String,
+    child_hashes: Vec<String>,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<DagLeaf> {
+    // Entries are counted before they are split under link groups
+    let additional_data = if config.include_entry_counts {
+        let mut data = BTreeMap::new();
+        data.insert(ENTRY_COUNT_KEY.to_string(), child_hashes.len().to_string());
+        Some(data)
+    } else {
+        None
+    };
+    let child_hashes = group_links(
+        &rel_path,
+        LeafType::Directory,
+        child_hashes,
+        builder,
+        config,
+    )?;
+
+    child_hashes
+        .into_iter()
+        .fold(
+            DagLeafBuilder::new(rel_path)
+                .set_type(LeafType::Directory)
+                .set_hash_algorithm(config.hash_algorithm)
+                .set_cid_base(config.cid_base),
+            |builder, hash| builder.add_link(hash),
+        )
+        .build_leaf(additional_data)
 }
 
 /// Process a file and create a DAG leaf (with chunking if needed)
@@ -139,10 +475,7 @@ fn process_file(
     config: &DagBuilderConfig,
 ) -> Result<DagLeaf> {
     let rel_path = if is_root {
-        path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("file")
-            .to_string()
+        root_item_name(path, config, "file")?
     } else {
         path.strip_prefix(base_path)
             .map_err(|_| ScionicError::InvalidDag("Invalid path".to_string()))?
@@ -150,47 +483,496 @@ fn process_file(
             .to_string()
     };
 
-    let data = fs::read(path)?;
-    let mut leaf_builder = DagLeafBuilder::new(rel_path.clone()).set_type(LeafType::File);
+    builder.count_file(path, fs::metadata(path)?.len(), config)?;
+    let data = read_file(path)?;
+    build_file_leaf(&rel_path, data, builder, config)
+}
+
+/// Read a whole file, failing if its size changes while it is being read
+pub(crate) fn read_file(path: &Path) -> Result<Vec<u8>> {
+    let file = fs::File::open(path)?;
+    let expected = file.metadata()?.len();
+    read_expected_size(file, path, expected)
+}
+
+/// Read exactly `expected` bytes, reporting a changed file if the reader
+/// ends early or has more data
+fn read_expected_size<R: Read>(reader: R, path: &Path, expected: u64) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(expected as usize);
+    // One extra byte is enough to notice that the file grew
+    reader.take(expected + 1).read_to_end(&mut data)?;
+
+    if data.len() as u64 != expected {
+        return Err(ScionicError::FileChanged {
+            path: path.display().to_string(),
+            expected,
+            got: data.len() as u64,
+        });
+    }
+
+    Ok(data)
+}
+
+/// Build a file leaf from its content, adding any chunk leaves to the builder
+pub(crate) fn build_file_leaf(
+    rel_path: &str,
+    data: Vec<u8>,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<DagLeaf> {
+    let data = if config.normalize_line_endings {
+        let (data, had_crlf) = normalize_line_endings(data);
+        if had_crlf {
+            builder.record_crlf_file(rel_path);
+        }
+        data
+    } else {
+        data
+    };
+    let leaf_builder = DagLeafBuilder::new(rel_path)
+        .set_type(LeafType::File)
+        .set_hash_algorithm(config.hash_algorithm)
+        .set_cid_base(config.cid_base);
+    let additional_data = file_additional_data(data.len() as u64, config);
 
     // Determine chunk size to use
     let chunk_size = config.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
 
     // Chunk the file if it's larger than the chunk size (and chunking is enabled)
     if chunk_size > 0 && data.len() > chunk_size {
-        let chunks: Vec<_> = data.chunks(chunk_size).collect();
+        let chunks = split_chunks(&data, chunk_size, config.coalesce_small_tail);
+
+        let chunk_hashes = if config.enable_parallel {
+            // Hash chunks in parallel, keeping their order for the links
+            chunks
+                .par_iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let mut local = DagBuilder::new();
+                    let hash = build_chunk_leaf(rel_path, i, chunk.to_vec(), &mut local, config)?;
+                    Ok((hash, local))
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .map(|(hash, local)| {
+                    builder.leaves.extend(local.leaves);
+                    hash
+                })
+                .collect()
+        } else {
+            let mut chunk_hashes = Vec::with_capacity(chunks.len());
+            for (i, chunk) in chunks.iter().enumerate() {
+                chunk_hashes.push(build_chunk_leaf(
+                    rel_path,
+                    i,
+                    chunk.to_vec(),
+                    builder,
+                    config,
+                )?);
+            }
+            chunk_hashes
+        };
+
+        group_links(rel_path, LeafType::File, chunk_hashes, builder, config)?
+            .into_iter()
+            .fold(leaf_builder, |leaf_builder, link| {
+                leaf_builder.add_link(link)
+            })
+            .build_leaf(additional_data)
+    } else {
+        leaf_builder.set_data(data).build_leaf(additional_data)
+    }
+}
 
-        for (i, chunk) in chunks.iter().enumerate() {
-            // Use path-based naming to match Go's sequential implementation
-            let chunk_name = format!("{}/{}", rel_path, i);
-            let chunk_leaf = DagLeafBuilder::new(chunk_name)
-                .set_type(LeafType::Chunk)
-                .set_data(chunk.to_vec())
-                .build_leaf(None)?;
+/// Convert CRLF to LF if `data` looks like text: valid UTF-8 without NUL
+/// bytes, returning whether anything was converted
+///
+/// Text that also has bare LFs is left as is, since converting every LF back
+/// to CRLF couldn't restore it.
+fn normalize_line_endings(data: Vec<u8>) -> (Vec<u8>, bool) {
+    let is_text = !data.contains(&0) && std::str::from_utf8(&data).is_ok();
+    let has_bare_lf = data
+        .iter()
+        .enumerate()
+        .any(|(i, &byte)| byte == b'\n' && (i == 0 || data[i - 1] != b'\r'));
+    if !is_text || has_bare_lf || !data.windows(2).any(|pair| pair == b"\r\n") {
+        return (data, false);
+    }
 
-            builder
-                .leaves
-                .insert(chunk_leaf.hash.clone(), chunk_leaf.clone());
-            leaf_builder = leaf_builder.add_link(chunk_leaf.hash);
+    let mut normalized = Vec::with_capacity(data.len());
+    let mut bytes = data.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if byte != b'\r' || bytes.peek() != Some(&&b'\n') {
+            normalized.push(byte);
+        }
+    }
+    (normalized, true)
+}
+
+/// Build a file leaf from a reader, chunking the content as it is read
+///
+/// Produces the same leaves as [`build_file_leaf`] on the whole content,
+/// while holding at most two chunks in memory. Normalizing line endings
+/// needs the whole file, so it's read in full then. The bytes count
+/// towards the config's size limits as they are read, and reading stops
+/// once one is broken.
+pub(crate) fn build_file_leaf_from_reader<R: Read>(
+    rel_path: &str,
+    mut reader: R,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<DagLeaf> {
+    let path = Path::new(rel_path);
+    let chunk_size = config.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 || config.normalize_line_endings {
+        let mut data = Vec::new();
+        loop {
+            let len = (&mut reader).take(READ_BLOCK_SIZE).read_to_end(&mut data)?;
+            if len == 0 {
+                break;
+            }
+            builder.count_read(path, data.len() as u64, len as u64, config)?;
         }
+        return build_file_leaf(rel_path, data, builder, config);
+    }
+
+    let leaf_builder = DagLeafBuilder::new(rel_path)
+        .set_type(LeafType::File)
+        .set_hash_algorithm(config.hash_algorithm)
+        .set_cid_base(config.cid_base);
+    let mut chunk_hashes = Vec::new();
+    let mut size = 0u64;
+    let mut emitted = 0;
+
+    // The last two chunks are held back: the file may turn out to fit in
+    // one chunk, or its tail may need coalescing
+    let mut held: Vec<Vec<u8>> = Vec::with_capacity(3);
+    loop {
+        let mut chunk = Vec::with_capacity(chunk_size);
+        (&mut reader)
+            .take(chunk_size as u64)
+            .read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+        size += chunk.len() as u64;
+        builder.count_read(path, size, chunk.len() as u64, config)?;
+        held.push(chunk);
+
+        if held.len() > 2 {
+            let chunk_hash = build_chunk_leaf(rel_path, emitted, held.remove(0), builder, config)?;
+            chunk_hashes.push(chunk_hash);
+            emitted += 1;
+        }
+    }
+
+    let additional_data = file_additional_data(size, config);
+
+    if emitted == 0 && held.len() <= 1 {
+        let data = held.pop().unwrap_or_default();
+        return leaf_builder.set_data(data).build_leaf(additional_data);
+    }
+
+    if held.len() == 2 && held[1].len() < tail_threshold(chunk_size, config.coalesce_small_tail) {
+        let tail = held.pop().unwrap_or_default();
+        held[0].extend(tail);
+    }
+    for chunk in held {
+        let chunk_hash = build_chunk_leaf(rel_path, emitted, chunk, builder, config)?;
+        chunk_hashes.push(chunk_hash);
+        emitted += 1;
+    }
+
+    group_links(rel_path, LeafType::File, chunk_hashes, builder, config)?
+        .into_iter()
+        .fold(leaf_builder, |leaf_builder, link| {
+            leaf_builder.add_link(link)
+        })
+        .build_leaf(additional_data)
+}
+
+/// Group `links` under intermediate leaves until at most
+/// `max_links_per_leaf` remain, adding the groups to the builder
+///
+/// Each level splits its links into the fewest groups that fit, as evenly
+/// as possible. Groups take the parent's name and type and keep the links
+/// in order.
+pub(crate) fn group_links(
+    item_name: &str,
+    leaf_type: LeafType,
+    mut links: Vec<String>,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<Vec<String>> {
+    let max = config.max_links_per_leaf;
+    if max == 0 || links.len() <= max {
+        return Ok(links);
+    }
+    if max < 2 {
+        return Err(ScionicError::InvalidDag(
+            "max_links_per_leaf must be at least 2".to_string(),
+        ));
+    }
+
+    let mut additional_data = BTreeMap::new();
+    additional_data.insert(LINK_GROUP_KEY.to_string(), "true".to_string());
+
+    while links.len() > max {
+        let group_size = links.len().div_ceil(links.len().div_ceil(max));
+        links = links
+            .chunks(group_size)
+            .map(|group| {
+                let leaf = group
+                    .iter()
+                    .fold(
+                        DagLeafBuilder::new(item_name)
+                            .set_type(leaf_type.clone())
+                            .set_hash_algorithm(config.hash_algorithm)
+                            .set_cid_base(config.cid_base),
+                        |leaf_builder, link| leaf_builder.add_link(link.clone()),
+                    )
+                    .build_leaf(Some(additional_data.clone()))?;
+                let hash = leaf.hash.clone();
+                builder.leaves.insert(hash.clone(), leaf);
+                Ok(hash)
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    Ok(links)
+}
 
-        leaf_builder.build_leaf(None)
+/// A file leaf's additional data, recording its size if configured
+fn file_additional_data(size: u64, config: &DagBuilderConfig) -> Option<BTreeMap<String, String>> {
+    if config.include_file_sizes {
+        let mut data_map = BTreeMap::new();
+        data_map.insert(FILE_SIZE_KEY.to_string(), size.to_string());
+        Some(data_map)
     } else {
-        leaf_builder.set_data(data).build_leaf(None)
+        None
+    }
+}
+
+/// Build the chunk leaf at `index` of a file, returning its hash
+///
+/// Empty chunks are never emitted; verification rejects them.
+fn build_chunk_leaf(
+    rel_path: &str,
+    index: usize,
+    data: Vec<u8>,
+    builder: &mut DagBuilder,
+    config: &DagBuilderConfig,
+) -> Result<String> {
+    if data.is_empty() {
+        return Err(ScionicError::InvalidLeaf("Empty chunk".to_string()));
+    }
+
+    // Path-based naming by default to match Go's sequential implementation
+    let chunk_name = config.chunk_naming.chunk_name(rel_path, index);
+    let chunk_leaf = DagLeafBuilder::new(chunk_name)
+        .set_type(LeafType::Chunk)
+        .set_hash_algorithm(config.hash_algorithm)
+        .set_cid_base(config.cid_base)
+        .set_data(data)
+        .build_leaf(None)?;
+
+    let hash = chunk_leaf.hash.clone();
+    builder.leaves.insert(hash.clone(), chunk_leaf);
+    Ok(hash)
+}
+
+/// Number of chunk leaves a file of `size` bytes is split into
+///
+/// Files no larger than the chunk size, and all files when chunking is
+/// disabled (`chunk_size` 0), are stored inline and have no chunks. Doesn't
+/// account for `coalesce_small_tail`.
+pub fn chunk_count_for(size: u64, chunk_size: usize) -> usize {
+    let chunk_size = chunk_size as u64;
+    if chunk_size == 0 || size <= chunk_size {
+        return 0;
+    }
+    size.div_ceil(chunk_size) as usize
+}
+
+/// Byte range `[start, end)` of the chunk at `index` in a file of `size`
+/// bytes, clamped to the end of the file
+///
+/// The inverse of [`chunk_count_for`], slicing the way the builder does.
+pub fn chunk_byte_range(index: usize, size: u64, chunk_size: usize) -> (u64, u64) {
+    let start = (index as u64).saturating_mul(chunk_size as u64).min(size);
+    let end = start.saturating_add(chunk_size as u64).min(size);
+    (start, end)
+}
+
+/// Split file content into chunks of `chunk_size` bytes
+///
+/// A trailing chunk shorter than `coalesce_small_tail * chunk_size` is merged
+/// into the chunk before it.
+fn split_chunks(data: &[u8], chunk_size: usize, coalesce_small_tail: f32) -> Vec<&[u8]> {
+    let size = data.len() as u64;
+    let mut chunks: Vec<&[u8]> = (0..chunk_count_for(size, chunk_size))
+        .map(|index| {
+            let (start, end) = chunk_byte_range(index, size, chunk_size);
+            &data[start as usize..end as usize]
+        })
+        .collect();
+
+    if chunks.len() > 1
+        && chunks[chunks.len() - 1].len() < tail_threshold(chunk_size, coalesce_small_tail)
+    {
+        chunks.pop();
+        let start = (chunks.len() - 1) * chunk_size;
+        *chunks.last_mut().unwrap() = &data[start..];
+    }
+
+    chunks
+}
+
+/// Length below which a trailing chunk is coalesced
+fn tail_threshold(chunk_size: usize, coalesce_small_tail: f32) -> usize {
+    (coalesce_small_tail.max(0.0) as f64 * chunk_size as f64) as usize
+}
+
+/// Failures collected while recreating a directory
+struct Failures {
+    best_effort: bool,
+    failures: Vec<(PathBuf, ScionicError)>,
+}
+
+impl Failures {
+    fn new(mode: CreateDirectoryMode) -> Self {
+        Self {
+            best_effort: mode == CreateDirectoryMode::BestEffort,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Returns whether `result` succeeded, collecting the error in
+    /// best-effort mode and propagating it otherwise
+    fn record(&mut self, path: &Path, result: Result<()>) -> Result<bool> {
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if self.best_effort => {
+                self.failures.push((path.to_path_buf(), e));
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
     }
 }
 
 /// Builder for constructing DAGs
 pub struct DagBuilder {
     pub leaves: HashMap<String, DagLeaf>,
+    /// Bytes of the files read so far, shared with forked builders
+    read_bytes: Arc<AtomicU64>,
+    /// Files whose CRLF line endings were normalized, shared likewise
+    crlf_files: Arc<Mutex<BTreeSet<String>>>,
 }
 
 impl DagBuilder {
     pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Builder whose leaf map holds `capacity` leaves without reallocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            leaves: HashMap::with_capacity(capacity),
+            read_bytes: Arc::default(),
+            crlf_files: Arc::default(),
+        }
+    }
+
+    /// Empty builder counting towards the same size limits, for building
+    /// part of the DAG on another thread
+    pub(crate) fn fork(&self) -> Self {
         Self {
             leaves: HashMap::new(),
+            read_bytes: Arc::clone(&self.read_bytes),
+            crlf_files: Arc::clone(&self.crlf_files),
+        }
+    }
+
+    /// Record that the file at `rel_path` had CRLF line endings normalized
+    pub(crate) fn record_crlf_file(&self, rel_path: &str) {
+        self.crlf_files.lock().unwrap().insert(rel_path.to_string());
+    }
+
+    /// The files recorded by [`record_crlf_file`](Self::record_crlf_file)
+    pub(crate) fn crlf_files(&self) -> BTreeSet<String> {
+        self.crlf_files.lock().unwrap().clone()
+    }
+
+    /// Count a file of `size` bytes about to be read, failing if it breaks
+    /// the config's size limits
+    pub(crate) fn count_file(
+        &self,
+        path: &Path,
+        size: u64,
+        config: &DagBuilderConfig,
+    ) -> Result<()> {
+        check_size_limits(path, size, &self.read_bytes, config)
+    }
+
+    /// Count `len` more bytes read of a file whose size isn't known up
+    /// front, `read` bytes so far including them, failing once the config's
+    /// size limits are broken
+    pub(crate) fn count_read(
+        &self,
+        path: &Path,
+        read: u64,
+        len: u64,
+        config: &DagBuilderConfig,
+    ) -> Result<()> {
+        check_read_limits(path, read, len, &self.read_bytes, config)
+    }
+}
+
+/// Add a file of `size` bytes to `read_bytes`, failing if it is over
+/// `max_file_size` or brings the total over `max_total_size`
+pub(crate) fn check_size_limits(
+    path: &Path,
+    size: u64,
+    read_bytes: &AtomicU64,
+    config: &DagBuilderConfig,
+) -> Result<()> {
+    check_read_limits(path, size, size, read_bytes, config)
+}
+
+/// Add `len` bytes of a file `size` bytes long so far to `read_bytes`,
+/// failing if the file is over `max_file_size` or the total over
+/// `max_total_size`
+fn check_read_limits(
+    path: &Path,
+    size: u64,
+    len: u64,
+    read_bytes: &AtomicU64,
+    config: &DagBuilderConfig,
+) -> Result<()> {
+    if let Some(max) = config.max_file_size {
+        if size > max {
+            return Err(ScionicError::InvalidDag(format!(
+                "{} is over the {} byte file size limit",
+                path.display(),
+                max
+            )));
+        }
+    }
+
+    let total = read_bytes
+        .fetch_add(len, Ordering::Relaxed)
+        .saturating_add(len);
+    if let Some(max) = config.max_total_size {
+        if total > max {
+            return Err(ScionicError::InvalidDag(format!(
+                "Input is over the {} byte total size limit at {}",
+                max,
+                path.display()
+            )));
         }
     }
+    Ok(())
 }
 
 impl Default for DagBuilder {
@@ -202,88 +984,554 @@ impl Default for DagBuilder {
 impl Dag {
     /// Verify the entire DAG
     pub fn verify(&self) -> Result<()> {
-        if self.is_partial() {
-            self.verify_with_proofs()
-        } else {
-            self.verify_full_dag()
-        }
+        self.verify_with(VerifyOptions::default())
     }
 
-    /// Check if this is a partial DAG
-    pub fn is_partial(&self) -> bool {
-        if let Some(root_leaf) = self.leaves.get(&self.root) {
-            if let Some(leaf_count) = root_leaf.leaf_count {
-                return self.leaves.len() < leaf_count;
-            }
-        }
-        true
+    /// Verify the DAG running only the checks enabled in `options`
+    pub fn verify_with(&self, options: VerifyOptions) -> Result<()> {
+        self.run_verification(options, &mut |_| {}, false)
     }
 
-    /// Verify a full DAG (all leaves present)
-    fn verify_full_dag(&self) -> Result<()> {
-        let root_leaf = self
-            .leaves
-            .get(&self.root)
-            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+    /// Verify the entire DAG, calling `progress` after each leaf is verified
+    pub fn verify_with_progress<F>(&self, mut progress: F) -> Result<()>
+    where
+        F: FnMut(VerifyProgress),
+    {
+        self.run_verification(VerifyOptions::default(), &mut progress, false)
+    }
 
-        // Verify root
-        root_leaf.verify_root_leaf()?;
+    /// Verify the DAG, hashing leaves in parallel on the global rayon pool
+    ///
+    /// Checks the same things as [`verify`](Self::verify).
+    pub fn verify_parallel(&self) -> Result<()> {
+        let options = VerifyOptions::default();
+        self.leaves
+            .par_iter()
+            .try_for_each(|(hash, leaf)| self.verify_single_leaf(hash, leaf, options))?;
 
-        // Verify all other leaves
-        for (hash, leaf) in &self.leaves {
-            if hash == &self.root {
-                continue;
-            }
+        self.run_verification(options, &mut |_| {}, true)
+    }
 
-            leaf.verify_leaf()?;
+    /// Verify the DAG in parallel on a dedicated pool of `max_threads`
+    /// threads (0 = one per CPU)
+    ///
+    /// Bounds the threads a single verification can occupy, e.g. on a server
+    /// running many at once.
+    pub fn verify_with_thread_limit(&self, max_threads: usize) -> Result<()> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(max_threads)
+            .build()
+            .map_err(|e| ScionicError::Io(std::io::Error::other(e)))?;
+
+        pool.install(|| self.verify_parallel())
+    }
 
-            // Verify parent-child relationships
-            if let Some(parent) = self.find_parent(hash) {
-                if !parent.has_link(hash) {
-                    return Err(ScionicError::InvalidDag(format!(
-                        "Parent {} does not link to child {}",
-                        parent.hash, hash
-                    )));
-                }
-            }
-        }
+    /// Verify the DAG and reject proofs where they don't belong
+    ///
+    /// Proofs only belong in partial DAGs, so a full DAG carrying any is
+    /// rejected. A partial DAG's proofs are already checked by
+    /// [`verify`](Self::verify).
+    pub fn verify_strict(&self) -> Result<()> {
+        self.verify()?;
+
+        if self.is_partial() {
+            return Ok(());
+        }
+        if let Some(leaf) = self
+            .leaves
+            .values()
+            .find(|leaf| leaf.proofs.as_ref().is_some_and(|p| !p.is_empty()))
+        {
+            return Err(ScionicError::InvalidDag(format!(
+                "Full DAG leaf {} carries proofs",
+                leaf.hash
+            )));
+        }
 
         Ok(())
     }
 
-    /// Verify a partial DAG using Merkle proofs
-    fn verify_with_proofs(&self) -> Result<()> {
+    /// The root hash parsed as a CID
+    pub fn root_cid(&self) -> Result<Cid> {
+        parse_cid(&self.root)
+    }
+
+    /// Verify the DAG and check that its root is the one the caller trusts
+    pub fn verify_as(&self, expected_root: &str) -> Result<()> {
+        if self.root != expected_root {
+            return Err(ScionicError::HashMismatch {
+                expected: expected_root.to_string(),
+                got: self.root.clone(),
+            });
+        }
+
+        self.verify()
+    }
+
+    /// Quick integrity gate that checks only the root leaf
+    ///
+    /// Verifies the root's CID, its link count and that it carries a leaf
+    /// count, without touching any other leaf.
+    pub fn verify_root(&self) -> Result<()> {
+        let root_leaf = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+
+        if root_leaf.hash != self.root {
+            return Err(ScionicError::HashMismatch {
+                expected: self.root.clone(),
+                got: root_leaf.hash.clone(),
+            });
+        }
+
+        if root_leaf.leaf_count.is_none() {
+            return Err(ScionicError::InvalidLeaf(
+                "Root leaf has no leaf count".to_string(),
+            ));
+        }
+
+        // Also checks current_link_count against the links
+        root_leaf.verify_root_leaf()
+    }
+
+    /// Whether this DAG is full or partial
+    ///
+    /// Uses the explicit marker when set (by building, [`Dag::get_partial`],
+    /// [`Dag::as_full`] or [`Dag::as_partial`]); otherwise, e.g. after
+    /// deserializing, infers it by comparing the leaves present with the
+    /// root's leaf count.
+    pub fn kind(&self) -> DagKind {
+        if let Some(kind) = self.kind {
+            return kind;
+        }
+
+        if let Some(root_leaf) = self.leaves.get(&self.root) {
+            if let Some(leaf_count) = root_leaf.leaf_count {
+                if self.leaves.len() >= leaf_count {
+                    return DagKind::Full;
+                }
+            }
+        }
+        DagKind::Partial
+    }
+
+    /// The chunking parameters recorded on the root, if it was built with
+    /// `record_chunk_params`
+    pub fn chunk_params(&self) -> Option<ChunkParams> {
+        let data = self.leaves.get(&self.root)?.additional_data.as_ref()?;
+        Some(ChunkParams {
+            chunk_size: data.get(CHUNK_SIZE_KEY)?.parse().ok()?,
+            chunk_naming: ChunkNaming::from_name(data.get(CHUNK_NAMING_KEY)?)?,
+            coalesce_small_tail: data.get(COALESCE_SMALL_TAIL_KEY)?.parse().ok()?,
+        })
+    }
+
+    /// How the DAG's chunk leaves are named, per the root
+    ///
+    /// Builds record any scheme other than
+    /// [`ChunkNaming::PathBased`], which is assumed when nothing is recorded.
+    pub fn chunk_naming(&self) -> ChunkNaming {
+        self.leaves
+            .get(&self.root)
+            .and_then(|root| root.additional_data.as_ref())
+            .and_then(|data| data.get(CHUNK_NAMING_KEY))
+            .and_then(|name| ChunkNaming::from_name(name))
+            .unwrap_or_default()
+    }
+
+    /// Whether text files were stored with CRLF converted to LF, per the
+    /// root's marker from `normalize_line_endings`
+    ///
+    /// The files that had CRLF are listed on the root rather than marked
+    /// on their own leaves, so identical text still gets one CID; see
+    /// [`crlf_files`](Self::crlf_files).
+    pub fn line_endings_normalized(&self) -> bool {
+        self.leaves
+            .get(&self.root)
+            .and_then(|root| root.additional_data.as_ref())
+            .and_then(|data| data.get(LINE_ENDINGS_KEY))
+            .is_some_and(|value| value == "lf")
+    }
+
+    /// Paths of the text files whose CRLF line endings were normalized, as
+    /// recorded on the root, relative to it as in item names
+    pub fn crlf_files(&self) -> BTreeSet<String> {
+        self.leaves
+            .get(&self.root)
+            .and_then(|root| root.additional_data.as_ref())
+            .and_then(|data| data.get(CRLF_FILES_KEY))
+            .and_then(|list| serde_json::from_str(list).ok())
+            .unwrap_or_default()
+    }
+
+    /// Assemble a DAG from its root hash, leaves and labels
+    ///
+    /// Fails with `MissingLeaf` if the root isn't among the leaves. Whether
+    /// the DAG is full or partial is inferred, as after deserializing.
+    pub fn from_parts(
+        root: String,
+        leaves: HashMap<String, DagLeaf>,
+        labels: Option<HashMap<String, String>>,
+    ) -> Result<Dag> {
+        if !leaves.contains_key(&root) {
+            return Err(ScionicError::MissingLeaf(root));
+        }
+
+        Ok(Dag {
+            root,
+            leaves,
+            labels,
+            kind: None,
+        })
+    }
+
+    /// An empty DAG for `root`, to be filled in with transmission packets
+    ///
+    /// See [`Dag::apply_and_verify_transmission_packet`].
+    pub fn receiving(root: impl Into<String>) -> Dag {
+        Dag {
+            root: root.into(),
+            leaves: HashMap::new(),
+            labels: None,
+            kind: None,
+        }
+    }
+
+    /// Take the DAG apart into its root hash, leaves and labels, without
+    /// copying them
+    pub fn into_parts(
+        self,
+    ) -> (
+        String,
+        HashMap<String, DagLeaf>,
+        Option<HashMap<String, String>>,
+    ) {
+        (self.root, self.leaves, self.labels)
+    }
+
+    /// Mark this DAG as full, so it is verified as a complete DAG
+    pub fn as_full(mut self) -> Self {
+        self.kind = Some(DagKind::Full);
+        self
+    }
+
+    /// Mark this DAG as partial, so it is verified with Merkle proofs
+    pub fn as_partial(mut self) -> Self {
+        self.kind = Some(DagKind::Partial);
+        self
+    }
+
+    /// Check if this is a partial DAG
+    pub fn is_partial(&self) -> bool {
+        self.kind() == DagKind::Partial
+    }
+
+    /// Run the checks enabled in `options`, leaf by leaf and then across
+    /// the DAG
+    ///
+    /// `leaves_verified` skips the per-leaf checks when they were already
+    /// run, e.g. in parallel.
+    fn run_verification(
+        &self,
+        options: VerifyOptions,
+        progress: &mut dyn FnMut(VerifyProgress),
+        leaves_verified: bool,
+    ) -> Result<()> {
         let root_leaf = self
             .leaves
             .get(&self.root)
             .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
 
-        // Verify root
-        root_leaf.verify_root_leaf()?;
+        let total = self.leaves.len();
+        let others = self.leaves.iter().filter(|(hash, _)| **hash != self.root);
+
+        // Root first, then the rest
+        for (verified, (hash, leaf)) in std::iter::once((&self.root, root_leaf))
+            .chain(others)
+            .enumerate()
+        {
+            if !leaves_verified {
+                self.verify_single_leaf(hash, leaf, options)?;
+            }
+            progress(VerifyProgress {
+                verified: verified + 1,
+                total,
+            });
+        }
+
+        let full = !self.is_partial();
+        if options.check_reachability {
+            self.verify_reachability(full)?;
+            self.verify_no_duplicate_item_names_within_directory()?;
+            self.verify_chunk_order()?;
+        }
+        if options.check_sizes {
+            self.verify_sizes()?;
+        }
+        if options.check_proofs && !full {
+            self.verify_stored_proofs()?;
+        }
 
-        // Verify each non-root leaf and its proof
-        for (hash, leaf) in &self.leaves {
-            if hash == &self.root {
+        Ok(())
+    }
+
+    /// The per-leaf checks: CID and structure, and content hash
+    fn verify_single_leaf(&self, hash: &str, leaf: &DagLeaf, options: VerifyOptions) -> Result<()> {
+        if options.check_cids {
+            if leaf.hash != hash {
+                return Err(ScionicError::HashMismatch {
+                    expected: hash.to_string(),
+                    got: leaf.hash.clone(),
+                });
+            }
+            if hash == self.root {
+                leaf.verify_root_leaf()?;
+            } else {
+                leaf.verify_leaf()?;
+            }
+        }
+        if options.check_content {
+            // Structure-only leaves have had their content dropped
+            if self.kind() != DagKind::Structure {
+                leaf.verify_content_fields()?;
+            }
+            leaf.verify_content_hash()?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that every present leaf hangs off the root through present
+    /// links, and with `all_links` that every link resolves
+    fn verify_reachability(&self, all_links: bool) -> Result<()> {
+        let mut reachable = HashSet::new();
+        let mut stack = vec![self.root.as_str()];
+        while let Some(hash) = stack.pop() {
+            match self.leaves.get(hash) {
+                Some(leaf) if reachable.insert(hash) => {
+                    stack.extend(leaf.links.iter().map(String::as_str));
+                }
+                None if all_links => return Err(ScionicError::MissingLink(hash.to_string())),
+                _ => {}
+            }
+        }
+
+        if let Some(stray) = self
+            .leaves
+            .keys()
+            .find(|hash| !reachable.contains(hash.as_str()))
+        {
+            return Err(ScionicError::InvalidDag(format!(
+                "Leaf {} is not reachable from the root",
+                stray
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check that no directory has two present children with the same name
+    ///
+    /// Children are compared by the last component of their item names, as
+    /// that's the name they're recreated under, so a duplicate would
+    /// overwrite the other entry.
+    pub fn verify_no_duplicate_item_names_within_directory(&self) -> Result<()> {
+        for dir in self.leaves.values() {
+            if dir.leaf_type != LeafType::Directory || dir.is_link_group() {
                 continue;
             }
 
-            // Verify the leaf itself
-            leaf.verify_leaf()?;
-
-            // Find parent and verify proof if needed
-            if let Some(parent) = self.find_parent(hash) {
-                if parent.links.len() > 1 {
-                    if let Some(ref proofs) = parent.proofs {
-                        if let Some(_proof) = proofs.get(hash) {
-                            // Proof verification would go here
-                            // For now, just check that it exists
-                        } else {
-                            return Err(ScionicError::InvalidDag(format!(
-                                "Missing proof for leaf {}",
-                                hash
-                            )));
-                        }
-                    }
+            let children = self.present_linked_leaves(dir);
+            let mut names = HashSet::with_capacity(children.len());
+            for child in children {
+                let name = child.item_name.rsplit('/').next().unwrap_or_default();
+                if !names.insert(name) {
+                    return Err(ScionicError::InvalidDag(format!(
+                        "Directory {} ({}) has more than one entry named {}",
+                        dir.item_name, dir.hash, name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that reserved additional data keys on every leaf have
+    /// well-formed values
+    ///
+    /// Not part of [`verify`](Self::verify), as earlier DAGs may use these
+    /// keys freely; see [`DagLeaf::verify_reserved_keys`].
+    pub fn verify_reserved_keys(&self) -> Result<()> {
+        self.leaves
+            .values()
+            .try_for_each(DagLeaf::verify_reserved_keys)
+    }
+
+    /// Check that every file links its chunks in index order
+    ///
+    /// The index is the last component of a chunk's item name (`{file}/{i}`
+    /// or `{i}`); a chunk at the wrong position would reassemble the file
+    /// out of order. Missing chunks are skipped.
+    pub fn verify_chunk_order(&self) -> Result<()> {
+        for file in self.leaves.values() {
+            if file.leaf_type != LeafType::File || file.is_link_group() {
+                continue;
+            }
+
+            for (position, hash) in self.chunk_links(file).into_iter().enumerate() {
+                let Some(chunk) = self.leaves.get(hash) else {
+                    continue;
+                };
+                let index = chunk.item_name.rsplit('/').next().unwrap_or_default();
+                if index.parse::<usize>().ok() != Some(position) {
+                    return Err(ScionicError::InvalidDag(format!(
+                        "File {} ({}) links chunk {} at position {}",
+                        file.item_name, file.hash, chunk.item_name, position
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check recorded file sizes and, for a full DAG, the root's leaf count,
+    /// total content size and DAG size against the leaves present
+    ///
+    /// The sizes are hashed into the root, but a root rebuilt with consistent
+    /// wrong values would still verify without this. The DAG size is
+    /// recomputed as in `build_root_leaf`. A structure-only DAG has no
+    /// content to size, so only its leaf count is checked.
+    pub fn verify_sizes(&self) -> Result<()> {
+        let structure = self.kind() == DagKind::Structure;
+        if !structure {
+            self.verify_reassembly()?;
+        }
+        self.verify_entry_counts()?;
+        if self.is_partial() {
+            return Ok(());
+        }
+
+        let root_leaf = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+
+        if let Some(leaf_count) = root_leaf.leaf_count {
+            if leaf_count != self.leaves.len() {
+                return Err(ScionicError::SizeMismatch {
+                    expected: leaf_count as i64,
+                    got: self.leaves.len() as i64,
+                });
+            }
+        }
+        if structure {
+            return Ok(());
+        }
+        if let Some(content_size) = root_leaf.content_size {
+            let actual: i64 = self
+                .leaves
+                .values()
+                .filter_map(|leaf| leaf.content.as_ref())
+                .map(|content| content.len() as i64)
+                .sum();
+            if content_size != actual {
+                return Err(ScionicError::SizeMismatch {
+                    expected: content_size,
+                    got: actual,
+                });
+            }
+        }
+        if let Some(dag_size) = root_leaf.dag_size {
+            let mut actual = root_leaf.root_dag_size_contribution()?;
+            for leaf in self.leaves.values().filter(|leaf| leaf.hash != self.root) {
+                actual += leaf.dag_size_contribution()?;
+            }
+            if dag_size != actual {
+                return Err(ScionicError::SizeMismatch {
+                    expected: dag_size,
+                    got: actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check recorded directory entry counts against the entries, looking
+    /// through link groups
+    ///
+    /// Directories with entries missing from a partial DAG are skipped.
+    fn verify_entry_counts(&self) -> Result<()> {
+        for dir in self.leaves.values() {
+            let Some(expected) = dir.entry_count() else {
+                continue;
+            };
+            let Ok(entries) = self.linked_leaves(dir) else {
+                continue;
+            };
+            if entries.len() != expected {
+                return Err(ScionicError::InvalidLeaf(format!(
+                    "entry count mismatch: stored {}, actual {}",
+                    expected,
+                    entries.len()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check a partial DAG's proofs
+    ///
+    /// Every stored proof must be for one of the parent's links and verify
+    /// against the parent's Merkle root. A parent with several links that
+    /// carries proofs must have one for each child present.
+    fn verify_stored_proofs(&self) -> Result<()> {
+        for parent in self.leaves.values() {
+            let Some(proofs) = &parent.proofs else {
+                continue;
+            };
+
+            for (child, branch) in proofs {
+                if branch.leaf != *child {
+                    return Err(ScionicError::InvalidDag(format!(
+                        "Proof stored for {} on {} is for {}",
+                        child, parent.hash, branch.leaf
+                    )));
+                }
+                // Checked before the proof itself, which only shows some
+                // hash is under the Merkle root
+                if !parent.has_link(&branch.leaf) {
+                    return Err(ScionicError::MissingLink(format!(
+                        "{} carries a proof for {}, which it doesn't link",
+                        parent.hash, branch.leaf
+                    )));
+                }
+                let merkle_root = parent
+                    .classic_merkle_root
+                    .as_deref()
+                    .ok_or(ScionicError::InvalidProof)?;
+                verify_proof_with(
+                    child.as_bytes(),
+                    &branch.proof,
+                    merkle_root,
+                    parent.hash_algorithm()?,
+                )?;
+            }
+
+            if parent.links.len() > 1 {
+                if let Some(missing) = parent
+                    .links
+                    .iter()
+                    .find(|link| self.leaves.contains_key(*link) && !proofs.contains_key(*link))
+                {
+                    return Err(ScionicError::InvalidDag(format!(
+                        "Missing proof for leaf {}",
+                        missing
+                    )));
                 }
             }
         }
@@ -292,130 +1540,489 @@ impl Dag {
     }
 
     /// Find the parent of a given leaf
-    fn find_parent(&self, child_hash: &str) -> Option<&DagLeaf> {
+    pub(crate) fn find_parent(&self, child_hash: &str) -> Option<&DagLeaf> {
         self.leaves
             .values()
             .find(|&leaf| leaf.has_link(child_hash))
             .map(|v| v as _)
     }
 
+    /// Map each linked leaf to its parent, built from the links in one pass
+    ///
+    /// A leaf with several parents maps to the lowest parent hash.
+    pub(crate) fn parent_index(&self) -> HashMap<String, String> {
+        let mut parents: HashMap<String, String> = HashMap::new();
+        for leaf in self.leaves.values() {
+            for link in &leaf.links {
+                let parent = parents
+                    .entry(link.clone())
+                    .or_insert_with(|| leaf.hash.clone());
+                if leaf.hash < *parent {
+                    *parent = leaf.hash.clone();
+                }
+            }
+        }
+        parents
+    }
+
+    /// Hash of the lowest directory that contains both leaves
+    ///
+    /// A directory counts as containing itself, so identical inputs return
+    /// that leaf, and a directory and one of its descendants return the
+    /// directory. Falls back to the root when the leaves share no other
+    /// ancestor.
+    pub fn common_ancestor(&self, hash_a: &str, hash_b: &str) -> Result<String> {
+        for hash in [hash_a, hash_b] {
+            if !self.leaves.contains_key(hash) {
+                return Err(ScionicError::MissingLeaf(hash.to_string()));
+            }
+        }
+        if hash_a == hash_b {
+            return Ok(hash_a.to_string());
+        }
+
+        let parents = self.parent_index();
+        let ancestors = |start: &str| {
+            let mut chain = vec![start.to_string()];
+            while let Some(parent) = parents.get(chain.last().unwrap()) {
+                if chain.len() > self.leaves.len() {
+                    break;
+                }
+                chain.push(parent.clone());
+            }
+            chain
+        };
+
+        let is_dir = |hash: &String| {
+            self.leaves
+                .get(hash)
+                .is_some_and(|leaf| leaf.leaf_type == LeafType::Directory && !leaf.is_link_group())
+        };
+        let of_a: HashSet<String> = ancestors(hash_a).into_iter().filter(is_dir).collect();
+
+        Ok(ancestors(hash_b)
+            .into_iter()
+            .filter(is_dir)
+            .find(|hash| of_a.contains(hash))
+            .unwrap_or_else(|| self.root.clone()))
+    }
+
     /// Recreate directory structure from DAG
     pub fn create_directory(&self, output_path: impl AsRef<Path>) -> Result<()> {
+        self.create_directory_with_mode(output_path, CreateDirectoryMode::AbortOnError)?;
+        Ok(())
+    }
+
+    /// Recreate the directory structure, converting the files listed by
+    /// [`crlf_files`](Self::crlf_files) back to CRLF line endings
+    ///
+    /// Every LF in those files becomes CRLF, which restores them exactly:
+    /// only files without bare LFs are normalized. Listed paths that aren't
+    /// files of this DAG are ignored.
+    pub fn create_directory_restoring_line_endings(
+        &self,
+        output_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let output_path = output_path.as_ref();
+        self.create_directory(output_path)?;
+
+        let root_leaf = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+        for rel_path in self.crlf_files() {
+            let escapes = Path::new(&rel_path)
+                .components()
+                .any(|c| !matches!(c, std::path::Component::Normal(_)));
+            let is_file = !escapes
+                && match root_leaf.leaf_type {
+                    LeafType::Directory => self
+                        .leaf_at_path(&rel_path)
+                        .is_some_and(|leaf| leaf.leaf_type == LeafType::File),
+                    _ => rel_path == root_leaf.item_name,
+                };
+            if !is_file {
+                continue;
+            }
+
+            let file_path = output_path.join(&rel_path);
+            let content = fs::read(&file_path)?;
+            let mut restored = Vec::with_capacity(content.len());
+            for byte in content {
+                if byte == b'\n' {
+                    restored.push(b'\r');
+                }
+                restored.push(byte);
+            }
+            fs::write(&file_path, restored)?;
+        }
+
+        Ok(())
+    }
+
+    /// Recreate directory structure from DAG with the given error handling
+    ///
+    /// In [`CreateDirectoryMode::BestEffort`] mode, failures to write
+    /// individual files or directories are collected and returned instead of
+    /// stopping the restore. Failing to find the root or to create the
+    /// output directory is always an error.
+    pub fn create_directory_with_mode(
+        &self,
+        output_path: impl AsRef<Path>,
+        mode: CreateDirectoryMode,
+    ) -> Result<Vec<(PathBuf, ScionicError)>> {
+        let root_leaf = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+
+        let output_path = output_path.as_ref();
+        let mut failures = Failures::new(mode);
+
+        // For root, create the output directory and process its children directly
+        match root_leaf.leaf_type {
+            LeafType::Directory => {
+                fs::create_dir_all(output_path)?;
+                self.create_children(root_leaf, output_path, &mut failures)?;
+            }
+            LeafType::File => {
+                // If root is a file, create it with its name
+                let file_path = output_path.join(&root_leaf.item_name);
+                failures.record(&file_path, self.write_file(root_leaf, &file_path))?;
+            }
+            LeafType::Symlink => {
+                fs::create_dir_all(output_path)?;
+                let link_path = output_path.join(&root_leaf.item_name);
+                failures.record(&link_path, create_symlink(root_leaf, &link_path))?;
+            }
+            LeafType::Chunk => {
+                return Err(ScionicError::InvalidDag(
+                    "Root cannot be a chunk".to_string(),
+                ));
+            }
+        }
+
+        Ok(failures.failures)
+    }
+
+    fn create_children(&self, leaf: &DagLeaf, path: &Path, failures: &mut Failures) -> Result<()> {
+        // Child item_names are relative to root, not to this directory
+        for link in &leaf.links {
+            let Some(child_leaf) = self.leaves.get(link) else {
+                failures.record(path, Err(ScionicError::MissingLeaf(link.clone())))?;
+                continue;
+            };
+            if child_leaf.is_link_group() {
+                self.create_children(child_leaf, path, failures)?;
+                continue;
+            }
+
+            // Extract just the basename of the child's item_name
+            let child_basename = std::path::Path::new(&child_leaf.item_name)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(&child_leaf.item_name);
+
+            let child_path = path.join(child_basename);
+            self.create_directory_leaf(child_leaf, &child_path, failures)?;
+        }
+
+        Ok(())
+    }
+
+    fn create_directory_leaf(
+        &self,
+        leaf: &DagLeaf,
+        path: &Path,
+        failures: &mut Failures,
+    ) -> Result<()> {
+        match leaf.leaf_type {
+            LeafType::Directory => {
+                let created = fs::create_dir_all(path).map_err(ScionicError::from);
+                if failures.record(path, created)? {
+                    self.create_children(leaf, path, failures)?;
+                }
+            }
+            LeafType::File => {
+                failures.record(path, self.write_file(leaf, path))?;
+            }
+            LeafType::Symlink => {
+                failures.record(path, create_symlink(leaf, path))?;
+            }
+            LeafType::Chunk => {
+                // Chunks are handled by their parent file
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_file(&self, leaf: &DagLeaf, path: &Path) -> Result<()> {
+        // Ensure parent directory exists
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = self.get_content_from_leaf(leaf)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Get the full content from a file leaf (reassembling chunks if needed)
+    pub(crate) fn get_content_from_leaf(&self, leaf: &DagLeaf) -> Result<Vec<u8>> {
+        if !leaf.links.is_empty() {
+            // Reassemble from chunks
+            let mut content = Vec::new();
+
+            for chunk in self.linked_leaves(leaf)? {
+                if let Some(ref chunk_content) = chunk.content {
+                    content.extend_from_slice(chunk_content);
+                } else {
+                    return Err(ScionicError::InvalidLeaf(
+                        "Chunk has no content".to_string(),
+                    ));
+                }
+            }
+
+            Ok(content)
+        } else if let Some(ref content) = leaf.content {
+            Ok(content.clone())
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// The leaves `leaf` links to, in order, looking through link groups
+    ///
+    /// For a file these are its chunks, for a directory its entries.
+    pub(crate) fn linked_leaves<'a>(&'a self, leaf: &'a DagLeaf) -> Result<Vec<&'a DagLeaf>> {
+        let mut linked = Vec::with_capacity(leaf.links.len());
+        self.collect_linked(leaf, &mut linked)?;
+        Ok(linked)
+    }
+
+    /// Like [`linked_leaves`](Self::linked_leaves), skipping missing leaves
+    pub(crate) fn present_linked_leaves<'a>(&'a self, leaf: &'a DagLeaf) -> Vec<&'a DagLeaf> {
+        let mut linked = Vec::with_capacity(leaf.links.len());
+        for child in leaf.links.iter().filter_map(|link| self.leaves.get(link)) {
+            if child.is_link_group() {
+                linked.extend(self.present_linked_leaves(child));
+            } else {
+                linked.push(child);
+            }
+        }
+        linked
+    }
+
+    fn collect_linked<'a>(
+        &'a self,
+        leaf: &'a DagLeaf,
+        linked: &mut Vec<&'a DagLeaf>,
+    ) -> Result<()> {
+        for link in &leaf.links {
+            let child = self
+                .leaves
+                .get(link)
+                .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
+            if child.is_link_group() {
+                self.collect_linked(child, linked)?;
+            } else {
+                linked.push(child);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The content of the file at `item_path`, without recreating anything
+    /// else
+    ///
+    /// The path is relative to the root with `/` separators, as in the
+    /// leaves' item names; for a file root it's the root's name. Fails with
+    /// `MissingLeaf` if the path doesn't resolve and `InvalidType` if it
+    /// isn't a file.
+    pub fn extract_file(&self, item_path: &str) -> Result<Vec<u8>> {
+        let root = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+        let components: Vec<&str> = item_path.split('/').filter(|c| !c.is_empty()).collect();
+
+        let target = if root.leaf_type == LeafType::Directory {
+            let mut current = root;
+            for depth in 1..=components.len() {
+                let name = components[..depth].join("/");
+                current = self
+                    .present_linked_leaves(current)
+                    .into_iter()
+                    .find(|child| child.item_name == name)
+                    .ok_or_else(|| ScionicError::MissingLeaf(item_path.to_string()))?;
+            }
+            current
+        } else if components.join("/") == root.item_name {
+            root
+        } else {
+            return Err(ScionicError::MissingLeaf(item_path.to_string()));
+        };
+
+        if target.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is a {}, not a file",
+                item_path, target.leaf_type
+            )));
+        }
+
+        self.get_content_from_leaf(target)
+    }
+
+    /// Map each file's path to the hash of its whole content
+    ///
+    /// Inline files use their stored content hash; chunked files are
+    /// reassembled and hashed.
+    pub fn flatten(&self) -> Result<BTreeMap<String, Vec<u8>>> {
+        let mut files = BTreeMap::new();
+
+        for leaf in self.leaves.values() {
+            if leaf.leaf_type == LeafType::File && !leaf.is_link_group() {
+                files.insert(leaf.item_name.clone(), self.file_content_hash(leaf)?);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Name, type and hash of each entry directly under the root
+    ///
+    /// Entries are sorted by name. A DAG whose root is a file has no
+    /// entries.
+    pub fn top_level(&self) -> Result<Vec<(String, LeafType, String)>> {
         let root_leaf = self
             .leaves
             .get(&self.root)
             .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
 
-        let output_path = output_path.as_ref();
-
-        // For root, create the output directory and process its children directly
-        match root_leaf.leaf_type {
-            LeafType::Directory => {
-                fs::create_dir_all(output_path)?;
+        if root_leaf.leaf_type != LeafType::Directory {
+            return Ok(Vec::new());
+        }
 
-                for link in &root_leaf.links {
-                    let child_leaf = self
-                        .leaves
-                        .get(link)
-                        .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
+        let mut entries = Vec::with_capacity(root_leaf.links.len());
+        for child in self.linked_leaves(root_leaf)? {
+            let name = child.item_name.rsplit('/').next().unwrap_or_default();
+            entries.push((
+                name.to_string(),
+                child.leaf_type.clone(),
+                child.hash.clone(),
+            ));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
 
-                    let child_path = output_path.join(&child_leaf.item_name);
-                    self.create_directory_leaf(child_leaf, &child_path)?;
-                }
-            }
-            LeafType::File => {
-                // If root is a file, create it with its name
-                let file_path = output_path.join(&root_leaf.item_name);
-                if let Some(parent) = file_path.parent() {
-                    fs::create_dir_all(parent)?;
-                }
+        Ok(entries)
+    }
 
-                let content = self.get_content_from_leaf(root_leaf)?;
-                fs::write(file_path, content)?;
-            }
-            LeafType::Chunk => {
-                return Err(ScionicError::InvalidDag(
-                    "Root cannot be a chunk".to_string(),
-                ));
-            }
+    /// Hash of a file leaf's whole content, using the leaf's hash algorithm
+    pub(crate) fn file_content_hash(&self, leaf: &DagLeaf) -> Result<Vec<u8>> {
+        match (&leaf.content_hash, leaf.links.is_empty()) {
+            (Some(hash), true) => Ok(hash.clone()),
+            _ => Ok(leaf
+                .hash_algorithm()?
+                .digest(&self.get_content_from_leaf(leaf)?)),
         }
-
-        Ok(())
     }
 
-    fn create_directory_leaf(&self, leaf: &DagLeaf, path: &Path) -> Result<()> {
-        match leaf.leaf_type {
-            LeafType::Directory => {
-                fs::create_dir_all(path)?;
-
-                // For directory children, we need to handle the path correctly
-                // Child item_names are relative to root, not to this directory
-                for link in &leaf.links {
-                    let child_leaf = self
-                        .leaves
-                        .get(link)
-                        .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
-
-                    // Extract just the basename of the child's item_name
-                    let child_basename = std::path::Path::new(&child_leaf.item_name)
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or(&child_leaf.item_name);
-
-                    let child_path = path.join(child_basename);
-                    self.create_directory_leaf(child_leaf, &child_path)?;
-                }
+    /// Check that every file with a recorded size reassembles to that size
+    ///
+    /// Chunk lengths are summed without buffering the file, so dropped or
+    /// duplicated chunk links are caught cheaply. Files built without
+    /// `include_file_sizes` are skipped.
+    pub fn verify_reassembly(&self) -> Result<()> {
+        for leaf in self.leaves.values() {
+            if leaf.leaf_type != LeafType::File {
+                continue;
             }
-            LeafType::File => {
-                // Ensure parent directory exists
-                if let Some(parent) = path.parent() {
-                    fs::create_dir_all(parent)?;
-                }
+            let Some(expected) = leaf.file_size() else {
+                continue;
+            };
 
-                let content = self.get_content_from_leaf(leaf)?;
-                fs::write(path, content)?;
+            let mut size = leaf.content.as_ref().map_or(0, |c| c.len() as u64);
+            for chunk in self.linked_leaves(leaf)? {
+                size += chunk.content.as_ref().map_or(0, |c| c.len() as u64);
             }
-            LeafType::Chunk => {
-                // Chunks are handled by their parent file
+
+            if size != expected {
+                return Err(ScionicError::SizeMismatch {
+                    expected: expected as i64,
+                    got: size as i64,
+                });
             }
         }
 
         Ok(())
     }
 
-    /// Get the full content from a file leaf (reassembling chunks if needed)
-    fn get_content_from_leaf(&self, leaf: &DagLeaf) -> Result<Vec<u8>> {
-        if !leaf.links.is_empty() {
-            // Reassemble from chunks
-            let mut content = Vec::new();
+    /// Whether two DAGs hold the same tree, ignoring the root's name
+    ///
+    /// Every item reachable from the root is compared by relative path, type
+    /// and (for files) whole-content hash, so chunking and metadata in
+    /// additional data don't matter. DAGs with missing leaves are never
+    /// equivalent.
+    pub fn equivalent_ignoring_names(&self, other: &Dag) -> bool {
+        match (self.content_manifest(), other.content_manifest()) {
+            (Ok(ours), Ok(theirs)) => ours == theirs,
+            _ => false,
+        }
+    }
 
-            for link in &leaf.links {
-                let chunk = self
-                    .leaves
-                    .get(link)
-                    .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
+    /// Relative path of every item with its content hash (None for
+    /// directories), with the root under the empty path
+    fn content_manifest(&self) -> Result<BTreeMap<String, Option<Vec<u8>>>> {
+        let mut manifest = BTreeMap::new();
 
-                if let Some(ref chunk_content) = chunk.content {
-                    content.extend_from_slice(chunk_content);
-                } else {
-                    return Err(ScionicError::InvalidLeaf(
-                        "Chunk has no content".to_string(),
-                    ));
+        self.iterate_dag(&self.root, &mut |leaf| {
+            if leaf.is_link_group() {
+                return Ok(());
+            }
+            let path = if leaf.hash == self.root {
+                String::new()
+            } else {
+                leaf.item_name.clone()
+            };
+
+            match leaf.leaf_type {
+                LeafType::File => {
+                    manifest.insert(path, Some(self.file_content_hash(leaf)?));
+                }
+                LeafType::Symlink => {
+                    manifest.insert(path, leaf.content_hash.clone());
                 }
+                LeafType::Directory => {
+                    manifest.insert(path, None);
+                }
+                LeafType::Chunk => {}
             }
+            Ok(())
+        })?;
 
-            Ok(content)
-        } else if let Some(ref content) = leaf.content {
-            Ok(content.clone())
-        } else {
-            Ok(Vec::new())
-        }
+        Ok(manifest)
     }
 
     /// Calculate labels for all leaves (for LeafSync)
     pub fn calculate_labels(&mut self) -> Result<()> {
+        self.calculate_labels_from(1)
+    }
+
+    /// Calculate labels for all leaves, numbering them from `start`
+    ///
+    /// Lets a server hosting several DAGs give each its own label range, e.g.
+    /// starting DAG B after the last label of DAG A. The root keeps label 0.
+    pub fn calculate_labels_from(&mut self, start: usize) -> Result<()> {
+        if start < 1 {
+            return Err(ScionicError::InvalidLabel(
+                "Start label must be >= 1".to_string(),
+            ));
+        }
+
         let mut labels = HashMap::new();
-        let mut counter = 1;
+        let mut counter = start;
 
         self.iterate_dag(&self.root.clone(), &mut |leaf| {
             if leaf.hash != self.root {
@@ -429,25 +2036,227 @@ impl Dag {
         Ok(())
     }
 
-    /// Iterate through the DAG in depth-first order
+    /// Calculate labels, returning the labelled DAG
+    pub fn with_labels(mut self) -> Result<Self> {
+        self.calculate_labels()?;
+        Ok(self)
+    }
+
+    /// The first and last label, if labels have been calculated
+    ///
+    /// `None` as well when the DAG has nothing but its root.
+    pub fn label_range(&self) -> Option<(usize, usize)> {
+        let labels = self.labels.as_ref()?;
+        let first = labels.keys().filter_map(|label| label.parse().ok()).min()?;
+        Some((first, first + labels.len() - 1))
+    }
+
+    /// Remove labels, e.g. before sharing a DAG
+    ///
+    /// Labels are local numbering for LeafSync and are not part of any leaf's
+    /// hash, so adding or stripping them never changes the root CID.
+    pub fn strip_labels(&mut self) {
+        self.labels = None;
+    }
+
+    /// Recompute the derived fields that aren't covered by any CID
+    ///
+    /// Every leaf's `parent_hash` is reset from the links (the root has none;
+    /// a leaf with several parents gets the lowest parent hash). Labels are
+    /// recalculated for a full DAG and cleared for a partial one. Missing or
+    /// stale classic Merkle roots are recomputed from the links; since the
+    /// Merkle root is hashed, a recomputed root is only accepted if the
+    /// leaf's CID still verifies with it, and `MerkleRootMismatch` is
+    /// returned otherwise, leaving the DAG untouched.
+    pub fn repair(&mut self) -> Result<()> {
+        let mut merkle_roots = Vec::new();
+        for leaf in self.leaves.values() {
+            let algorithm = leaf.hash_algorithm()?;
+            let merkle_root = merkle_root_for_links_with(&leaf.links, algorithm);
+            if merkle_root == leaf.classic_merkle_root {
+                continue;
+            }
+
+            let mut repaired = leaf.clone();
+            repaired.classic_merkle_root = merkle_root.clone();
+            let verified = if leaf.hash == self.root {
+                repaired.verify_root_leaf()
+            } else {
+                repaired.verify_leaf()
+            };
+            if verified.is_err() {
+                return Err(ScionicError::MerkleRootMismatch);
+            }
+            merkle_roots.push((leaf.hash.clone(), merkle_root));
+        }
+
+        let parents = self.parent_index();
+
+        for (hash, merkle_root) in merkle_roots {
+            if let Some(leaf) = self.leaves.get_mut(&hash) {
+                leaf.classic_merkle_root = merkle_root;
+            }
+        }
+        for (hash, leaf) in self.leaves.iter_mut() {
+            leaf.parent_hash = parents.get(hash).cloned();
+        }
+
+        if self.labels.is_some() {
+            if self.is_partial() {
+                self.labels = None;
+            } else {
+                let start = self.label_range().map_or(1, |(first, _)| first);
+                self.calculate_labels_from(start)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Iterate through the DAG in depth-first order, children in link order
+    ///
+    /// Uses an explicit stack, so deeply nested DAGs don't overflow the call
+    /// stack.
     fn iterate_dag<F>(&self, hash: &str, f: &mut F) -> Result<()>
     where
         F: FnMut(&DagLeaf) -> Result<()>,
     {
-        let leaf = self
-            .leaves
-            .get(hash)
-            .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
+        let mut stack = vec![hash];
+        while let Some(hash) = stack.pop() {
+            let leaf = self
+                .leaves
+                .get(hash)
+                .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
 
-        f(leaf)?;
+            f(leaf)?;
 
-        for link in &leaf.links {
-            self.iterate_dag(link, f)?;
+            // Pushed in reverse so they're visited in order
+            stack.extend(leaf.links.iter().rev().map(String::as_str));
         }
 
         Ok(())
     }
 
+    /// Hashes of the leaves reachable from the root in the order to fetch
+    /// them, highest `priority` first, parents always before children
+    ///
+    /// A subtree is ranked by the highest priority within it, so the leaves
+    /// leading to a high priority file come right before it. Ties keep the
+    /// depth-first order. Links to missing leaves are skipped.
+    pub fn fetch_order(&self, priority: impl Fn(&DagLeaf) -> i32) -> Vec<String> {
+        // Highest priority in each subtree, children computed first
+        let mut ranks: HashMap<&str, i32> = HashMap::new();
+        let mut stack = vec![(self.root.as_str(), false)];
+        while let Some((hash, expanded)) = stack.pop() {
+            let Some(leaf) = self.leaves.get(hash) else {
+                continue;
+            };
+            if expanded {
+                let rank = leaf
+                    .links
+                    .iter()
+                    .filter_map(|link| ranks.get(link.as_str()))
+                    .fold(priority(leaf), |rank, &child| rank.max(child));
+                ranks.insert(hash, rank);
+            } else if !ranks.contains_key(hash) {
+                stack.push((hash, true));
+                stack.extend(leaf.links.iter().map(|link| (link.as_str(), false)));
+            }
+        }
+
+        let position: HashMap<String, usize> = self
+            .hashes_root_first()
+            .into_iter()
+            .enumerate()
+            .map(|(index, hash)| (hash, index))
+            .collect();
+        let entry = |hash: &str| (ranks[hash], std::cmp::Reverse(position[hash]));
+
+        let mut order = Vec::with_capacity(ranks.len());
+        let mut queued = HashSet::new();
+        let mut heap = std::collections::BinaryHeap::new();
+        if ranks.contains_key(self.root.as_str()) {
+            queued.insert(self.root.as_str());
+            heap.push((entry(&self.root), self.root.as_str()));
+        }
+
+        while let Some((_, hash)) = heap.pop() {
+            order.push(hash.to_string());
+            for link in &self.leaves[hash].links {
+                if ranks.contains_key(link.as_str()) && queued.insert(link.as_str()) {
+                    heap.push((entry(link), link.as_str()));
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Every leaf reachable from the root with its depth and path, depth-first
+    ///
+    /// The root is at depth 0 with an empty path; every other leaf's path is
+    /// its parent's joined with its own base name, so chunks appear below
+    /// their file. Directory entries are visited in name order and chunks
+    /// in file order. Link groups are looked through, and links to missing
+    /// leaves are skipped.
+    pub fn iter_with_depth(&self) -> impl Iterator<Item = (usize, PathBuf, &DagLeaf)> {
+        let mut entries = Vec::with_capacity(self.leaves.len());
+        let mut stack = Vec::new();
+        if let Some(root) = self.leaves.get(&self.root) {
+            stack.push((0, PathBuf::new(), root));
+        }
+
+        while let Some((depth, path, leaf)) = stack.pop() {
+            let mut children = self.present_linked_leaves(leaf);
+            if leaf.leaf_type == LeafType::Directory {
+                children.sort_by(|a, b| a.item_name.cmp(&b.item_name));
+            }
+
+            // Pushed in reverse so they're visited in order
+            for child in children.into_iter().rev() {
+                let name = child.item_name.rsplit('/').next().unwrap_or_default();
+                stack.push((depth + 1, path.join(name), child));
+            }
+            entries.push((depth, path, leaf));
+        }
+
+        entries.into_iter()
+    }
+
+    /// Hashes of all present leaves ordered root-first (depth-first, children
+    /// in `links` order). Links to missing leaves are skipped, and leaves not
+    /// reachable from the root are appended in hash order.
+    pub(crate) fn hashes_root_first(&self) -> Vec<String> {
+        let mut order = Vec::with_capacity(self.leaves.len());
+        let mut visited = std::collections::HashSet::new();
+        let mut stack = vec![self.root.as_str()];
+
+        while let Some(hash) = stack.pop() {
+            let Some(leaf) = self.leaves.get(hash) else {
+                continue;
+            };
+            if !visited.insert(hash) {
+                continue;
+            }
+
+            order.push(hash.to_string());
+            for link in leaf.links.iter().rev() {
+                stack.push(link);
+            }
+        }
+
+        let mut rest: Vec<_> = self
+            .leaves
+            .keys()
+            .filter(|hash| !visited.contains(hash.as_str()))
+            .cloned()
+            .collect();
+        rest.sort();
+        order.extend(rest);
+
+        order
+    }
+
     /// Get hashes by label range (for LeafSync)
     pub fn get_hashes_by_label_range(&self, start: usize, end: usize) -> Result<Vec<String>> {
         let labels = self
@@ -455,11 +2264,14 @@ impl Dag {
             .as_ref()
             .ok_or_else(|| ScionicError::InvalidLabel("Labels not calculated".to_string()))?;
 
+        let (first, last) = self.label_range().unwrap_or((1, 0));
+
         // Validate range
-        if start < 1 {
-            return Err(ScionicError::InvalidLabel(
-                "Start label must be >= 1".to_string(),
-            ));
+        if start < first {
+            return Err(ScionicError::InvalidLabel(format!(
+                "Start label must be >= {}",
+                first
+            )));
         }
 
         if end < start {
@@ -469,11 +2281,10 @@ impl Dag {
             )));
         }
 
-        if end > labels.len() {
+        if end > last {
             return Err(ScionicError::InvalidLabel(format!(
-                "End label ({}) exceeds available labels ({})",
-                end,
-                labels.len()
+                "End label ({}) exceeds last label ({})",
+                end, last
             )));
         }
 
@@ -517,6 +2328,9 @@ impl Dag {
     }
 
     /// Get a partial DAG containing only the specified leaves and their verification paths
+    ///
+    /// Every multi-link parent carries Merkle proofs for its present
+    /// children, so the partial verifies without their siblings.
     pub fn get_partial(&self, leaf_hashes: &[String], _prune_links: bool) -> Result<Dag> {
         if leaf_hashes.is_empty() {
             return Err(ScionicError::InvalidDag(
@@ -555,11 +2369,120 @@ impl Dag {
             }
         }
 
-        Ok(Dag {
+        let mut partial = Dag {
             root: self.root.clone(),
             leaves: partial_leaves,
             labels: None,
-        })
+            kind: Some(DagKind::Partial),
+        };
+        partial.attach_proofs()?;
+        Ok(partial)
+    }
+
+    /// Get a partial DAG for a label range (server side of LeafSync)
+    ///
+    /// The labeled leaves are included with their paths to the root, and every
+    /// multi-link parent carries Merkle proofs for its present children, so a
+    /// client can verify the partial against the root it already knows.
+    pub fn partial_for_labels(&self, start: usize, end: usize) -> Result<Dag> {
+        let hashes = self.get_hashes_by_label_range(start, end)?;
+        self.get_partial(&hashes, false)
+    }
+
+    /// Get a partial DAG holding every file whose path matches a glob
+    ///
+    /// Paths are relative to the root and use `/` as the separator. `*` and
+    /// `?` match within a single component, and a `**` component matches any
+    /// number of directories. Matched files are included with their chunks,
+    /// their paths to the root and Merkle proofs, as in
+    /// [`partial_for_labels`](Self::partial_for_labels).
+    pub fn partial_by_glob(&self, pattern: &str) -> Result<Dag> {
+        let pattern: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+        let mut hashes = Vec::new();
+
+        self.iterate_dag(&self.root, &mut |leaf| {
+            if leaf.leaf_type != LeafType::File || leaf.hash == self.root || leaf.is_link_group() {
+                return Ok(());
+            }
+
+            let path: Vec<&str> = leaf.item_name.split('/').collect();
+            if glob_match(&pattern, &path) {
+                hashes.push(leaf.hash.clone());
+                hashes.extend(
+                    self.linked_leaves(leaf)?
+                        .iter()
+                        .map(|chunk| chunk.hash.clone()),
+                );
+            }
+            Ok(())
+        })?;
+
+        if hashes.is_empty() {
+            return Err(ScionicError::InvalidDag(format!(
+                "No files match {}",
+                pattern.join("/")
+            )));
+        }
+
+        self.get_partial(&hashes, false)
+    }
+
+    /// Get a partial DAG holding the first `byte_len` bytes of the file leaf
+    /// `file_hash`
+    ///
+    /// Only the chunks overlapping `[0, byte_len)` are included, with their
+    /// paths to the root and Merkle proofs, so a prefix downloaded so far can
+    /// be verified against the original root. A file stored inline is
+    /// included whole.
+    pub fn file_prefix(&self, file_hash: &str, byte_len: u64) -> Result<Dag> {
+        let file = self
+            .leaves
+            .get(file_hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(file_hash.to_string()))?;
+        if file.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is not a file leaf",
+                file_hash
+            )));
+        }
+
+        let mut hashes = vec![file_hash.to_string()];
+        let chunks = self.chunk_links(file);
+        if !chunks.is_empty() && byte_len > 0 {
+            let chunk_size = self.chunk_size_of(file, &chunks)?;
+            let count = byte_len.div_ceil(chunk_size).min(chunks.len() as u64) as usize;
+            hashes.extend(chunks[..count].iter().map(|hash| hash.to_string()));
+        }
+
+        self.get_partial(&hashes, false)
+    }
+
+    /// Store a Merkle proof on each multi-link parent for every present child
+    pub(crate) fn attach_proofs(&mut self) -> Result<()> {
+        let mut proofs = Vec::new();
+        for leaf in self.leaves.values() {
+            if leaf.links.len() <= 1 {
+                continue;
+            }
+            for link in &leaf.links {
+                if self.leaves.contains_key(link) {
+                    if let Some(branch) = leaf.get_branch(link)? {
+                        proofs.push((leaf.hash.clone(), link.clone(), branch));
+                    }
+                }
+            }
+        }
+
+        for (parent_hash, child_hash, branch) in proofs {
+            if let Some(parent) = self.leaves.get_mut(&parent_hash) {
+                parent
+                    .proofs
+                    .get_or_insert_with(HashMap::new)
+                    .insert(child_hash, branch);
+            }
+        }
+
+        Ok(())
     }
 
     /// Get total size of file content (for files/chunks only, not directories)
@@ -574,12 +2497,7 @@ impl Dag {
                 if !root_leaf.links.is_empty() {
                     // Chunked file - sum up all chunk sizes
                     let mut total_size = 0u64;
-                    for link in &root_leaf.links {
-                        let chunk = self
-                            .leaves
-                            .get(link)
-                            .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
-
+                    for chunk in self.linked_leaves(root_leaf)? {
                         if let Some(ref content) = chunk.content {
                             total_size += content.len() as u64;
                         }
@@ -592,8 +2510,8 @@ impl Dag {
                     Ok(0)
                 }
             }
-            LeafType::Chunk => {
-                // Single chunk
+            LeafType::Chunk | LeafType::Symlink => {
+                // Single chunk, or a symlink's target
                 if let Some(ref content) = root_leaf.content {
                     Ok(content.len() as u64)
                 } else {
@@ -647,12 +2565,7 @@ impl Dag {
         let mut result = Vec::new();
         let mut current_offset = 0u64;
 
-        for link in &root_leaf.links {
-            let chunk = self
-                .leaves
-                .get(link)
-                .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
-
+        for chunk in self.linked_leaves(root_leaf)? {
             let chunk_content = chunk
                 .content
                 .as_ref()
@@ -689,6 +2602,181 @@ impl Dag {
 
         Ok(result)
     }
+
+    /// Read bytes `[start, end)` of the file leaf `file_hash`, touching only
+    /// the chunks that overlap the range
+    ///
+    /// Works on partial DAGs: only the overlapping chunks (and the link
+    /// groups above them) need to be present, and a missing one fails with
+    /// `MissingLeaf`. Chunk offsets follow from the chunk size, which every
+    /// chunk but the last has. The range is clamped to the end of the file.
+    pub fn read_file_range(&self, file_hash: &str, start: u64, end: u64) -> Result<Vec<u8>> {
+        let file = self
+            .leaves
+            .get(file_hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(file_hash.to_string()))?;
+        if file.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is not a file leaf",
+                file_hash
+            )));
+        }
+        if start > end {
+            return Err(ScionicError::InvalidDag(format!(
+                "Invalid range: start ({}) > end ({})",
+                start, end
+            )));
+        }
+
+        if file.links.is_empty() {
+            return Ok(slice_range(
+                file.content.as_deref().unwrap_or_default(),
+                start,
+                end,
+            ));
+        }
+
+        let chunks = self.chunk_links(file);
+        if start == end || chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let chunk_size = self.chunk_size_of(file, &chunks)?;
+
+        // A coalesced last chunk runs past its nominal end
+        let last_index = chunks.len() - 1;
+        let first = ((start / chunk_size) as usize).min(last_index);
+        let last = (((end - 1) / chunk_size) as usize).min(last_index);
+
+        let mut data = Vec::new();
+        for hash in &chunks[first..=last] {
+            let chunk = self
+                .leaves
+                .get(*hash)
+                .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
+            let content = chunk
+                .content
+                .as_deref()
+                .ok_or_else(|| ScionicError::InvalidLeaf("Chunk has no content".to_string()))?;
+            data.extend_from_slice(content);
+        }
+
+        let offset = first as u64 * chunk_size;
+        Ok(slice_range(&data, start - offset, end - offset))
+    }
+
+    /// A file's chunk hashes in order, looking through link groups
+    ///
+    /// Chunks may be missing. A missing link is taken for a link group if a
+    /// present sibling is one, as groups fill whole levels.
+    fn chunk_links<'a>(&'a self, leaf: &'a DagLeaf) -> Vec<&'a str> {
+        let groups = leaf
+            .links
+            .iter()
+            .filter_map(|link| self.leaves.get(link))
+            .any(|child| child.is_link_group());
+        if !groups {
+            return leaf.links.iter().map(String::as_str).collect();
+        }
+
+        let mut links = Vec::new();
+        for link in &leaf.links {
+            match self.leaves.get(link) {
+                Some(group) => links.extend(self.chunk_links(group)),
+                None => links.push(link.as_str()),
+            }
+        }
+        links
+    }
+
+    /// The size of every chunk but the last, from any such chunk present, or
+    /// else from the file size and the last chunk or the recorded chunk
+    /// parameters
+    fn chunk_size_of(&self, file: &DagLeaf, chunks: &[&str]) -> Result<u64> {
+        let chunk_len = |hash: &str| {
+            self.leaves
+                .get(hash)
+                .and_then(|chunk| chunk.content.as_ref())
+                .map(|content| content.len() as u64)
+        };
+
+        let (last, others) = chunks
+            .split_last()
+            .ok_or_else(|| ScionicError::InvalidDag("File has no chunks".to_string()))?;
+        if others.is_empty() {
+            return Ok(u64::MAX);
+        }
+        if let Some(size) = others.iter().find_map(|hash| chunk_len(hash)) {
+            return Ok(size);
+        }
+        if let (Some(file_size), Some(last_len)) = (file.file_size(), chunk_len(last)) {
+            let size = file_size.saturating_sub(last_len) / others.len() as u64;
+            if size > 0 {
+                return Ok(size);
+            }
+        }
+        if let Some(params) = self.chunk_params().filter(|params| params.chunk_size > 0) {
+            return Ok(params.chunk_size as u64);
+        }
+
+        Err(ScionicError::MissingLeaf(others[0].to_string()))
+    }
+}
+
+/// Bytes `[start, end)` of `data`, clamped to its length
+fn slice_range(data: &[u8], start: u64, end: u64) -> Vec<u8> {
+    let len = data.len() as u64;
+    data[start.min(len) as usize..end.min(len) as usize].to_vec()
+}
+
+/// Match path components against glob components
+fn glob_match(pattern: &[&str], path: &[&str]) -> bool {
+    wildcard_match(
+        pattern,
+        path,
+        |part| *part == "**",
+        |part, component| component_match(part, component),
+    )
+}
+
+/// Match a single path component against a pattern with `*` and `?`
+fn component_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    wildcard_match(&pattern, &name, |c| *c == '*', |p, c| *p == '?' || p == c)
+}
+
+/// Match `items` against `pattern`, where a star matches any run of items
+/// and every other token exactly one item
+///
+/// Only the latest star is backtracked to: an earlier one could only take
+/// items the latest can take as well, so matching takes
+/// O(pattern × items) steps.
+fn wildcard_match<P, T>(
+    pattern: &[P],
+    items: &[T],
+    is_star: impl Fn(&P) -> bool,
+    matches_one: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Pattern index after the latest star, and the item it resumes from
+    let mut backtrack = None;
+    while i < items.len() {
+        if p < pattern.len() && is_star(&pattern[p]) {
+            p += 1;
+            backtrack = Some((p, i));
+        } else if p < pattern.len() && matches_one(&pattern[p], &items[i]) {
+            p += 1;
+            i += 1;
+        } else if let Some((star_p, star_i)) = backtrack {
+            // Let the star take one more item
+            p = star_p;
+            i = star_i + 1;
+            backtrack = Some((star_p, i));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_star)
 }
 
 #[cfg(test)]
@@ -711,6 +2799,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_read_detects_changed_size() -> Result<()> {
+        let path = Path::new("changing.bin");
+        let content = vec![1u8; 100];
+
+        assert_eq!(read_expected_size(content.as_slice(), path, 100)?, content);
+
+        // File shrank since its size was read
+        match read_expected_size(content.as_slice(), path, 150) {
+            Err(ScionicError::FileChanged { expected, got, .. }) => {
+                assert_eq!((expected, got), (150, 100));
+            }
+            result => panic!("expected FileChanged, got {:?}", result.map(|d| d.len())),
+        }
+
+        // File grew since its size was read
+        match read_expected_size(content.as_slice(), path, 50) {
+            Err(ScionicError::FileChanged { expected, got, .. }) => {
+                assert_eq!((expected, got), (50, 51));
+            }
+            result => panic!("expected FileChanged, got {:?}", result.map(|d| d.len())),
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_create_dag_from_directory() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -844,4 +2958,37 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_glob_match() {
+        let matches = |pattern: &str, path: &str| {
+            let pattern: Vec<&str> = pattern.split('/').collect();
+            let path: Vec<&str> = path.split('/').collect();
+            glob_match(&pattern, &path)
+        };
+
+        assert!(matches("*.txt", "a.txt"));
+        assert!(!matches("*.txt", "dir/a.txt"));
+        assert!(matches("**/*.txt", "a.txt"));
+        assert!(matches("**/*.txt", "dir/sub/a.txt"));
+        assert!(matches("dir/**", "dir/sub/a.txt"));
+        assert!(matches("file?.rs", "file1.rs"));
+        assert!(!matches("file?.rs", "file10.rs"));
+        assert!(!matches("**/*.txt", "a.txt.bak"));
+        assert!(matches("**/b/**/c", "a/b/x/b/y/c"));
+        assert!(!matches("**/b/**/c", "a/b/x/c/y"));
+        assert!(matches("*a*b", "xaybzb"));
+        assert!(!matches("*a*b", "xaybzbc"));
+
+        // `?` matches a character, not a byte
+        assert!(matches("caf?.txt", "café.txt"));
+        assert!(!matches("caf??.txt", "café.txt"));
+
+        // Many stars against a near miss finish quickly
+        assert!(!matches(&"*a".repeat(30), &format!("{}b", "a".repeat(200))));
+        assert!(!matches(
+            &format!("{}b", "**/a/".repeat(20)),
+            &vec!["a"; 200].join("/")
+        ));
+    }
 }
@@ -63,6 +63,7 @@ impl DagDiff {
                 root: old_dag.root.clone(),
                 leaves: old_dag.leaves.clone(),
                 labels: None,
+                kind: old_dag.kind,
             });
         }
 
@@ -89,51 +90,39 @@ impl DagDiff {
         let mut new_root_hash: Option<String> = None;
 
         for (hash, leaf) in &added_leaves {
-            if !child_hashes.contains(hash) {
-                if leaf.leaf_count.is_some() && leaf.leaf_count.unwrap() > 0 {
-                    new_root_hash = Some(hash.clone());
-                    break;
-                }
+            if !child_hashes.contains(hash) && leaf.leaf_count.is_some_and(|count| count > 0) {
+                new_root_hash = Some(hash.clone());
+                break;
             }
         }
 
-        let new_root_hash =
-            new_root_hash.ok_or_else(|| ScionicError::InvalidDag("Cannot find new root among added leaves".into()))?;
+        let new_root_hash = new_root_hash.ok_or_else(|| {
+            ScionicError::InvalidDag("Cannot find new root among added leaves".into())
+        })?;
 
         // Traverse from new root to collect referenced leaves
         let mut new_leaves: HashMap<String, DagLeaf> = HashMap::new();
         let mut visited: HashSet<String> = HashSet::new();
 
-        fn traverse(
-            hash: &str,
-            leaf_pool: &HashMap<String, DagLeaf>,
-            new_leaves: &mut HashMap<String, DagLeaf>,
-            visited: &mut HashSet<String>,
-        ) -> Result<()> {
-            if visited.contains(hash) {
-                return Ok(());
+        let mut stack = vec![new_root_hash.clone()];
+        while let Some(hash) = stack.pop() {
+            if !visited.insert(hash.clone()) {
+                continue;
             }
-            visited.insert(hash.to_string());
 
-            let leaf = leaf_pool
-                .get(hash)
-                .ok_or_else(|| ScionicError::InvalidDag(format!("Missing leaf in pool: {}", hash)))?;
+            let leaf = leaf_pool.get(&hash).ok_or_else(|| {
+                ScionicError::InvalidDag(format!("Missing leaf in pool: {}", hash))
+            })?;
 
-            new_leaves.insert(hash.to_string(), leaf.clone());
-
-            for child_hash in &leaf.links {
-                traverse(child_hash, leaf_pool, new_leaves, visited)?;
-            }
-
-            Ok(())
+            stack.extend(leaf.links.iter().rev().cloned());
+            new_leaves.insert(hash, leaf.clone());
         }
 
-        traverse(&new_root_hash, &leaf_pool, &mut new_leaves, &mut visited)?;
-
         Ok(Dag {
             root: new_root_hash,
             leaves: new_leaves,
             labels: None,
+            kind: None,
         })
     }
 
@@ -141,7 +130,9 @@ impl DagDiff {
     pub fn create_partial_dag(&self, full_new_dag: &Dag) -> Result<Dag> {
         let added_leaves = self.get_added_leaves();
         if added_leaves.is_empty() {
-            return Err(ScionicError::InvalidDag("No added leaves to create partial DAG".into()));
+            return Err(ScionicError::InvalidDag(
+                "No added leaves to create partial DAG".into(),
+            ));
         }
 
         let added_hashes: Vec<String> = added_leaves.keys().cloned().collect();
@@ -196,7 +187,10 @@ pub fn diff(first_dag: &Dag, second_dag: &Dag) -> Result<DagDiff> {
 
 /// Compare old DAG with a set of new leaves (e.g., from partial DAG)
 /// Identifies added leaves and removed leaves no longer referenced by new structure
-pub fn diff_from_new_leaves(original_dag: &Dag, new_leaves: &HashMap<String, DagLeaf>) -> Result<DagDiff> {
+pub fn diff_from_new_leaves(
+    original_dag: &Dag,
+    new_leaves: &HashMap<String, DagLeaf>,
+) -> Result<DagDiff> {
     let mut diffs: HashMap<String, LeafDiff> = HashMap::new();
     let mut summary = DiffSummary::default();
 
@@ -231,28 +225,21 @@ pub fn diff_from_new_leaves(original_dag: &Dag, new_leaves: &HashMap<String, Dag
     let mut reachable: HashSet<String> = HashSet::new();
 
     if let Some((root_hash, _)) = new_root {
-        fn traverse_reachable(
-            hash: &str,
-            new_leaves: &HashMap<String, DagLeaf>,
-            old_leaves: &HashMap<String, DagLeaf>,
-            reachable: &mut HashSet<String>,
-        ) {
-            if reachable.contains(hash) {
-                return;
+        let mut stack = vec![root_hash.clone()];
+        while let Some(hash) = stack.pop() {
+            if reachable.contains(&hash) {
+                continue;
             }
-            reachable.insert(hash.to_string());
 
             // Look in both new and old leaves
-            let leaf = new_leaves.get(hash).or_else(|| old_leaves.get(hash));
-
+            let leaf = new_leaves
+                .get(&hash)
+                .or_else(|| original_dag.leaves.get(&hash));
             if let Some(leaf) = leaf {
-                for child_hash in &leaf.links {
-                    traverse_reachable(child_hash, new_leaves, old_leaves, reachable);
-                }
+                stack.extend(leaf.links.iter().rev().cloned());
             }
+            reachable.insert(hash);
         }
-
-        traverse_reachable(root_hash, new_leaves, &original_dag.leaves, &mut reachable);
     }
 
     // Any old leaf not reachable is removed
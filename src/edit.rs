@@ -0,0 +1,762 @@
+//! Structural edits
+//!
+//! A leaf's CID covers its links, so changing any leaf means rebuilding every
+//! ancestor up to the root. The helpers here do that re-hashing and drop the
+//! leaves that are no longer reachable afterwards.
+
+use crate::dag::{build_file_leaf, group_links, read_file, with_crlf_files, DagBuilder};
+use crate::error::{Result, ScionicError};
+use crate::types::{
+    Dag, DagBuilderConfig, DagKind, DagLeaf, DagLeafBuilder, LeafType, CHUNK_NAMING_KEY,
+    CHUNK_SIZE_KEY, COALESCE_SMALL_TAIL_KEY, CRLF_FILES_KEY, ENTRY_COUNT_KEY, LINE_ENDINGS_KEY,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Pending changes below a directory, keyed by entry name
+enum Change {
+    /// A file's new leaf, or `None` if it was removed
+    File(Option<Box<DagLeaf>>),
+    Dir(BTreeMap<String, Change>),
+}
+
+impl DagLeaf {
+    /// Builder with this leaf's name, type, hash algorithm and content,
+    /// linking to `links`
+    pub(crate) fn rebuilder(
+        &self,
+        links: impl IntoIterator<Item = String>,
+    ) -> Result<DagLeafBuilder> {
+        let builder = DagLeafBuilder::new(self.item_name.clone())
+            .set_type(self.leaf_type.clone())
+            .set_hash_algorithm(self.hash_algorithm()?)
+            .set_cid_base(self.cid_base()?);
+
+        let builder = match &self.content {
+            Some(content) => builder.set_data(content.clone()),
+            None => builder,
+        };
+
+        Ok(links
+            .into_iter()
+            .fold(builder, |builder, link| builder.add_link(link)))
+    }
+}
+
+impl Dag {
+    /// Replace the leaf `old_hash` with `new_leaf`, rebuilding its ancestors
+    ///
+    /// `new_descendants` holds any leaves `new_leaf` links to that are not
+    /// already in the DAG. If `old_hash` is the root, `new_leaf` is rebuilt
+    /// as the new root. Labels are recalculated if the DAG had them.
+    pub(crate) fn replace_leaf(
+        &self,
+        old_hash: &str,
+        new_leaf: DagLeaf,
+        new_descendants: HashMap<String, DagLeaf>,
+    ) -> Result<Dag> {
+        // Ancestors from the direct parent up to the root
+        let mut ancestors = Vec::new();
+        let mut current = old_hash.to_string();
+        while current != self.root {
+            let parent = self.find_parent(&current).ok_or_else(|| {
+                ScionicError::MissingLeaf(format!("Parent not found for {}", current))
+            })?;
+            current = parent.hash.clone();
+            ancestors.push(parent);
+        }
+
+        let mut leaves = self.leaves.clone();
+        leaves.extend(new_descendants);
+
+        let (root_source, root_links) = match ancestors.pop() {
+            None => {
+                let links = new_leaf.links.clone();
+                (new_leaf, links)
+            }
+            Some(root_leaf) => {
+                let mut old_hash = old_hash.to_string();
+                let mut new_hash = new_leaf.hash.clone();
+                leaves.insert(new_leaf.hash.clone(), new_leaf);
+
+                for ancestor in ancestors {
+                    let links = replace_link(&ancestor.links, &old_hash, &new_hash);
+                    let rebuilt = ancestor
+                        .rebuilder(links)?
+                        .build_leaf(ancestor.additional_data.clone())?;
+
+                    old_hash = ancestor.hash.clone();
+                    new_hash = rebuilt.hash.clone();
+                    leaves.insert(rebuilt.hash.clone(), rebuilt);
+                }
+
+                let links = replace_link(&root_leaf.links, &old_hash, &new_hash);
+                (root_leaf.clone(), links)
+            }
+        };
+
+        let mut leaves = reachable_leaves(&leaves, &root_links);
+        let root = root_source
+            .rebuilder(root_links)?
+            .build_root_leaf(&leaves, root_source.additional_data.clone())?;
+        leaves.insert(root.hash.clone(), root.clone());
+
+        let mut dag = Dag {
+            root: root.hash,
+            leaves,
+            labels: None,
+            kind: self.kind,
+        };
+
+        if self.labels.is_some() {
+            let start = self.label_range().map_or(1, |(first, _)| first);
+            dag.calculate_labels_from(start)?;
+        }
+
+        Ok(dag)
+    }
+
+    /// Collapse a chunked file into a single inline file leaf
+    ///
+    /// The chunks are reassembled into the file leaf's content and every
+    /// ancestor is rebuilt. A file that is already inline is left unchanged.
+    pub fn inline_file(&self, file_hash: &str) -> Result<Dag> {
+        let leaf = self
+            .leaves
+            .get(file_hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(file_hash.to_string()))?;
+
+        if leaf.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "{} is not a file leaf",
+                file_hash
+            )));
+        }
+
+        if leaf.links.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let content = self.get_content_from_leaf(leaf)?;
+        let inline_leaf = DagLeafBuilder::new(leaf.item_name.clone())
+            .set_type(LeafType::File)
+            .set_hash_algorithm(leaf.hash_algorithm()?)
+            .set_cid_base(leaf.cid_base()?)
+            .set_data(content)
+            .build_leaf(leaf.additional_data.clone())?;
+
+        self.replace_leaf(file_hash, inline_leaf, HashMap::new())
+    }
+
+    /// Find the leaf at a `/`-separated path relative to the root
+    ///
+    /// An empty path refers to the root itself.
+    pub(crate) fn leaf_at_path(&self, path: &str) -> Option<&DagLeaf> {
+        let mut current = self.leaves.get(&self.root)?;
+        let mut prefix = String::new();
+
+        for component in path_components(path) {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(component);
+
+            current = self
+                .present_linked_leaves(current)
+                .into_iter()
+                .find(|child| child.item_name == prefix)?;
+        }
+
+        Some(current)
+    }
+
+    /// Attach another DAG as a subdirectory
+    ///
+    /// `path` is where the subtree ends up, e.g. `docs/sub`: the subtree's
+    /// root is renamed to it and linked from the directory `docs`, which
+    /// must already exist and must not have an entry named `sub`. Every
+    /// descendant is rebuilt under `path` (index-only chunk names are kept),
+    /// so the result matches building the combined tree from disk with
+    /// `config`, which rebuilt directories are regrouped with. Both DAGs
+    /// must be complete and built with the same hash algorithm, CID base,
+    /// chunk naming and line ending handling. The subtree's normalized
+    /// files join the root's list under `path`; its other root-only
+    /// metadata (chunking parameters) is dropped.
+    pub fn graft(&self, path: &str, subtree: &Dag, config: &DagBuilderConfig) -> Result<Dag> {
+        let components = path_components(path);
+        let Some((_, parents)) = components.split_last() else {
+            return Err(ScionicError::InvalidDag(
+                "Graft path must not be empty".to_string(),
+            ));
+        };
+
+        let parent_path = parents.join("/");
+        let parent = self
+            .leaf_at_path(&parent_path)
+            .ok_or_else(|| ScionicError::PathNotFound(parent_path.clone()))?;
+
+        if parent.leaf_type != LeafType::Directory {
+            return Err(ScionicError::InvalidDag(format!(
+                "{} is not a directory",
+                parent_path
+            )));
+        }
+
+        let item_name = components.join("/");
+        if self.leaf_at_path(&item_name).is_some() {
+            return Err(ScionicError::InvalidDag(format!(
+                "{} already exists",
+                item_name
+            )));
+        }
+
+        let subtree_root = subtree
+            .leaves
+            .get(&subtree.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+        self.check_same_settings(subtree)?;
+
+        // A directory's descendants are named relative to it, a file's
+        // chunks after the file
+        let old_prefix = match subtree_root.leaf_type {
+            LeafType::Directory => "",
+            _ => subtree_root.item_name.as_str(),
+        };
+        let mut builder = DagBuilder::new();
+        let grafted_links = graft_links(
+            subtree,
+            subtree_root,
+            old_prefix,
+            &item_name,
+            &mut builder.leaves,
+        )?;
+        for file in subtree.crlf_files() {
+            builder.record_crlf_file(&graft_name(&file, old_prefix, &item_name));
+        }
+        let grafted = {
+            let mut renamed = subtree_root.rebuilder(grafted_links)?;
+            renamed.item_name = item_name;
+            renamed.build_leaf(without_root_keys(&subtree_root.additional_data))?
+        };
+
+        // Added like a new file, so the parent is regrouped and the spine
+        // rebuilt as for any other edit
+        let components = components.iter().map(|c| c.to_string()).collect();
+        self.with_file_changes(vec![(components, Some(grafted))], builder, config)
+    }
+
+    /// Fail unless `other` was built with the same hash algorithm, CID
+    /// base, chunk naming and line ending handling, so leaves of both can
+    /// share a DAG
+    fn check_same_settings(&self, other: &Dag) -> Result<()> {
+        let settings = |dag: &Dag| -> Result<_> {
+            let root = dag
+                .leaves
+                .get(&dag.root)
+                .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+            Ok((
+                root.hash_algorithm()?,
+                root.cid_base()?,
+                dag.chunk_naming(),
+                dag.line_endings_normalized(),
+            ))
+        };
+
+        let (ours, theirs) = (settings(self)?, settings(other)?);
+        if ours != theirs {
+            return Err(ScionicError::InvalidDag(format!(
+                "Built with different settings: {:?} and {:?}",
+                ours, theirs
+            )));
+        }
+        Ok(())
+    }
+
+    /// Rebuild the DAG after the files at `changed` were modified on disk
+    ///
+    /// `self` must be a full DAG built from the directory `root`. Only the
+    /// listed files are re-read: each is rebuilt with `config`, added if it
+    /// is new or dropped if it no longer exists. Only the directories on
+    /// their paths are rebuilt, and every other leaf is reused, so with the
+    /// config the DAG was built with the result matches a full rebuild. New
+    /// files must be in directories that already exist.
+    pub fn update_paths(
+        &self,
+        changed: &[&Path],
+        root: &Path,
+        config: DagBuilderConfig,
+    ) -> Result<Dag> {
+        self.root_directory()?;
+
+        let mut builder = DagBuilder::new();
+        let mut files = Vec::with_capacity(changed.len());
+        for path in changed {
+            let components = relative_components(path, root)?;
+            let leaf = if path.is_dir() {
+                return Err(ScionicError::InvalidType(format!(
+                    "{} is a directory, not a file",
+                    path.display()
+                )));
+            } else if path.exists() {
+                builder.count_file(path, fs::metadata(path)?.len(), &config)?;
+                let data = read_file(path)?;
+                Some(build_file_leaf(
+                    &components.join("/"),
+                    data,
+                    &mut builder,
+                    &config,
+                )?)
+            } else {
+                None
+            };
+            files.push((components, leaf));
+        }
+
+        self.with_file_changes(files, builder, &config)
+    }
+
+    /// Add the file `name` with content `data` to the directory at
+    /// `parent_path` (empty for the root)
+    ///
+    /// The file is built with `config` and only the directories on its path
+    /// are rebuilt, so with the config the DAG was built with the new root
+    /// matches a full rebuild. The directory must exist and must not already
+    /// have an entry named `name`.
+    pub fn add_file(
+        &mut self,
+        parent_path: &str,
+        name: &str,
+        data: Vec<u8>,
+        config: &DagBuilderConfig,
+    ) -> Result<()> {
+        if name.is_empty() || name.contains('/') {
+            return Err(ScionicError::InvalidName(name.to_string()));
+        }
+
+        let mut components: Vec<String> = path_components(parent_path)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        components.push(name.to_string());
+        let item_name = components.join("/");
+        if self.leaf_at_path(&item_name).is_some() {
+            return Err(ScionicError::InvalidDag(format!(
+                "{} already exists",
+                item_name
+            )));
+        }
+
+        let mut builder = DagBuilder::new();
+        let leaf = build_file_leaf(&item_name, data, &mut builder, config)?;
+        *self = self.with_file_changes(vec![(components, Some(leaf))], builder, config)?;
+        Ok(())
+    }
+
+    /// Remove the file at `path`, rebuilding the directories above it and
+    /// dropping the leaves no longer reachable
+    ///
+    /// The rebuilt directories are regrouped with `config`'s
+    /// `max_links_per_leaf`, so pass the config the DAG was built with.
+    pub fn remove_file(&mut self, path: &str, config: &DagBuilderConfig) -> Result<()> {
+        let components: Vec<String> = path_components(path)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let leaf = self
+            .leaf_at_path(path)
+            .ok_or_else(|| ScionicError::PathNotFound(path.to_string()))?;
+        if leaf.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidType(format!("{} is not a file", path)));
+        }
+
+        *self = self.with_file_changes(vec![(components, None)], DagBuilder::new(), config)?;
+        Ok(())
+    }
+
+    /// The root leaf, which must be a directory
+    fn root_directory(&self) -> Result<&DagLeaf> {
+        let root_leaf = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+        if root_leaf.leaf_type != LeafType::Directory {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is not a directory",
+                root_leaf.item_name
+            )));
+        }
+        Ok(root_leaf)
+    }
+
+    /// Set or remove files, given by their path components, rebuilding the
+    /// directories on their paths
+    ///
+    /// `builder` holds the leaves the new file leaves link to and the files
+    /// it normalized, which replace the changed paths in the root's list.
+    /// Rebuilt directories are split under link groups as `config` would
+    /// build them.
+    fn with_file_changes(
+        &self,
+        files: Vec<(Vec<String>, Option<DagLeaf>)>,
+        builder: DagBuilder,
+        config: &DagBuilderConfig,
+    ) -> Result<Dag> {
+        let root_leaf = self.root_directory()?;
+
+        let mut crlf_files = self.crlf_files();
+        for (components, _) in &files {
+            crlf_files.remove(&components.join("/"));
+        }
+        crlf_files.extend(builder.crlf_files());
+
+        let mut changes = BTreeMap::new();
+        for (components, leaf) in files {
+            let (name, parents) = components.split_last().expect("components are non-empty");
+            let mut dir = &mut changes;
+            for parent in parents {
+                let entry = dir
+                    .entry(parent.clone())
+                    .or_insert_with(|| Change::Dir(BTreeMap::new()));
+                dir = match entry {
+                    Change::Dir(children) => children,
+                    Change::File(_) => {
+                        return Err(ScionicError::InvalidType(format!(
+                            "{} is a file, not a directory",
+                            parent
+                        )))
+                    }
+                };
+            }
+            dir.insert(name.clone(), Change::File(leaf.map(Box::new)));
+        }
+
+        let mut leaves = self.leaves.clone();
+        leaves.extend(builder.leaves);
+        let entries = self.apply_changes(root_leaf, "", changes, &mut leaves, config)?;
+        let additional_data = with_crlf_files(
+            with_entry_count(&root_leaf.additional_data, entries.len()),
+            &crlf_files,
+        );
+        let links = regroup(root_leaf, entries, &mut leaves, config)?;
+
+        let mut leaves = reachable_leaves(&leaves, &links);
+        let root = root_leaf
+            .rebuilder(links)?
+            .build_root_leaf(&leaves, additional_data)?;
+        leaves.insert(root.hash.clone(), root.clone());
+
+        let mut dag = Dag {
+            root: root.hash,
+            leaves,
+            labels: None,
+            kind: Some(DagKind::Full),
+        };
+
+        if self.labels.is_some() {
+            let start = self.label_range().map_or(1, |(first, _)| first);
+            dag.calculate_labels_from(start)?;
+        }
+
+        Ok(dag)
+    }
+
+    /// Apply `changes` to the directory `dir` at `path`, rebuilding changed
+    /// subdirectories into `leaves`, and return the directory's new entries
+    /// before any grouping
+    fn apply_changes(
+        &self,
+        dir: &DagLeaf,
+        path: &str,
+        changes: BTreeMap<String, Change>,
+        leaves: &mut HashMap<String, DagLeaf>,
+        config: &DagBuilderConfig,
+    ) -> Result<Vec<String>> {
+        let mut links: Vec<String> = self
+            .linked_leaves(dir)?
+            .into_iter()
+            .map(|entry| entry.hash.clone())
+            .collect();
+
+        for (name, change) in changes {
+            let child_path = if path.is_empty() {
+                name
+            } else {
+                format!("{}/{}", path, name)
+            };
+            let existing = links.iter().position(|link| {
+                self.leaves
+                    .get(link)
+                    .is_some_and(|c| c.item_name == child_path)
+            });
+
+            match change {
+                Change::File(leaf) => {
+                    if let Some(index) = existing {
+                        if self.leaves[&links[index]].leaf_type != LeafType::File {
+                            return Err(ScionicError::InvalidType(format!(
+                                "{} is not a file",
+                                child_path
+                            )));
+                        }
+                        links.remove(index);
+                    }
+                    if let Some(leaf) = leaf {
+                        links.push(leaf.hash.clone());
+                        leaves.insert(leaf.hash.clone(), *leaf);
+                    }
+                }
+                Change::Dir(children) => {
+                    let index =
+                        existing.ok_or_else(|| ScionicError::PathNotFound(child_path.clone()))?;
+                    let child = &self.leaves[&links[index]];
+                    if child.leaf_type != LeafType::Directory {
+                        return Err(ScionicError::InvalidType(format!(
+                            "{} is not a directory",
+                            child_path
+                        )));
+                    }
+
+                    let entries =
+                        self.apply_changes(child, &child_path, children, leaves, config)?;
+                    let additional_data = with_entry_count(&child.additional_data, entries.len());
+                    let child_links = regroup(child, entries, leaves, config)?;
+                    let rebuilt = child.rebuilder(child_links)?.build_leaf(additional_data)?;
+                    links[index] = rebuilt.hash.clone();
+                    leaves.insert(rebuilt.hash.clone(), rebuilt);
+                }
+            }
+        }
+
+        // In name order as the builder reads them, which decides the groups
+        links.sort_by(|a, b| leaves[a].item_name.cmp(&leaves[b].item_name));
+        Ok(links)
+    }
+
+    /// The directory hierarchy alone, as a new DAG
+    ///
+    /// Every link to a file is pruned and each directory is rebuilt over its
+    /// remaining subdirectories, so the skeleton has a different root and
+    /// can't be verified against the original one. Recorded entry counts
+    /// are updated to match, and link groups are dropped with the
+    /// directories linking to their subdirectories directly.
+    pub fn skeleton(&self) -> Result<Dag> {
+        let root_leaf = self
+            .leaves
+            .get(&self.root)
+            .ok_or_else(|| ScionicError::MissingLeaf("Root leaf not found".to_string()))?;
+
+        if root_leaf.leaf_type != LeafType::Directory {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is not a directory",
+                root_leaf.item_name
+            )));
+        }
+
+        let mut leaves = HashMap::new();
+        let links = self.skeleton_links(root_leaf, &mut leaves)?;
+        let root = root_leaf.rebuilder(links.clone())?.build_root_leaf(
+            &leaves,
+            with_entry_count(&root_leaf.additional_data, links.len()),
+        )?;
+        leaves.insert(root.hash.clone(), root.clone());
+
+        Ok(Dag {
+            root: root.hash,
+            leaves,
+            labels: None,
+            kind: Some(DagKind::Full),
+        })
+    }
+
+    /// Rebuild the subdirectories of `leaf` without files, returning their hashes
+    fn skeleton_links(
+        &self,
+        leaf: &DagLeaf,
+        leaves: &mut HashMap<String, DagLeaf>,
+    ) -> Result<Vec<String>> {
+        let mut links = Vec::new();
+
+        for child in self.linked_leaves(leaf)? {
+            if child.leaf_type != LeafType::Directory {
+                continue;
+            }
+
+            let child_links = self.skeleton_links(child, leaves)?;
+            let additional_data = with_entry_count(&child.additional_data, child_links.len());
+            let rebuilt = child.rebuilder(child_links)?.build_leaf(additional_data)?;
+            links.push(rebuilt.hash.clone());
+            leaves.insert(rebuilt.hash.clone(), rebuilt);
+        }
+
+        Ok(links)
+    }
+}
+
+/// Split a changed path into its components relative to `root`
+fn relative_components(path: &Path, root: &Path) -> Result<Vec<String>> {
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|_| ScionicError::InvalidDag(format!("{} is outside the root", path.display())))?;
+
+    let components = relative
+        .iter()
+        .map(|c| {
+            c.to_str()
+                .map(str::to_string)
+                .ok_or_else(|| ScionicError::InvalidName(path.to_string_lossy().to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if components.is_empty() {
+        return Err(ScionicError::InvalidDag(
+            "The root itself can't be updated".to_string(),
+        ));
+    }
+    Ok(components)
+}
+
+/// Split a rebuilt directory's `entries` under link groups as `config`
+/// would, adding the groups to `leaves`, and return its links
+fn regroup(
+    dir: &DagLeaf,
+    entries: Vec<String>,
+    leaves: &mut HashMap<String, DagLeaf>,
+    config: &DagBuilderConfig,
+) -> Result<Vec<String>> {
+    let mut builder = DagBuilder::new();
+    let links = group_links(
+        &dir.item_name,
+        LeafType::Directory,
+        entries,
+        &mut builder,
+        config,
+    )?;
+    leaves.extend(builder.leaves);
+    Ok(links)
+}
+
+/// Copy of `additional_data` with any recorded entry count set to `count`
+fn with_entry_count(
+    additional_data: &Option<BTreeMap<String, String>>,
+    count: usize,
+) -> Option<BTreeMap<String, String>> {
+    let mut additional_data = additional_data.clone();
+    if let Some(data) = additional_data.as_mut() {
+        if data.contains_key(ENTRY_COUNT_KEY) {
+            data.insert(ENTRY_COUNT_KEY.to_string(), count.to_string());
+        }
+    }
+    additional_data
+}
+
+/// `name` with the prefix `old` replaced by `new`, as [`graft_links`]
+/// renames leaves (an empty `old` prefixes every name)
+fn graft_name(name: &str, old: &str, new: &str) -> String {
+    if old.is_empty() {
+        return format!("{}/{}", new, name);
+    }
+    if name == old {
+        return new.to_string();
+    }
+    match name.strip_prefix(&format!("{}/", old)) {
+        Some(rest) => format!("{}/{}", new, rest),
+        None => name.to_string(),
+    }
+}
+
+/// Copy of a root's `additional_data` without the keys only a root carries,
+/// for when it becomes a regular leaf
+fn without_root_keys(
+    additional_data: &Option<BTreeMap<String, String>>,
+) -> Option<BTreeMap<String, String>> {
+    let mut data = additional_data.clone()?;
+    for key in [
+        CHUNK_SIZE_KEY,
+        CHUNK_NAMING_KEY,
+        COALESCE_SMALL_TAIL_KEY,
+        LINE_ENDINGS_KEY,
+        CRLF_FILES_KEY,
+    ] {
+        data.remove(key);
+    }
+    (!data.is_empty()).then_some(data)
+}
+
+/// Rebuild the descendants of `leaf` in `subtree` with the name prefix
+/// `old` replaced by `new` (an empty `old` prefixes every name), returning
+/// the new links of `leaf`
+fn graft_links(
+    subtree: &Dag,
+    leaf: &DagLeaf,
+    old: &str,
+    new: &str,
+    leaves: &mut HashMap<String, DagLeaf>,
+) -> Result<Vec<String>> {
+    let mut links = Vec::with_capacity(leaf.links.len());
+
+    for link in &leaf.links {
+        let child = subtree
+            .leaves
+            .get(link)
+            .ok_or_else(|| ScionicError::MissingLeaf(link.clone()))?;
+
+        // Link groups take their owner's name and pass its prefix on
+        // Index-only chunk names don't carry the path and are kept
+        let (child_old, child_new) = if child.is_link_group() {
+            (old.to_string(), new.to_string())
+        } else {
+            let renamed = graft_name(&child.item_name, old, new);
+            (child.item_name.clone(), renamed)
+        };
+
+        let child_links = graft_links(subtree, child, &child_old, &child_new, leaves)?;
+        let mut renamed = child.rebuilder(child_links)?;
+        renamed.item_name = child_new;
+        let rebuilt = renamed.build_leaf(child.additional_data.clone())?;
+        links.push(rebuilt.hash.clone());
+        leaves.insert(rebuilt.hash.clone(), rebuilt);
+    }
+
+    Ok(links)
+}
+
+/// Non-empty components of a `/`-separated path
+fn path_components(path: &str) -> Vec<&str> {
+    path.split('/').filter(|c| !c.is_empty()).collect()
+}
+
+/// Copy of `links` with every occurrence of `old` replaced by `new`
+fn replace_link(links: &[String], old: &str, new: &str) -> Vec<String> {
+    links
+        .iter()
+        .map(|link| {
+            if link == old {
+                new.to_string()
+            } else {
+                link.clone()
+            }
+        })
+        .collect()
+}
+
+/// All leaves reachable from `links`, skipping links to missing leaves
+fn reachable_leaves(
+    leaves: &HashMap<String, DagLeaf>,
+    links: &[String],
+) -> HashMap<String, DagLeaf> {
+    let mut reachable = HashMap::new();
+    let mut stack: Vec<&String> = links.iter().collect();
+
+    while let Some(hash) = stack.pop() {
+        if reachable.contains_key(hash) {
+            continue;
+        }
+        if let Some(leaf) = leaves.get(hash) {
+            stack.extend(leaf.links.iter());
+            reachable.insert(hash.clone(), leaf.clone());
+        }
+    }
+
+    reachable
+}
@@ -49,6 +49,20 @@ pub enum ScionicError {
 
     #[error("Invalid type: {0}")]
     InvalidType(String),
+
+    #[error("Invalid name (not UTF-8): {0}")]
+    InvalidName(String),
+
+    #[cfg(feature = "git")]
+    #[error("Git error: {0}")]
+    Git(String),
+
+    #[error("File changed while reading: {path} (expected {expected} bytes, read {got})")]
+    FileChanged {
+        path: String,
+        expected: u64,
+        got: u64,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, ScionicError>;
@@ -0,0 +1,71 @@
+//! Building DAGs straight from git trees
+//!
+//! Walks a tree object and reads blobs from the object database, so no
+//! checkout is needed. The result matches [`create_dag_with_config`] run on a
+//! checkout of the same tree with the same root name.
+//!
+//! Like a filesystem build, the DAG doesn't record permissions, so executable
+//! and regular blobs produce the same leaves.
+//!
+//! [`create_dag_with_config`]: crate::create_dag_with_config
+
+use crate::builder::InMemoryDagBuilder;
+use crate::error::{Result, ScionicError};
+use crate::types::{Dag, DagBuilderConfig};
+use gix::object::tree::EntryKind;
+
+/// Create a DAG from a git tree object
+///
+/// The root is named by `config.root_name`, or `root` when unset. Symlinks
+/// and submodules aren't supported.
+pub fn create_dag_from_git(
+    repo: &gix::Repository,
+    tree_oid: gix::ObjectId,
+    config: DagBuilderConfig,
+) -> Result<Dag> {
+    add_tree(repo, tree_oid, "", Dag::builder().with_config(config))?.build()
+}
+
+/// Add every entry of a tree below `prefix`
+fn add_tree(
+    repo: &gix::Repository,
+    tree_oid: gix::ObjectId,
+    prefix: &str,
+    mut builder: InMemoryDagBuilder,
+) -> Result<InMemoryDagBuilder> {
+    let tree = repo.find_tree(tree_oid).map_err(git_error)?;
+
+    for entry in tree.iter() {
+        let entry = entry.map_err(git_error)?;
+        let name = std::str::from_utf8(entry.filename())
+            .map_err(|_| ScionicError::InvalidName(entry.filename().to_string()))?;
+
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        match entry.mode().kind() {
+            EntryKind::Tree => {
+                builder = add_tree(repo, entry.oid().to_owned(), &path, builder.add_dir(&path))?;
+            }
+            EntryKind::Blob | EntryKind::BlobExecutable => {
+                let blob = repo.find_blob(entry.oid()).map_err(git_error)?;
+                builder = builder.add_file(path, blob.data.clone());
+            }
+            EntryKind::Link | EntryKind::Commit => {
+                return Err(ScionicError::InvalidType(format!(
+                    "Unsupported git entry (symlink or submodule): {}",
+                    path
+                )));
+            }
+        }
+    }
+
+    Ok(builder)
+}
+
+fn git_error(e: impl std::fmt::Display) -> ScionicError {
+    ScionicError::Git(e.to_string())
+}
@@ -1,66 +1,134 @@
 use crate::error::{Result, ScionicError};
-use crate::merkle_tree::{build_merkle_root, MerkleTreeBuilder};
-use crate::types::{ClassicTreeBranch, DagLeaf, DagLeafBuilder, LeafType};
-use cid::Cid;
+use crate::merkle_tree::{merkle_root_for_links_with, MerkleTree};
+use crate::types::{
+    ChunkNaming, ClassicTreeBranch, DagLeaf, DagLeafBuilder, HashAlgorithm, LeafType,
+    CHUNK_NAMING_KEY, CHUNK_SIZE_KEY, COALESCE_SMALL_TAIL_KEY, CONTENT_ENCODING_KEY,
+    CRLF_FILES_KEY, ENTRY_COUNT_KEY, FILE_SIZE_KEY, LINE_ENDINGS_KEY, LINK_GROUP_KEY, MIME_KEY,
+    PREV_KEY, TIMESTAMP_KEY, UNIX_MODE_KEY,
+};
+use cid::{Cid, Version};
+use multibase::Base;
 use multihash::Multihash;
 use serde::Serialize;
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Sizes a root leaf records over its descendants
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DescendantTotals {
+    pub leaves: usize,
+    pub content_size: i64,
+    pub dag_size: i64,
+}
+
+impl DescendantTotals {
+    /// Count a (non-root) leaf, before any of its content is dropped
+    pub(crate) fn add(&mut self, leaf: &DagLeaf) -> Result<()> {
+        self.leaves += 1;
+        self.content_size += leaf.content.as_ref().map_or(0, |c| c.len() as i64);
+        self.dag_size += leaf.dag_size_contribution()?;
+        Ok(())
+    }
+}
 
-/// Convert CID to string (Go uses default base32 lower)
-fn cid_to_string(cid: &Cid) -> String {
-    cid.to_string()
+/// Whether `s` is a non-empty MIME-style token (letters, digits and
+/// `!#$&-^_.+`)
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
 }
 
-/// Sort a HashMap by keys and return as Vec of tuples
+/// Convert CID to string in `base` (Go uses base32 lower, the default)
+fn cid_to_string(cid: &Cid, base: Base) -> Result<String> {
+    cid.to_string_of_base(base)
+        .map_err(|e| ScionicError::InvalidCid(e.to_string()))
+}
+
+/// CID codec of every leaf (CBOR, matching Go)
+const CBOR_CODEC: u64 = 0x51;
+
+/// Parse a CID string
+pub(crate) fn parse_cid(hash: &str) -> Result<Cid> {
+    Cid::try_from(hash).map_err(|e| ScionicError::InvalidCid(e.to_string()))
+}
+
+/// Flatten the (already key-sorted) map into a Vec of tuples
 /// Always returns empty vec (never nil/null) to match Go's behavior
-fn sort_map_for_verification(map: &Option<HashMap<String, String>>) -> Vec<(String, String)> {
-    let m = map.clone().unwrap_or_default();
-    if m.is_empty() {
-        return Vec::new();
-    }
-    let mut pairs: Vec<_> = m.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
-    pairs.sort_by(|a, b| a.0.cmp(&b.0));
-    pairs
+fn sort_map_for_verification(map: &Option<BTreeMap<String, String>>) -> Vec<(String, String)> {
+    map.iter()
+        .flatten()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// Hashed fields of a root leaf
+#[derive(Serialize)]
+struct RootLeafData {
+    #[serde(rename = "ItemName")]
+    item_name: String,
+    #[serde(rename = "Type")]
+    leaf_type: String,
+    #[serde(rename = "MerkleRoot", with = "serde_bytes")]
+    merkle_root: Vec<u8>,
+    #[serde(rename = "CurrentLinkCount")]
+    current_link_count: usize,
+    #[serde(rename = "LeafCount")]
+    leaf_count: usize,
+    #[serde(rename = "ContentSize")]
+    content_size: i64,
+    #[serde(rename = "DagSize")]
+    dag_size: i64,
+    #[serde(rename = "ContentHash")]
+    content_hash: Option<serde_bytes::ByteBuf>,
+    #[serde(rename = "AdditionalData")]
+    additional_data: Vec<(String, String)>,
+}
+
+/// Leaf fields serialized when summing the DAG size
+/// Must match Go's CalculateTotalDagSize which serializes specific fields
+#[derive(Serialize)]
+struct LeafForSize {
+    #[serde(rename = "Hash")]
+    hash: String,
+    #[serde(rename = "ItemName")]
+    item_name: String,
+    #[serde(rename = "Type")]
+    leaf_type: String,
+    #[serde(rename = "ContentHash")]
+    content_hash: Option<serde_bytes::ByteBuf>,
+    #[serde(rename = "Content")]
+    content: Option<serde_bytes::ByteBuf>,
+    #[serde(rename = "ClassicMerkleRoot", with = "serde_bytes")]
+    classic_merkle_root: Vec<u8>,
+    #[serde(rename = "CurrentLinkCount")]
+    current_link_count: usize,
+    #[serde(rename = "LeafCount")]
+    leaf_count: usize,
+    #[serde(rename = "ContentSize")]
+    content_size: i64,
+    #[serde(rename = "DagSize")]
+    dag_size: i64,
+    #[serde(rename = "Links")]
+    links: Vec<String>,
+    #[serde(rename = "AdditionalData")]
+    additional_data: BTreeMap<String, String>,
 }
 
 impl DagLeafBuilder {
     /// Build a regular (non-root) leaf
-    pub fn build_leaf(self, additional_data: Option<HashMap<String, String>>) -> Result<DagLeaf> {
+    pub fn build_leaf(self, additional_data: Option<BTreeMap<String, String>>) -> Result<DagLeaf> {
         let leaf_type = self
             .leaf_type
             .ok_or_else(|| ScionicError::InvalidLeaf("Leaf must have a type".to_string()))?;
 
         // Build merkle root for links (matching TypeScript/Go behavior exactly)
-        let merkle_root = if self.links.len() > 1 {
-            // Sort links, hash each one, then build tree
-            let mut sorted_links = self.links.clone();
-            sorted_links.sort();
-
-            let hashed_leaves: Vec<_> = sorted_links
-                .iter()
-                .map(|link| {
-                    let mut hasher = Sha256::new();
-                    hasher.update(link.as_bytes());
-                    hasher.finalize().to_vec()
-                })
-                .collect();
-
-            Some(build_merkle_root(&hashed_leaves))
-        } else if self.links.len() == 1 {
-            let mut hasher = Sha256::new();
-            hasher.update(self.links[0].as_bytes());
-            Some(hasher.finalize().to_vec())
-        } else {
-            None
-        };
+        let merkle_root = merkle_root_for_links_with(&self.links, self.hash_algorithm);
 
         // Compute content hash
-        let content_hash = self.data.as_ref().map(|data| {
-            let mut hasher = Sha256::new();
-            hasher.update(data);
-            hasher.finalize().to_vec()
-        });
+        let content_hash = self
+            .data
+            .as_ref()
+            .map(|data| self.hash_algorithm.digest(data));
 
         // Create leaf data for hashing
         #[derive(Serialize)]
@@ -92,16 +160,14 @@ impl DagLeafBuilder {
         let serialized = serde_cbor::to_vec(&leaf_data)
             .map_err(|e| ScionicError::Serialization(e.to_string()))?;
 
-        // Create CID with SHA2-256 hash
-        let mut hasher = Sha256::new();
-        hasher.update(&serialized);
-        let hash_bytes = hasher.finalize();
+        // Create CID with the configured hash
+        let hash_bytes = self.hash_algorithm.digest(&serialized);
 
         // Create multihash from the hash bytes
-        let mh = Multihash::<64>::wrap(0x12, &hash_bytes) // 0x12 = SHA2-256
+        let mh = Multihash::<64>::wrap(self.hash_algorithm.multihash_code(), &hash_bytes)
             .map_err(|e| ScionicError::InvalidCid(e.to_string()))?;
 
-        let cid = Cid::new_v1(0x51, mh); // 0x51 = CBOR codec (matching Go)
+        let cid = Cid::new_v1(CBOR_CODEC, mh);
 
         // Sort links (for directories only, preserve order for files)
         let mut sorted_links = self.links.clone();
@@ -110,7 +176,7 @@ impl DagLeafBuilder {
         }
 
         Ok(DagLeaf {
-            hash: cid_to_string(&cid),
+            hash: cid_to_string(&cid, self.cid_base)?,
             item_name: self.item_name,
             leaf_type,
             content_hash,
@@ -124,6 +190,7 @@ impl DagLeafBuilder {
             parent_hash: None,
             additional_data,
             proofs: None,
+            extra: BTreeMap::new(),
         })
     }
 
@@ -131,110 +198,49 @@ impl DagLeafBuilder {
     pub fn build_root_leaf(
         self,
         leaves: &HashMap<String, DagLeaf>,
-        additional_data: Option<HashMap<String, String>>,
+        additional_data: Option<BTreeMap<String, String>>,
+    ) -> Result<DagLeaf> {
+        // Leaves are keyed by CID, so a leaf shared by several parents (e.g.
+        // an identical chunk) is counted once
+        let mut totals = DescendantTotals::default();
+        for leaf in leaves.values() {
+            totals.add(leaf)?;
+        }
+
+        self.build_root_leaf_with_totals(totals, additional_data)
+    }
+
+    /// Build the root leaf from running totals over its descendants, for
+    /// builds that don't keep every leaf
+    pub(crate) fn build_root_leaf_with_totals(
+        self,
+        totals: DescendantTotals,
+        additional_data: Option<BTreeMap<String, String>>,
     ) -> Result<DagLeaf> {
         let leaf_type = self
             .leaf_type
             .ok_or_else(|| ScionicError::InvalidLeaf("Leaf must have a type".to_string()))?;
 
         // Build merkle root for links (matching TypeScript/Go behavior exactly)
-        let merkle_root = if self.links.len() > 1 {
-            // Sort links, hash each one, then build tree
-            let mut sorted_links = self.links.clone();
-            sorted_links.sort();
-
-            let hashed_leaves: Vec<_> = sorted_links
-                .iter()
-                .map(|link| {
-                    let mut hasher = Sha256::new();
-                    hasher.update(link.as_bytes());
-                    hasher.finalize().to_vec()
-                })
-                .collect();
-
-            Some(build_merkle_root(&hashed_leaves))
-        } else if self.links.len() == 1 {
-            let mut hasher = Sha256::new();
-            hasher.update(self.links[0].as_bytes());
-            Some(hasher.finalize().to_vec())
-        } else {
-            None
-        };
+        let merkle_root = merkle_root_for_links_with(&self.links, self.hash_algorithm);
 
         // Calculate content size
-        let mut content_size: i64 = 0;
-        for leaf in leaves.values() {
-            if let Some(ref content) = leaf.content {
-                content_size += content.len() as i64;
-            }
-        }
+        let mut content_size = totals.content_size;
         if let Some(ref data) = self.data {
             content_size += data.len() as i64;
         }
 
         // Compute content hash
-        let content_hash = self.data.as_ref().map(|data| {
-            let mut hasher = Sha256::new();
-            hasher.update(data);
-            hasher.finalize().to_vec()
-        });
+        let content_hash = self
+            .data
+            .as_ref()
+            .map(|data| self.hash_algorithm.digest(data));
 
-        let leaf_count = leaves.len() + 1; // +1 for root itself
+        let leaf_count = totals.leaves + 1; // +1 for root itself
 
-        // Calculate children DAG size by serializing each child leaf
+        // Children DAG size from serializing each child leaf
         // Must match Go's CalculateTotalDagSize which serializes specific fields
-        let mut children_dag_size: i64 = 0;
-        for (_hash, leaf) in leaves.iter() {
-            #[derive(Serialize)]
-            struct LeafForSize {
-                #[serde(rename = "Hash")]
-                hash: String,
-                #[serde(rename = "ItemName")]
-                item_name: String,
-                #[serde(rename = "Type")]
-                leaf_type: String,
-                #[serde(rename = "ContentHash")]
-                content_hash: Option<serde_bytes::ByteBuf>,
-                #[serde(rename = "Content")]
-                content: Option<serde_bytes::ByteBuf>,
-                #[serde(rename = "ClassicMerkleRoot", with = "serde_bytes")]
-                classic_merkle_root: Vec<u8>,
-                #[serde(rename = "CurrentLinkCount")]
-                current_link_count: usize,
-                #[serde(rename = "LeafCount")]
-                leaf_count: usize,
-                #[serde(rename = "ContentSize")]
-                content_size: i64,
-                #[serde(rename = "DagSize")]
-                dag_size: i64,
-                #[serde(rename = "Links")]
-                links: Vec<String>,
-                #[serde(rename = "AdditionalData")]
-                additional_data: HashMap<String, String>,
-            }
-
-            let mut sorted_links = leaf.links.clone();
-            sorted_links.sort();
-
-            let leaf_for_size = LeafForSize {
-                hash: leaf.hash.clone(),
-                item_name: leaf.item_name.clone(),
-                leaf_type: leaf.leaf_type.to_string(),
-                content_hash: leaf.content_hash.clone().map(serde_bytes::ByteBuf::from),
-                content: leaf.content.clone().map(serde_bytes::ByteBuf::from),
-                classic_merkle_root: leaf.classic_merkle_root.clone().unwrap_or_default(),
-                current_link_count: leaf.current_link_count,
-                leaf_count: leaf.leaf_count.unwrap_or(0),
-                content_size: leaf.content_size.unwrap_or(0),
-                dag_size: leaf.dag_size.unwrap_or(0),
-                links: sorted_links,
-                additional_data: leaf.additional_data.clone().unwrap_or_default(),
-            };
-
-            let leaf_cbor = serde_cbor::to_vec(&leaf_for_size)
-                .map_err(|e| ScionicError::Serialization(e.to_string()))?;
-            children_dag_size += leaf_cbor.len() as i64;
-        }
+        let children_dag_size = totals.dag_size;
 
         // First pass: calculate temporary root size with DagSize=0
         let temp_leaf_data = RootLeafData {
@@ -257,28 +263,6 @@ impl DagLeafBuilder {
         let dag_size = children_dag_size + root_leaf_size;
 
         // Second pass: Create final leaf data for hashing
-        #[derive(Serialize)]
-        struct RootLeafData {
-            #[serde(rename = "ItemName")]
-            item_name: String,
-            #[serde(rename = "Type")]
-            leaf_type: String,
-            #[serde(rename = "MerkleRoot", with = "serde_bytes")]
-            merkle_root: Vec<u8>,
-            #[serde(rename = "CurrentLinkCount")]
-            current_link_count: usize,
-            #[serde(rename = "LeafCount")]
-            leaf_count: usize,
-            #[serde(rename = "ContentSize")]
-            content_size: i64,
-            #[serde(rename = "DagSize")]
-            dag_size: i64,
-            #[serde(rename = "ContentHash")]
-            content_hash: Option<serde_bytes::ByteBuf>,
-            #[serde(rename = "AdditionalData")]
-            additional_data: Vec<(String, String)>,
-        }
-
         let leaf_data = RootLeafData {
             item_name: self.item_name.clone(),
             leaf_type: leaf_type.to_string(),
@@ -295,16 +279,14 @@ impl DagLeafBuilder {
         let serialized = serde_cbor::to_vec(&leaf_data)
             .map_err(|e| ScionicError::Serialization(e.to_string()))?;
 
-        // Create CID with SHA2-256 hash
-        let mut hasher_cid = Sha256::new();
-        hasher_cid.update(&serialized);
-        let hash_bytes = hasher_cid.finalize();
+        // Create CID with the configured hash
+        let hash_bytes = self.hash_algorithm.digest(&serialized);
 
         // Create multihash from the hash bytes
-        let mh = Multihash::<64>::wrap(0x12, &hash_bytes) // 0x12 = SHA2-256
+        let mh = Multihash::<64>::wrap(self.hash_algorithm.multihash_code(), &hash_bytes)
             .map_err(|e| ScionicError::InvalidCid(e.to_string()))?;
 
-        let cid = Cid::new_v1(0x51, mh); // 0x51 = CBOR codec (matching Go)
+        let cid = Cid::new_v1(CBOR_CODEC, mh);
 
         // Sort links (for directories only)
         let mut sorted_links = self.links.clone();
@@ -313,7 +295,7 @@ impl DagLeafBuilder {
         }
 
         Ok(DagLeaf {
-            hash: cid_to_string(&cid),
+            hash: cid_to_string(&cid, self.cid_base)?,
             item_name: self.item_name,
             leaf_type,
             content_hash,
@@ -327,13 +309,317 @@ impl DagLeafBuilder {
             parent_hash: None,
             additional_data,
             proofs: None,
+            extra: BTreeMap::new(),
         })
     }
 }
 
 impl DagLeaf {
+    /// Hash algorithm recorded in this leaf's CID
+    ///
+    /// Fails with `InvalidCid` unless the CID is a CIDv1 with the CBOR codec
+    /// and a supported multihash.
+    pub fn hash_algorithm(&self) -> Result<HashAlgorithm> {
+        let cid = parse_cid(&self.hash)?;
+        let algorithm = HashAlgorithm::from_multihash_code(cid.hash().code());
+
+        match algorithm {
+            Some(algorithm) if cid.version() == Version::V1 && cid.codec() == CBOR_CODEC => {
+                Ok(algorithm)
+            }
+            _ => Err(ScionicError::InvalidCid(format!(
+                "{} is not a CIDv1 with codec {:#x} and a supported multihash",
+                self.hash, CBOR_CODEC
+            ))),
+        }
+    }
+
+    /// Check structural invariants that must hold before hashing, returning
+    /// the leaf's hash algorithm
+    fn verify_structure(&self) -> Result<HashAlgorithm> {
+        let algorithm = self.hash_algorithm()?;
+
+        // A chunk's content may be dropped, but it is never empty
+        if self.leaf_type == LeafType::Chunk && self.content.as_ref().is_some_and(Vec::is_empty) {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "empty chunk: {}",
+                self.hash
+            )));
+        }
+
+        let mut seen = HashSet::with_capacity(self.links.len());
+        if let Some(link) = self.links.iter().find(|link| !seen.insert(*link)) {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "duplicate link: {}",
+                link
+            )));
+        }
+
+        if self.current_link_count != self.links.len() {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "link count mismatch: stored {}, actual {}",
+                self.current_link_count,
+                self.links.len()
+            )));
+        }
+
+        // build_leaf only sets a merkle root over links
+        let has_merkle_root = self
+            .classic_merkle_root
+            .as_ref()
+            .is_some_and(|root| !root.is_empty());
+        if has_merkle_root == self.links.is_empty() {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "{}: {}",
+                if has_merkle_root {
+                    "merkle root without links"
+                } else {
+                    "links without a merkle root"
+                },
+                self.hash
+            )));
+        }
+
+        // The CID covers the merkle root only, so the links must make it up
+        if has_merkle_root
+            && merkle_root_for_links_with(&self.links, algorithm) != self.classic_merkle_root
+        {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "links don't match the merkle root: {}",
+                self.hash
+            )));
+        }
+
+        if let Some(data) = &self.additional_data {
+            if data.contains_key(ENTRY_COUNT_KEY) {
+                let entry_count = self.entry_count().ok_or_else(|| {
+                    ScionicError::InvalidLeaf(format!("Invalid entry count on leaf {}", self.hash))
+                })?;
+                // Link groups hold several entries per link
+                if entry_count < self.current_link_count {
+                    return Err(ScionicError::InvalidLeaf(format!(
+                        "entry count mismatch: stored {}, actual {}",
+                        entry_count, self.current_link_count
+                    )));
+                }
+            }
+        }
+
+        Ok(algorithm)
+    }
+
+    /// Multibase this leaf's CID is written in
+    pub fn cid_base(&self) -> Result<Base> {
+        self.hash
+            .chars()
+            .next()
+            .and_then(|code| Base::from_code(code).ok())
+            .ok_or_else(|| {
+                ScionicError::InvalidCid(format!("{} has no multibase prefix", self.hash))
+            })
+    }
+
+    /// Links parsed as CIDs, for use with IPLD tooling
+    pub fn link_cids(&self) -> Result<Vec<Cid>> {
+        self.links.iter().map(|link| parse_cid(link)).collect()
+    }
+
+    /// Number of bytes this leaf contributes to the root's `dag_size`
+    ///
+    /// This is the CBOR size of the fields Go's `CalculateTotalDagSize`
+    /// serializes for every non-root leaf.
+    pub fn dag_size_contribution(&self) -> Result<i64> {
+        let mut sorted_links = self.links.clone();
+        sorted_links.sort();
+
+        let leaf_for_size = LeafForSize {
+            hash: self.hash.clone(),
+            item_name: self.item_name.clone(),
+            leaf_type: self.leaf_type.to_string(),
+            content_hash: self.content_hash.clone().map(serde_bytes::ByteBuf::from),
+            content: self.content.clone().map(serde_bytes::ByteBuf::from),
+            classic_merkle_root: self.classic_merkle_root.clone().unwrap_or_default(),
+            current_link_count: self.current_link_count,
+            leaf_count: self.leaf_count.unwrap_or(0),
+            content_size: self.content_size.unwrap_or(0),
+            dag_size: self.dag_size.unwrap_or(0),
+            links: sorted_links,
+            additional_data: self.additional_data.clone().unwrap_or_default(),
+        };
+
+        let leaf_cbor = serde_cbor::to_vec(&leaf_for_size)
+            .map_err(|e| ScionicError::Serialization(e.to_string()))?;
+        Ok(leaf_cbor.len() as i64)
+    }
+
+    /// Number of bytes a root leaf contributes to its own `dag_size`
+    ///
+    /// This is the size of the root's hashed fields with `DagSize` zeroed.
+    pub fn root_dag_size_contribution(&self) -> Result<i64> {
+        let leaf_data = RootLeafData {
+            item_name: self.item_name.clone(),
+            leaf_type: self.leaf_type.to_string(),
+            merkle_root: self.classic_merkle_root.clone().unwrap_or_default(),
+            current_link_count: self.current_link_count,
+            leaf_count: self.leaf_count.unwrap_or(0),
+            content_size: self.content_size.unwrap_or(0),
+            dag_size: 0,
+            content_hash: self.content_hash.clone().map(serde_bytes::ByteBuf::from),
+            additional_data: sort_map_for_verification(&self.additional_data),
+        };
+
+        let serialized = serde_cbor::to_vec(&leaf_data)
+            .map_err(|e| ScionicError::Serialization(e.to_string()))?;
+        Ok(serialized.len() as i64)
+    }
+
+    /// Number of entries recorded on a directory leaf (see `include_entry_counts`)
+    pub fn entry_count(&self) -> Option<usize> {
+        self.additional_data
+            .as_ref()?
+            .get(ENTRY_COUNT_KEY)?
+            .parse()
+            .ok()
+    }
+
+    /// Size recorded on a file leaf (see `include_file_sizes`)
+    pub fn file_size(&self) -> Option<u64> {
+        self.additional_data
+            .as_ref()?
+            .get(FILE_SIZE_KEY)?
+            .parse()
+            .ok()
+    }
+
+    /// Whether this is an intermediate leaf holding some of its parent's
+    /// links (see `max_links_per_leaf`)
+    pub fn is_link_group(&self) -> bool {
+        self.additional_data
+            .as_ref()
+            .is_some_and(|data| data.contains_key(LINK_GROUP_KEY))
+    }
+
+    /// The target path of a symlink leaf
+    pub fn symlink_target(&self) -> Result<&str> {
+        if self.leaf_type != LeafType::Symlink {
+            return Err(ScionicError::InvalidType(format!(
+                "{} is not a symlink",
+                self.item_name
+            )));
+        }
+
+        self.content
+            .as_deref()
+            .and_then(|content| std::str::from_utf8(content).ok())
+            .ok_or_else(|| {
+                ScionicError::InvalidLeaf(format!("Symlink {} has no UTF-8 target", self.item_name))
+            })
+    }
+
+    /// Check that the stored content matches the stored content hash
+    ///
+    /// The CID covers only the content hash, so this is what ties the
+    /// content bytes to the CID.
+    pub(crate) fn verify_content_hash(&self) -> Result<()> {
+        if let (Some(content), Some(expected)) = (&self.content, &self.content_hash) {
+            if self.hash_algorithm()?.digest(content) != *expected {
+                return Err(ScionicError::ContentHashMismatch);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that `content` and `content_hash` are present where the leaf's
+    /// type calls for them
+    ///
+    /// Directories and chunked files carry neither; chunks and non-empty
+    /// inline files carry both.
+    pub(crate) fn verify_content_fields(&self) -> Result<()> {
+        let has_content = self.content.as_ref().is_some_and(|c| !c.is_empty());
+        let problem = match self.leaf_type {
+            LeafType::Chunk if !has_content => Some("empty chunk"),
+            LeafType::Directory if self.content.is_some() => Some("directory has content"),
+            LeafType::Directory if self.content_hash.is_some() => {
+                Some("directory has a content hash")
+            }
+            LeafType::File if !self.links.is_empty() && self.content.is_some() => {
+                Some("chunked file has inline content")
+            }
+            LeafType::File if !self.links.is_empty() && self.content_hash.is_some() => {
+                Some("chunked file has a content hash")
+            }
+            LeafType::File | LeafType::Chunk if has_content && self.content_hash.is_none() => {
+                Some("content without a content hash")
+            }
+            LeafType::File | LeafType::Chunk
+                if self.content.is_none() && self.content_hash.is_some() =>
+            {
+                Some("content hash without content")
+            }
+            LeafType::Symlink if !has_content || self.content_hash.is_none() => {
+                Some("symlink without a target")
+            }
+            LeafType::Symlink if !self.links.is_empty() => Some("symlink has links"),
+            _ => None,
+        };
+
+        match problem {
+            Some(problem) => Err(ScionicError::InvalidLeaf(format!(
+                "{}: {} ({})",
+                problem, self.item_name, self.hash
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that reserved additional data keys, when present, have
+    /// well-formed values
+    ///
+    /// `timestamp` must be RFC 3339, `prev` a CID, `unix_mode` octal
+    /// permission bits, `mime` a `type/subtype` pair and `content_encoding`
+    /// a single token; the keys this crate writes (file size, link group
+    /// chunking parameters, line endings and CRLF files) must parse as it
+    /// writes them.
+    pub fn verify_reserved_keys(&self) -> Result<()> {
+        let Some(data) = &self.additional_data else {
+            return Ok(());
+        };
+
+        for (key, value) in data {
+            let valid = match key.as_str() {
+                TIMESTAMP_KEY => chrono::DateTime::parse_from_rfc3339(value).is_ok(),
+                PREV_KEY => parse_cid(value).is_ok(),
+                UNIX_MODE_KEY => u32::from_str_radix(value, 8).is_ok_and(|mode| mode <= 0o7777),
+                MIME_KEY => value
+                    .split_once('/')
+                    .is_some_and(|(kind, subtype)| is_token(kind) && is_token(subtype)),
+                CONTENT_ENCODING_KEY => is_token(value),
+                FILE_SIZE_KEY => value.parse::<u64>().is_ok(),
+                LINK_GROUP_KEY => value == "true",
+                CHUNK_SIZE_KEY => value.parse::<usize>().is_ok(),
+                CHUNK_NAMING_KEY => ChunkNaming::from_name(value).is_some(),
+                COALESCE_SMALL_TAIL_KEY => value.parse::<f32>().is_ok_and(f32::is_finite),
+                LINE_ENDINGS_KEY => value == "lf",
+                CRLF_FILES_KEY => serde_json::from_str::<Vec<String>>(value).is_ok(),
+                _ => true,
+            };
+
+            if !valid {
+                return Err(ScionicError::InvalidLeaf(format!(
+                    "Malformed {} {:?} on leaf {}",
+                    key, value, self.hash
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Verify a regular (non-root) leaf
     pub fn verify_leaf(&self) -> Result<()> {
+        let algorithm = self.verify_structure()?;
+
         #[derive(Serialize)]
         struct LeafData {
             #[serde(rename = "ItemName")]
@@ -363,19 +649,17 @@ impl DagLeaf {
         let serialized = serde_cbor::to_vec(&leaf_data)
             .map_err(|e| ScionicError::Serialization(e.to_string()))?;
 
-        // Create CID with SHA2-256 hash
-        let mut hasher_cid = Sha256::new();
-        hasher_cid.update(&serialized);
-        let hash_bytes = hasher_cid.finalize();
+        // Create CID with the hash the stored CID claims
+        let hash_bytes = algorithm.digest(&serialized);
 
         // Create multihash from the hash bytes
-        let mh = Multihash::<64>::wrap(0x12, &hash_bytes) // 0x12 = SHA2-256
+        let mh = Multihash::<64>::wrap(algorithm.multihash_code(), &hash_bytes)
             .map_err(|e| ScionicError::InvalidCid(e.to_string()))?;
 
-        let cid = Cid::new_v1(0x51, mh); // 0x51 = CBOR codec (matching Go)
+        let cid = Cid::new_v1(CBOR_CODEC, mh);
 
         // Compare with stored hash
-        let computed_hash = cid_to_string(&cid);
+        let computed_hash = cid_to_string(&cid, self.cid_base()?)?;
         if computed_hash != self.hash {
             return Err(ScionicError::HashMismatch {
                 expected: self.hash.clone(),
@@ -388,27 +672,7 @@ impl DagLeaf {
 
     /// Verify root leaf (includes leaf count and sizes)
     pub fn verify_root_leaf(&self) -> Result<()> {
-        #[derive(Serialize)]
-        struct RootLeafData {
-            #[serde(rename = "ItemName")]
-            item_name: String,
-            #[serde(rename = "Type")]
-            leaf_type: String,
-            #[serde(rename = "MerkleRoot", with = "serde_bytes")]
-            merkle_root: Vec<u8>,
-            #[serde(rename = "CurrentLinkCount")]
-            current_link_count: usize,
-            #[serde(rename = "LeafCount")]
-            leaf_count: usize,
-            #[serde(rename = "ContentSize")]
-            content_size: i64,
-            #[serde(rename = "DagSize")]
-            dag_size: i64,
-            #[serde(rename = "ContentHash")]
-            content_hash: Option<serde_bytes::ByteBuf>,
-            #[serde(rename = "AdditionalData")]
-            additional_data: Vec<(String, String)>,
-        }
+        let algorithm = self.verify_structure()?;
 
         let leaf_data = RootLeafData {
             item_name: self.item_name.clone(),
@@ -426,19 +690,17 @@ impl DagLeaf {
         let serialized = serde_cbor::to_vec(&leaf_data)
             .map_err(|e| ScionicError::Serialization(e.to_string()))?;
 
-        // Create CID with SHA2-256 hash
-        let mut hasher_cid = Sha256::new();
-        hasher_cid.update(&serialized);
-        let hash_bytes = hasher_cid.finalize();
+        // Create CID with the hash the stored CID claims
+        let hash_bytes = algorithm.digest(&serialized);
 
         // Create multihash from the hash bytes
-        let mh = Multihash::<64>::wrap(0x12, &hash_bytes) // 0x12 = SHA2-256
+        let mh = Multihash::<64>::wrap(algorithm.multihash_code(), &hash_bytes)
             .map_err(|e| ScionicError::InvalidCid(e.to_string()))?;
 
-        let cid = Cid::new_v1(0x51, mh); // 0x51 = CBOR codec (matching Go)
+        let cid = Cid::new_v1(CBOR_CODEC, mh);
 
         // Compare with stored hash
-        let computed_hash = cid_to_string(&cid);
+        let computed_hash = cid_to_string(&cid, self.cid_base()?)?;
         if computed_hash != self.hash {
             return Err(ScionicError::HashMismatch {
                 expected: self.hash.clone(),
@@ -460,12 +722,15 @@ impl DagLeaf {
             return Ok(None);
         }
 
-        // Build merkle tree
-        let mut builder = MerkleTreeBuilder::new();
-        for link in &self.links {
-            builder.add_leaf(link.clone(), link.as_bytes().to_vec());
-        }
-        let tree = builder.build()?;
+        // Build merkle tree over the sorted links, matching classic_merkle_root
+        let mut sorted_links = self.links.clone();
+        sorted_links.sort();
+
+        let data = sorted_links
+            .iter()
+            .map(|link| (link.clone(), link.as_bytes().to_vec()))
+            .collect();
+        let tree = MerkleTree::with_algorithm(data, self.hash_algorithm()?)?;
 
         // Get proof for the key
         let index = tree
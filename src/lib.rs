@@ -76,23 +76,55 @@
 //! # }
 //! ```
 
+#[cfg(feature = "tar")]
+pub mod archive;
+pub mod assembler;
+pub mod builder;
+pub mod car;
+pub mod collector;
 pub mod dag;
 pub mod diff;
+pub mod edit;
 pub mod error;
+#[cfg(feature = "git")]
+pub mod git;
 pub mod leaf;
 pub mod merkle_tree;
+pub mod pool;
+pub mod proof;
 pub mod serialize;
+#[cfg(feature = "tokio")]
+pub mod sink;
+pub mod stats;
+pub mod store;
 pub mod streaming;
 pub mod types;
 
 // Re-export commonly used items
-pub use dag::{create_dag, create_dag_with_config};
+pub use assembler::PacketAssembler;
+pub use builder::{create_dag_from_entries, InMemoryDagBuilder};
+pub use car::CarDag;
+pub use collector::ChunkCollector;
+pub use dag::{
+    chunk_byte_range, chunk_count_for, create_dag, create_dag_from_bytes, create_dag_with_config,
+    create_dags,
+};
 pub use diff::{diff, diff_from_new_leaves, DagDiff, DiffSummary, DiffType, LeafDiff};
 pub use error::{Result, ScionicError};
+#[cfg(feature = "git")]
+pub use git::create_dag_from_git;
+pub use pool::LeafPool;
+pub use proof::{verify_labeled_leaf, InclusionProof, ProofStep};
+pub use serialize::{FileHeader, PayloadKind};
+#[cfg(feature = "tokio")]
+pub use sink::{create_dag_streaming_to_sink, AsyncBlockSink};
+pub use stats::{ChunkDedupReport, TypeCounts};
+pub use store::{apply_and_verify_to_store, BlockStore};
 pub use streaming::{create_dag_from_stream, StreamingDagBuilder};
 pub use types::{
-    ClassicTreeBranch, Dag, DagBuilderConfig, DagLeaf, DagLeafBuilder, LeafType, MerkleProof,
-    TransmissionPacket, DEFAULT_CHUNK_SIZE,
+    ChunkNaming, ChunkParams, ClassicTreeBranch, CreateDirectoryMode, Dag, DagBuilderConfig,
+    DagKind, DagLeaf, DagLeafBuilder, HashAlgorithm, LeafType, MerkleProof, SymlinkMode,
+    TransmissionPacket, VerifyOptions, VerifyProgress, DEFAULT_CHUNK_SIZE,
 };
 
 // Version information
@@ -185,11 +217,7 @@ mod tests {
         assert!(!packets.is_empty());
 
         // Create a new DAG and apply packets
-        let mut new_dag = Dag {
-            root: dag.root.clone(),
-            leaves: std::collections::HashMap::new(),
-            labels: None,
-        };
+        let mut new_dag = Dag::receiving(dag.root.clone());
 
         for packet in packets {
             new_dag.apply_and_verify_transmission_packet(packet)?;
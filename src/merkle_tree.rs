@@ -1,6 +1,5 @@
 use crate::error::{Result, ScionicError};
-use crate::types::MerkleProof;
-use sha2::{Digest, Sha256};
+use crate::types::{HashAlgorithm, MerkleProof};
 use std::collections::HashMap;
 
 /// Classic Merkle Tree implementation
@@ -14,11 +13,19 @@ pub struct MerkleTree {
 
     /// Mapping of keys to indices
     key_to_index: HashMap<String, usize>,
+
+    /// Hash function for leaves and nodes
+    algorithm: HashAlgorithm,
 }
 
 impl MerkleTree {
     /// Create a new Merkle tree from data blocks
     pub fn new(data: Vec<(String, Vec<u8>)>) -> Result<Self> {
+        Self::with_algorithm(data, HashAlgorithm::Sha256)
+    }
+
+    /// Create a new Merkle tree from data blocks using the given hash function
+    pub fn with_algorithm(data: Vec<(String, Vec<u8>)>, algorithm: HashAlgorithm) -> Result<Self> {
         if data.is_empty() {
             return Err(ScionicError::InvalidLeaf(
                 "Cannot create tree with no data".to_string(),
@@ -30,21 +37,18 @@ impl MerkleTree {
 
         // Hash each data block to create leaves
         for (i, (key, value)) in data.iter().enumerate() {
-            let mut hasher = Sha256::new();
-            hasher.update(value);
-            let leaf_hash = hasher.finalize().to_vec();
-
-            leaves.push(leaf_hash);
+            leaves.push(algorithm.digest(value));
             key_to_index.insert(key.clone(), i);
         }
 
         // Build the tree
-        let (root, proofs) = build_tree(&leaves);
+        let (root, proofs) = build_tree(&leaves, algorithm);
 
         Ok(Self {
             root,
             proofs,
             key_to_index,
+            algorithm,
         })
     }
 
@@ -55,12 +59,12 @@ impl MerkleTree {
 
     /// Verify a proof against the root
     pub fn verify(&self, data: &[u8], proof: &MerkleProof) -> Result<()> {
-        verify_proof(data, proof, &self.root)
+        verify_proof_with(data, proof, &self.root, self.algorithm)
     }
 }
 
 /// Build a Merkle tree and generate proofs
-fn build_tree(leaves: &[Vec<u8>]) -> (Vec<u8>, Vec<MerkleProof>) {
+fn build_tree(leaves: &[Vec<u8>], algorithm: HashAlgorithm) -> (Vec<u8>, Vec<MerkleProof>) {
     if leaves.is_empty() {
         return (vec![], vec![]);
     }
@@ -73,26 +77,26 @@ fn build_tree(leaves: &[Vec<u8>]) -> (Vec<u8>, Vec<MerkleProof>) {
         return (leaves[0].clone(), vec![proof]);
     }
 
-    // Build levels from bottom up
+    // Build levels from bottom up, duplicating the last node of odd levels
+    // exactly like build_merkle_root so proofs verify against stored roots
     let mut current_level = leaves.to_vec();
-    let mut all_levels = vec![current_level.clone()];
+    let mut all_levels = Vec::new();
 
     while current_level.len() > 1 {
-        let mut next_level = Vec::new();
-
-        for chunk in current_level.chunks(2) {
-            let hash = if chunk.len() == 2 {
-                hash_pair(&chunk[0], &chunk[1])
-            } else {
-                // Odd number, promote the single node
-                chunk[0].clone()
-            };
-            next_level.push(hash);
+        if current_level.len() % 2 == 1 {
+            let last = current_level.last().unwrap().clone();
+            current_level.push(last);
         }
+        all_levels.push(current_level.clone());
+
+        let next_level: Vec<Vec<u8>> = current_level
+            .chunks(2)
+            .map(|chunk| hash_pair(&chunk[0], &chunk[1], algorithm))
+            .collect();
 
         current_level = next_level;
-        all_levels.push(current_level.clone());
     }
+    all_levels.push(current_level.clone());
 
     let root = current_level[0].clone();
 
@@ -121,10 +125,7 @@ fn generate_proof(leaf_index: usize, levels: &[Vec<Vec<u8>>]) -> MerkleProof {
         }
 
         let sibling_index = if is_right { index - 1 } else { index + 1 };
-
-        if sibling_index < level.len() {
-            siblings.push(serde_bytes::ByteBuf::from(level[sibling_index].clone()));
-        }
+        siblings.push(serde_bytes::ByteBuf::from(level[sibling_index].clone()));
 
         index /= 2;
     }
@@ -133,27 +134,32 @@ fn generate_proof(leaf_index: usize, levels: &[Vec<Vec<u8>>]) -> MerkleProof {
 }
 
 /// Hash a pair of nodes
-fn hash_pair(left: &[u8], right: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(left);
-    hasher.update(right);
-    hasher.finalize().to_vec()
+fn hash_pair(left: &[u8], right: &[u8], algorithm: HashAlgorithm) -> Vec<u8> {
+    algorithm.digest(&[left, right].concat())
 }
 
-/// Verify a Merkle proof
+/// Verify a SHA-256 Merkle proof
 pub fn verify_proof(data: &[u8], proof: &MerkleProof, root: &[u8]) -> Result<()> {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    let mut current_hash = hasher.finalize().to_vec();
+    verify_proof_with(data, proof, root, HashAlgorithm::Sha256)
+}
+
+/// Verify a Merkle proof using the given hash function
+pub fn verify_proof_with(
+    data: &[u8],
+    proof: &MerkleProof,
+    root: &[u8],
+    algorithm: HashAlgorithm,
+) -> Result<()> {
+    let mut current_hash = algorithm.digest(data);
 
     for (depth, sibling) in proof.siblings.iter().enumerate() {
         // Check bit in path - if set, sibling is on right (we're on left)
         let sibling_on_right = (proof.path & (1 << depth)) != 0;
 
         current_hash = if sibling_on_right {
-            hash_pair(&current_hash, sibling.as_slice())
+            hash_pair(&current_hash, sibling.as_slice(), algorithm)
         } else {
-            hash_pair(sibling.as_slice(), &current_hash)
+            hash_pair(sibling.as_slice(), &current_hash, algorithm)
         };
     }
 
@@ -167,6 +173,11 @@ pub fn verify_proof(data: &[u8], proof: &MerkleProof, root: &[u8]) -> Result<()>
 /// Build a merkle tree root directly from pre-hashed leaves
 /// Matches Go's txaty/go-merkletree behavior by duplicating odd nodes
 pub fn build_merkle_root(leaves: &[Vec<u8>]) -> Vec<u8> {
+    build_merkle_root_with(leaves, HashAlgorithm::Sha256)
+}
+
+/// Build a merkle tree root from pre-hashed leaves using the given hash function
+pub fn build_merkle_root_with(leaves: &[Vec<u8>], algorithm: HashAlgorithm) -> Vec<u8> {
     if leaves.is_empty() {
         return vec![];
     }
@@ -187,7 +198,7 @@ pub fn build_merkle_root(leaves: &[Vec<u8>]) -> Vec<u8> {
 
         // Process pairs - all should be pairs now since we fix odd lengths
         for chunk in current_level.chunks(2) {
-            let hash = hash_pair(&chunk[0], &chunk[1]);
+            let hash = hash_pair(&chunk[0], &chunk[1], algorithm);
             next_level.push(hash);
         }
 
@@ -202,6 +213,31 @@ pub fn build_merkle_root(leaves: &[Vec<u8>]) -> Vec<u8> {
     current_level[0].clone()
 }
 
+/// Compute the classic merkle root a leaf stores for its links
+///
+/// Links are sorted, each one is hashed with SHA256, and the hashes are
+/// combined with [`build_merkle_root`]. Returns None when there are no links.
+pub fn merkle_root_for_links(links: &[String]) -> Option<Vec<u8>> {
+    merkle_root_for_links_with(links, HashAlgorithm::Sha256)
+}
+
+/// Compute a leaf's classic merkle root for its links using the given hash function
+pub fn merkle_root_for_links_with(links: &[String], algorithm: HashAlgorithm) -> Option<Vec<u8>> {
+    if links.is_empty() {
+        return None;
+    }
+
+    let mut sorted_links = links.to_vec();
+    sorted_links.sort();
+
+    let hashed_leaves: Vec<_> = sorted_links
+        .iter()
+        .map(|link| algorithm.digest(link.as_bytes()))
+        .collect();
+
+    Some(build_merkle_root_with(&hashed_leaves, algorithm))
+}
+
 /// Builder for creating Merkle trees
 pub struct MerkleTreeBuilder {
     data: Vec<(String, Vec<u8>)>,
@@ -265,4 +301,25 @@ mod tests {
         let result = tree.verify(b"wrong", &tree.proofs[0]);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_odd_levels_match_build_merkle_root() {
+        for count in [3, 5, 6, 7] {
+            let data: Vec<(String, Vec<u8>)> = (0..count)
+                .map(|i| (format!("key{}", i), format!("data{}", i).into_bytes()))
+                .collect();
+            let leaves: Vec<Vec<u8>> = data
+                .iter()
+                .map(|(_, value)| HashAlgorithm::Sha256.digest(value))
+                .collect();
+
+            // Odd levels duplicate their last node, so the tree's root is
+            // the stored one and every proof verifies against it
+            let tree = MerkleTree::new(data.clone()).unwrap();
+            assert_eq!(tree.root, build_merkle_root(&leaves), "{} leaves", count);
+            for (i, (_, value)) in data.iter().enumerate() {
+                assert!(tree.verify(value, &tree.proofs[i]).is_ok());
+            }
+        }
+    }
 }
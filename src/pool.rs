@@ -0,0 +1,177 @@
+//! Content-addressed leaf storage
+//!
+//! [`LeafPool`] holds leaves by CID so DAGs can be assembled across calls and
+//! extracted once their root is known. Leaves from several DAGs can share
+//! one pool.
+
+use crate::error::{Result, ScionicError};
+use crate::serialize::same_leaf_data;
+use crate::types::{Dag, DagKind, DagLeaf};
+use std::collections::HashMap;
+
+/// An owned pool of leaves keyed by CID
+#[derive(Debug, Clone, Default)]
+pub struct LeafPool {
+    leaves: HashMap<String, DagLeaf>,
+}
+
+impl LeafPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify and store a leaf, returning its CID
+    ///
+    /// The leaf's CID is recomputed (as a root if it carries a leaf count)
+    /// and its content checked against its content hash. Storing a leaf
+    /// that is already present is a no-op, but a different leaf claiming a
+    /// stored CID is rejected, as one of them is corrupt.
+    pub fn put(&mut self, leaf: DagLeaf) -> Result<String> {
+        if leaf.leaf_count.is_some() {
+            leaf.verify_root_leaf()?;
+        } else {
+            leaf.verify_leaf()?;
+        }
+        leaf.verify_content_fields()?;
+        leaf.verify_content_hash()?;
+
+        if let Some(existing) = self.leaves.get(&leaf.hash) {
+            if !same_leaf_data(existing, &leaf) {
+                return Err(ScionicError::InvalidLeaf(format!(
+                    "Conflicting leaf for already stored CID {}",
+                    leaf.hash
+                )));
+            }
+            return Ok(leaf.hash);
+        }
+
+        let hash = leaf.hash.clone();
+        self.leaves.insert(hash.clone(), leaf);
+        Ok(hash)
+    }
+
+    /// Look up a leaf by CID
+    pub fn get(&self, cid: &str) -> Option<&DagLeaf> {
+        self.leaves.get(cid)
+    }
+
+    /// Whether a leaf with this CID is stored
+    pub fn contains(&self, cid: &str) -> bool {
+        self.leaves.contains_key(cid)
+    }
+
+    /// Number of stored leaves
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Extract the leaves reachable from `root_cid` into a full DAG
+    ///
+    /// Fails with `MissingLeaf` if the root or any leaf it reaches isn't in
+    /// the pool. The DAG is not verified.
+    pub fn build_dag(&self, root_cid: &str) -> Result<Dag> {
+        let mut leaves = HashMap::new();
+        let mut stack = vec![root_cid];
+
+        while let Some(hash) = stack.pop() {
+            if leaves.contains_key(hash) {
+                continue;
+            }
+            let leaf = self
+                .leaves
+                .get(hash)
+                .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
+            stack.extend(leaf.links.iter().map(String::as_str));
+            leaves.insert(hash.to_string(), leaf.clone());
+        }
+
+        Ok(Dag {
+            root: root_cid.to_string(),
+            leaves,
+            labels: None,
+            kind: Some(DagKind::Full),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_dag_from_pool() -> Result<()> {
+        let first = Dag::builder()
+            .add_file("a.txt", "alpha")
+            .add_file("dir/b.txt", "beta")
+            .build()?;
+        let second = Dag::builder()
+            .with_name("other")
+            .add_file("c.txt", vec![1u8; 100])
+            .with_chunk_size(16)
+            .build()?;
+
+        let mut pool = LeafPool::new();
+        for leaf in first.leaves.values().chain(second.leaves.values()) {
+            assert_eq!(pool.put(leaf.clone())?, leaf.hash);
+        }
+        assert_eq!(pool.len(), first.leaves.len() + second.leaves.len());
+
+        // Re-storing a leaf is fine; tampered or conflicting ones are not
+        let file = first
+            .leaves
+            .values()
+            .find(|leaf| leaf.item_name == "a.txt")
+            .unwrap();
+        pool.put(file.clone())?;
+        let mut tampered = file.clone();
+        tampered.content = Some(b"tampered".to_vec());
+        assert!(matches!(
+            pool.put(tampered),
+            Err(ScionicError::ContentHashMismatch)
+        ));
+        let mut renamed = file.clone();
+        renamed.item_name = "evil.txt".to_string();
+        assert!(matches!(
+            pool.put(renamed),
+            Err(ScionicError::HashMismatch { .. })
+        ));
+
+        // The merkle root sorts the links, so reordered links keep the CID
+        let root = &first.leaves[&first.root];
+        let mut conflicting = root.clone();
+        conflicting.links.reverse();
+        assert!(matches!(
+            pool.put(conflicting),
+            Err(ScionicError::InvalidLeaf(_))
+        ));
+        assert_eq!(pool.get(&root.hash).unwrap().links, root.links);
+        assert!(pool.get(&first.root).is_some());
+
+        for dag in [&first, &second] {
+            let built = pool.build_dag(&dag.root)?;
+            built.verify()?;
+            assert_eq!(built.root, dag.root);
+            assert_eq!(built.leaves.len(), dag.leaves.len());
+        }
+
+        // Extraction fails when a reachable leaf is missing
+        let chunk = second
+            .leaves
+            .values()
+            .find(|leaf| leaf.leaf_type == crate::LeafType::Chunk)
+            .unwrap();
+        let mut partial_pool = pool.clone();
+        partial_pool.leaves.remove(&chunk.hash);
+        assert!(matches!(
+            partial_pool.build_dag(&second.root),
+            Err(ScionicError::MissingLeaf(_))
+        ));
+        assert!(pool.build_dag("bafireimissing").is_err());
+
+        Ok(())
+    }
+}
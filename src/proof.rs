@@ -0,0 +1,189 @@
+//! Inclusion proofs
+//!
+//! An inclusion proof carries a leaf together with the chain of parents up to
+//! the root, so a client that only knows the root CID can check that the leaf
+//! belongs to the DAG.
+
+use crate::error::{Result, ScionicError};
+use crate::merkle_tree::verify_proof_with;
+use crate::types::{ClassicTreeBranch, Dag, DagLeaf};
+use serde::{Deserialize, Serialize};
+
+/// One hop of an inclusion proof: a parent leaf and the branch proving
+/// that the previous leaf is one of its children
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// The parent leaf
+    #[serde(rename = "Parent")]
+    pub parent: DagLeaf,
+
+    /// Branch for the child (None when the parent has a single link)
+    #[serde(rename = "Branch", skip_serializing_if = "Option::is_none", default)]
+    pub branch: Option<ClassicTreeBranch>,
+}
+
+/// Proof that a leaf is contained in a DAG with a given root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// The proven leaf
+    #[serde(rename = "Leaf")]
+    pub leaf: DagLeaf,
+
+    /// Parents from the leaf's direct parent up to the root
+    #[serde(rename = "Path")]
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Verify the proof against a trusted root CID
+    ///
+    /// The leaf's content is checked against its content hash too, since
+    /// the CID doesn't cover the content bytes.
+    pub fn verify(&self, root: &str) -> Result<()> {
+        self.leaf.verify_content_fields()?;
+        self.leaf.verify_content_hash()?;
+
+        // The root itself needs no branches
+        if self.path.is_empty() {
+            if self.leaf.hash != root {
+                return Err(ScionicError::HashMismatch {
+                    expected: root.to_string(),
+                    got: self.leaf.hash.clone(),
+                });
+            }
+            return self.leaf.verify_root_leaf();
+        }
+
+        self.leaf.verify_leaf()?;
+
+        let mut child_hash = &self.leaf.hash;
+        for (i, step) in self.path.iter().enumerate() {
+            let is_root = i == self.path.len() - 1;
+            if is_root {
+                if step.parent.hash != root {
+                    return Err(ScionicError::HashMismatch {
+                        expected: root.to_string(),
+                        got: step.parent.hash.clone(),
+                    });
+                }
+                step.parent.verify_root_leaf()?;
+            } else {
+                step.parent.verify_leaf()?;
+            }
+
+            verify_child_in_parent(&step.parent, child_hash, step.branch.as_ref())?;
+            child_hash = &step.parent.hash;
+        }
+
+        Ok(())
+    }
+}
+
+/// Verify a labeled leaf received by a LeafSync client, without a full DAG
+///
+/// Checks that the leaf's CID and content hash match its data and that
+/// `branch` proves the leaf under `parent_merkle_root`. Nothing here ties
+/// that merkle root to a DAG: the caller must take it from a parent it
+/// already verified back to the root it trusts, e.g. the root leaf itself
+/// for the root's children. The root (label 0) has no parent to prove it
+/// under; check it with [`DagLeaf::verify_root_leaf`] and its hash instead.
+pub fn verify_labeled_leaf(
+    leaf: &DagLeaf,
+    branch: &ClassicTreeBranch,
+    parent_merkle_root: &[u8],
+) -> Result<()> {
+    if branch.leaf != leaf.hash {
+        return Err(ScionicError::InvalidProof);
+    }
+
+    leaf.verify_leaf()?;
+    leaf.verify_content_fields()?;
+    leaf.verify_content_hash()?;
+    verify_proof_with(
+        leaf.hash.as_bytes(),
+        &branch.proof,
+        parent_merkle_root,
+        leaf.hash_algorithm()?,
+    )
+}
+
+/// Check that `child_hash` is committed to by the parent's classic merkle root
+pub(crate) fn verify_child_in_parent(
+    parent: &DagLeaf,
+    child_hash: &str,
+    branch: Option<&ClassicTreeBranch>,
+) -> Result<()> {
+    let merkle_root = parent
+        .classic_merkle_root
+        .as_ref()
+        .ok_or_else(|| ScionicError::InvalidLeaf(format!("Parent {} has no links", parent.hash)))?;
+
+    match branch {
+        Some(branch) => {
+            if branch.leaf != child_hash {
+                return Err(ScionicError::InvalidProof);
+            }
+            verify_proof_with(
+                child_hash.as_bytes(),
+                &branch.proof,
+                merkle_root,
+                parent.hash_algorithm()?,
+            )
+        }
+        None => {
+            // A single link's merkle root is just the hash of that link
+            if parent.hash_algorithm()?.digest(child_hash.as_bytes()) == *merkle_root {
+                Ok(())
+            } else {
+                Err(ScionicError::InvalidProof)
+            }
+        }
+    }
+}
+
+impl Dag {
+    /// Build an inclusion proof for the leaf with the given label
+    pub fn prove_label(&self, label: usize) -> Result<InclusionProof> {
+        let hash =
+            if label == 0 {
+                self.root.clone()
+            } else {
+                let labels = self.labels.as_ref().ok_or_else(|| {
+                    ScionicError::InvalidLabel("Labels not calculated".to_string())
+                })?;
+
+                labels.get(&label.to_string()).cloned().ok_or_else(|| {
+                    ScionicError::InvalidLabel(format!("Label {} not found", label))
+                })?
+            };
+
+        self.prove_leaf(&hash)
+    }
+
+    /// Build an inclusion proof for the leaf with the given hash
+    pub fn prove_leaf(&self, hash: &str) -> Result<InclusionProof> {
+        let leaf = self
+            .leaves
+            .get(hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
+
+        let mut path = Vec::new();
+        let mut current_hash = hash.to_string();
+        while current_hash != self.root {
+            let parent = self.find_parent(&current_hash).ok_or_else(|| {
+                ScionicError::MissingLeaf(format!("Parent not found for {}", current_hash))
+            })?;
+
+            path.push(ProofStep {
+                parent: parent.clone(),
+                branch: parent.get_branch(&current_hash)?,
+            });
+            current_hash = parent.hash.clone();
+        }
+
+        Ok(InclusionProof {
+            leaf: leaf.clone(),
+            path,
+        })
+    }
+}
@@ -1,8 +1,150 @@
 use crate::error::{Result, ScionicError};
-use crate::types::{Dag, TransmissionPacket};
+use crate::leaf::parse_cid;
+use crate::types::{Dag, DagLeaf, HashAlgorithm, TransmissionPacket};
 use std::fs;
+use std::io::{self, Read, Write};
 use std::path::Path;
 
+/// Magic bytes starting a serialized file with a header
+pub const FILE_MAGIC: &[u8; 4] = b"SMDG";
+
+/// Header version written by this crate
+pub const FILE_HEADER_VERSION: u8 = 1;
+
+/// What a serialized file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    Dag,
+    TransmissionPacket,
+}
+
+/// Header optionally prepended to serialized CBOR: the magic, a version
+/// byte, the payload kind and the hash algorithm
+///
+/// Plain CBOR always starts with a map, so headerless files are told apart
+/// by the missing magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub version: u8,
+    pub payload: PayloadKind,
+    pub hash_algorithm: HashAlgorithm,
+}
+
+impl FileHeader {
+    /// Encoded length in bytes
+    pub const LEN: usize = FILE_MAGIC.len() + 3;
+
+    fn new(payload: PayloadKind, hash_algorithm: HashAlgorithm) -> Self {
+        Self {
+            version: FILE_HEADER_VERSION,
+            payload,
+            hash_algorithm,
+        }
+    }
+
+    /// Encode the header
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let payload = match self.payload {
+            PayloadKind::Dag => 0,
+            PayloadKind::TransmissionPacket => 1,
+        };
+        let algorithm = match self.hash_algorithm {
+            HashAlgorithm::Sha256 => 0,
+            HashAlgorithm::Sha512_256 => 1,
+            HashAlgorithm::Blake3 => 2,
+        };
+
+        let mut bytes = [0; Self::LEN];
+        bytes[..FILE_MAGIC.len()].copy_from_slice(FILE_MAGIC);
+        bytes[FILE_MAGIC.len()..].copy_from_slice(&[self.version, payload, algorithm]);
+        bytes
+    }
+
+    /// Split off the header if `data` starts with one
+    ///
+    /// Fails on a header that's truncated, of a newer version or has
+    /// unknown flags.
+    pub fn split(data: &[u8]) -> Result<(Option<Self>, &[u8])> {
+        let Some(rest) = data.strip_prefix(FILE_MAGIC.as_slice()) else {
+            return Ok((None, data));
+        };
+        let invalid =
+            |what: String| ScionicError::Deserialization(format!("File header: {}", what));
+
+        let [version, payload, algorithm, ..] = *rest else {
+            return Err(invalid("truncated".to_string()));
+        };
+        if version != FILE_HEADER_VERSION {
+            return Err(invalid(format!("unsupported version {}", version)));
+        }
+        let payload = match payload {
+            0 => PayloadKind::Dag,
+            1 => PayloadKind::TransmissionPacket,
+            other => return Err(invalid(format!("unknown payload kind {}", other))),
+        };
+        let hash_algorithm = match algorithm {
+            0 => HashAlgorithm::Sha256,
+            1 => HashAlgorithm::Sha512_256,
+            2 => HashAlgorithm::Blake3,
+            other => return Err(invalid(format!("unknown hash algorithm {}", other))),
+        };
+
+        let header = Self {
+            version,
+            payload,
+            hash_algorithm,
+        };
+        Ok((Some(header), &rest[3..]))
+    }
+
+    /// Split off the header, checking that it announces `payload`
+    fn split_expecting(data: &[u8], payload: PayloadKind) -> Result<(Option<Self>, &[u8])> {
+        let (header, rest) = Self::split(data)?;
+        if let Some(header) = header.filter(|header| header.payload != payload) {
+            return Err(ScionicError::Deserialization(format!(
+                "File header: expected {:?}, found {:?}",
+                payload, header.payload
+            )));
+        }
+        Ok((header, rest))
+    }
+
+    /// Check that the header's algorithm, if there is one, is `actual`
+    fn check_algorithm(
+        header: Option<Self>,
+        actual: impl FnOnce() -> Result<HashAlgorithm>,
+    ) -> Result<()> {
+        let Some(header) = header else {
+            return Ok(());
+        };
+        let actual = actual()?;
+        if header.hash_algorithm != actual {
+            return Err(ScionicError::Deserialization(format!(
+                "File header: announces {:?} but leaves use {:?}",
+                header.hash_algorithm, actual
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A writer that discards its input and counts the bytes
+#[derive(Default)]
+struct CountingWriter {
+    count: usize,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.count += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl Dag {
     /// Serialize DAG to JSON
     pub fn to_json(&self) -> Result<Vec<u8>> {
@@ -24,9 +166,32 @@ impl Dag {
         serde_cbor::to_vec(self).map_err(|e| ScionicError::Serialization(e.to_string()))
     }
 
-    /// Deserialize DAG from CBOR
+    /// Size in bytes of the CBOR encoding, without buffering it
+    pub fn serialized_size(&self) -> Result<usize> {
+        let mut writer = CountingWriter::default();
+        serde_cbor::to_writer(&mut writer, self)
+            .map_err(|e| ScionicError::Serialization(e.to_string()))?;
+        Ok(writer.count)
+    }
+
+    /// Serialize DAG to CBOR preceded by a [`FileHeader`]
+    pub fn to_cbor_with_header(&self) -> Result<Vec<u8>> {
+        let header = FileHeader::new(PayloadKind::Dag, self.root_hash_algorithm()?);
+        let mut data = header.to_bytes().to_vec();
+        serde_cbor::to_writer(&mut data, self)
+            .map_err(|e| ScionicError::Serialization(e.to_string()))?;
+        Ok(data)
+    }
+
+    /// Deserialize DAG from CBOR, with or without a [`FileHeader`]
+    ///
+    /// A header must announce a DAG and the root's hash algorithm.
     pub fn from_cbor(data: &[u8]) -> Result<Self> {
-        serde_cbor::from_slice(data).map_err(|e| ScionicError::Deserialization(e.to_string()))
+        let (header, data) = FileHeader::split_expecting(data, PayloadKind::Dag)?;
+        let dag: Self = serde_cbor::from_slice(data)
+            .map_err(|e| ScionicError::Deserialization(e.to_string()))?;
+        FileHeader::check_algorithm(header, || dag.root_hash_algorithm())?;
+        Ok(dag)
     }
 
     /// Save DAG to file (CBOR format)
@@ -36,12 +201,35 @@ impl Dag {
         Ok(())
     }
 
-    /// Load DAG from file (CBOR format)
+    /// Save DAG to file (CBOR format) with a [`FileHeader`]
+    pub fn save_to_file_with_header(&self, path: impl AsRef<Path>) -> Result<()> {
+        let data = self.to_cbor_with_header()?;
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Load DAG from file (CBOR format), with or without a header
     pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
         let data = fs::read(path)?;
         Self::from_cbor(&data)
     }
 
+    /// Read the header of a saved file, or `None` for a headerless one
+    pub fn read_file_header(path: impl AsRef<Path>) -> Result<Option<FileHeader>> {
+        let mut start = Vec::with_capacity(FileHeader::LEN);
+        fs::File::open(path)?
+            .take(FileHeader::LEN as u64)
+            .read_to_end(&mut start)?;
+        Ok(FileHeader::split(&start)?.0)
+    }
+
+    /// Hash algorithm of the root leaf (the default if the root is missing)
+    fn root_hash_algorithm(&self) -> Result<HashAlgorithm> {
+        self.leaves
+            .get(&self.root)
+            .map_or(Ok(HashAlgorithm::default()), DagLeaf::hash_algorithm)
+    }
+
     /// Get leaf sequence as transmission packets (for syncing)
     pub fn get_leaf_sequence(&self) -> Vec<TransmissionPacket> {
         let mut packets = Vec::new();
@@ -88,6 +276,17 @@ impl Dag {
             packet.leaf.verify_leaf()?;
         }
 
+        // Re-applying the same leaf is fine, but a different one claiming a
+        // stored CID means one of them is corrupt
+        if let Some(existing) = self.leaves.get(&packet.leaf.hash) {
+            if !same_leaf_data(existing, &packet.leaf) {
+                return Err(ScionicError::InvalidLeaf(format!(
+                    "Conflicting leaf for already stored CID {}",
+                    packet.leaf.hash
+                )));
+            }
+        }
+
         // Apply the packet
         self.apply_transmission_packet(packet);
 
@@ -95,6 +294,51 @@ impl Dag {
     }
 }
 
+impl DagLeaf {
+    /// Serialize this leaf as a standalone block: the whole leaf as CBOR
+    ///
+    /// This is the leaf record, including its content and links, not the
+    /// bytes hashed for its CID, so the block doesn't hash to the CID.
+    pub fn to_block_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| ScionicError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize a leaf from a block, checking it is stored under `cid`
+    ///
+    /// Only the stored hash is compared, as a parsed CID so `cid` may be in
+    /// any multibase; verify the leaf to recompute it.
+    pub fn from_block_bytes(cid: &str, data: &[u8]) -> Result<Self> {
+        let leaf: DagLeaf = serde_cbor::from_slice(data)
+            .map_err(|e| ScionicError::Deserialization(e.to_string()))?;
+
+        if leaf.hash != cid && parse_cid(&leaf.hash)? != parse_cid(cid)? {
+            return Err(ScionicError::HashMismatch {
+                expected: cid.to_string(),
+                got: leaf.hash,
+            });
+        }
+
+        Ok(leaf)
+    }
+}
+
+/// Whether two leaves hold the same data, ignoring fields that aren't part
+/// of the leaf itself (parent hash, proofs and unknown fields)
+pub(crate) fn same_leaf_data(a: &DagLeaf, b: &DagLeaf) -> bool {
+    a.hash == b.hash
+        && a.item_name == b.item_name
+        && a.leaf_type == b.leaf_type
+        && a.content_hash == b.content_hash
+        && a.content == b.content
+        && a.classic_merkle_root == b.classic_merkle_root
+        && a.current_link_count == b.current_link_count
+        && a.leaf_count == b.leaf_count
+        && a.content_size == b.content_size
+        && a.dag_size == b.dag_size
+        && a.links == b.links
+        && a.additional_data == b.additional_data
+}
+
 impl TransmissionPacket {
     /// Serialize to JSON
     pub fn to_json(&self) -> Result<Vec<u8>> {
@@ -111,9 +355,22 @@ impl TransmissionPacket {
         serde_cbor::to_vec(self).map_err(|e| ScionicError::Serialization(e.to_string()))
     }
 
-    /// Deserialize from CBOR
+    /// Serialize to CBOR preceded by a [`FileHeader`]
+    pub fn to_cbor_with_header(&self) -> Result<Vec<u8>> {
+        let header = FileHeader::new(PayloadKind::TransmissionPacket, self.leaf.hash_algorithm()?);
+        let mut data = header.to_bytes().to_vec();
+        serde_cbor::to_writer(&mut data, self)
+            .map_err(|e| ScionicError::Serialization(e.to_string()))?;
+        Ok(data)
+    }
+
+    /// Deserialize from CBOR, with or without a [`FileHeader`]
     pub fn from_cbor(data: &[u8]) -> Result<Self> {
-        serde_cbor::from_slice(data).map_err(|e| ScionicError::Deserialization(e.to_string()))
+        let (header, data) = FileHeader::split_expecting(data, PayloadKind::TransmissionPacket)?;
+        let packet: Self = serde_cbor::from_slice(data)
+            .map_err(|e| ScionicError::Deserialization(e.to_string()))?;
+        FileHeader::check_algorithm(header, || packet.leaf.hash_algorithm())?;
+        Ok(packet)
     }
 }
 
@@ -164,6 +421,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialized_size_matches_cbor() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, b"Test data")?;
+
+        let dir = temp_dir.path().join("dir");
+        fs::create_dir_all(dir.join("nested"))?;
+        fs::write(dir.join("a.txt"), b"a")?;
+        fs::write(
+            dir.join("nested").join("large.bin"),
+            vec![7u8; 3 * 1024 * 1024],
+        )?;
+
+        let mut labelled = create_dag(&dir, false)?;
+        labelled.calculate_labels()?;
+
+        for dag in [
+            create_dag(&file_path, false)?,
+            create_dag(&dir, true)?,
+            labelled,
+        ] {
+            assert_eq!(dag.serialized_size()?, dag.to_cbor()?.len());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reapplying_packets_is_idempotent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file_path = temp_dir.path().join("large.bin");
+        let content: Vec<u8> = (0..200u8).collect();
+        fs::write(&file_path, &content)?;
+
+        let config = crate::types::DagBuilderConfig::new().with_chunk_size(64);
+        let dag = crate::dag::create_dag_with_config(&file_path, config)?;
+        let mut received = Dag::receiving(dag.root.clone());
+
+        // Every packet twice, as after a retry
+        for packet in dag.get_leaf_sequence() {
+            received.apply_and_verify_transmission_packet(packet.clone())?;
+            received.apply_and_verify_transmission_packet(packet)?;
+        }
+        received.verify()?;
+
+        // Swapped chunk links leave the CID unchanged but conflict with
+        // the stored root
+        let mut conflicting = dag
+            .get_leaf_sequence()
+            .into_iter()
+            .find(|packet| packet.leaf.hash == dag.root)
+            .unwrap();
+        conflicting.leaf.links.swap(0, 1);
+
+        match received.apply_and_verify_transmission_packet(conflicting) {
+            Err(ScionicError::InvalidLeaf(msg)) => assert!(msg.contains(&dag.root)),
+            result => panic!("expected InvalidLeaf, got {:?}", result),
+        }
+        assert_eq!(
+            received.leaves[&dag.root].links,
+            dag.leaves[&dag.root].links
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_file_save_load() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -183,4 +507,71 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_file_header() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, b"Test data")?;
+        let config = crate::DagBuilderConfig::new().with_hash_algorithm(HashAlgorithm::Sha512_256);
+        let dag = crate::create_dag_with_config(&file_path, config)?;
+
+        let with_header = temp_dir.path().join("header.dag");
+        dag.save_to_file_with_header(&with_header)?;
+        assert!(fs::read(&with_header)?.starts_with(FILE_MAGIC));
+        assert_eq!(
+            Dag::read_file_header(&with_header)?,
+            Some(FileHeader {
+                version: FILE_HEADER_VERSION,
+                payload: PayloadKind::Dag,
+                hash_algorithm: HashAlgorithm::Sha512_256,
+            })
+        );
+        let loaded = Dag::load_from_file(&with_header)?;
+        assert_eq!(loaded.root, dag.root);
+        loaded.verify()?;
+
+        // Legacy headerless files still load
+        let legacy = temp_dir.path().join("legacy.dag");
+        dag.save_to_file(&legacy)?;
+        assert_eq!(Dag::read_file_header(&legacy)?, None);
+        assert_eq!(Dag::load_from_file(&legacy)?.root, dag.root);
+
+        // A packet isn't mistaken for a DAG, and a header must match the
+        // leaves' algorithm
+        let packet = dag.get_leaf_sequence().remove(0);
+        let packet_data = packet.to_cbor_with_header()?;
+        assert_eq!(
+            TransmissionPacket::from_cbor(&packet_data)?.leaf.hash,
+            packet.leaf.hash
+        );
+        assert!(matches!(
+            Dag::from_cbor(&packet_data),
+            Err(ScionicError::Deserialization(_))
+        ));
+
+        let mut data = dag.to_cbor_with_header()?;
+        data[6] = 0;
+        assert!(Dag::from_cbor(&data).is_err());
+        data[4] = FILE_HEADER_VERSION + 1;
+        assert!(Dag::from_cbor(&data).is_err());
+
+        // Headerless data isn't checked, so a hash that isn't a CID still
+        // deserializes and is left for verify to reject
+        let mut bad_packet = packet;
+        bad_packet.leaf.hash = "not-a-cid".to_string();
+        let bad_packet = TransmissionPacket::from_cbor(&bad_packet.to_cbor()?)?;
+        assert_eq!(bad_packet.leaf.hash, "not-a-cid");
+
+        let mut bad_dag = dag;
+        let mut root_leaf = bad_dag.leaves.remove(&bad_dag.root).unwrap();
+        root_leaf.hash = "not-a-cid".to_string();
+        bad_dag.root = root_leaf.hash.clone();
+        bad_dag.leaves.insert(bad_dag.root.clone(), root_leaf);
+        let bad_dag = Dag::from_cbor(&bad_dag.to_cbor()?)?;
+        assert_eq!(bad_dag.root, "not-a-cid");
+        assert!(bad_dag.verify().is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,270 @@
+//! Building a DAG while pushing its blocks to an async sink
+//!
+//! [`create_dag_streaming_to_sink`] reads one file at a time, hands every
+//! leaf's block to an [`AsyncBlockSink`] as soon as it's built and keeps
+//! only the structure, so a directory can be uploaded without holding its
+//! content in memory.
+
+use crate::dag::{
+    build_directory_leaf, build_file_leaf, build_symlink_leaf, check_size_limits, classify_entry,
+    root_item_name, root_leaf_builder, utf8_name, DagBuilder, EntryKind,
+};
+use crate::error::{Result, ScionicError};
+use crate::leaf::DescendantTotals;
+use crate::types::{Dag, DagBuilderConfig, DagKind, DagLeaf};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+
+/// Destination for blocks, e.g. an object store
+pub trait AsyncBlockSink {
+    /// Store the block bytes of the leaf with the given CID
+    fn put(&mut self, cid: &str, bytes: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Create a DAG from a file or directory, pushing every leaf's block to
+/// `sink` as it is built
+///
+/// Each CID is pushed once, and the root is pushed last. The
+/// returned DAG has the same leaves as [`create_dag_with_config`] but with
+/// their content dropped, so it holds the structure only and is marked
+/// [`DagKind::Structure`]; the content lives in the pushed blocks. Parallel
+/// building isn't used.
+///
+/// [`create_dag_with_config`]: crate::create_dag_with_config
+pub async fn create_dag_streaming_to_sink<S: AsyncBlockSink + Send>(
+    path: impl AsRef<Path>,
+    config: DagBuilderConfig,
+    sink: S,
+) -> Result<Dag> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Err(ScionicError::PathNotFound(path.display().to_string()));
+    }
+    let metadata = tokio::fs::metadata(path).await?;
+
+    let mut upload = Upload {
+        sink,
+        config: &config,
+        leaves: HashMap::new(),
+        totals: DescendantTotals::default(),
+        read_bytes: AtomicU64::new(0),
+        crlf_files: BTreeSet::new(),
+    };
+
+    let top = if metadata.is_dir() {
+        let name = root_item_name(path, &config, "root")?;
+        upload.directory(path.to_path_buf(), path, name).await?
+    } else {
+        let name = root_item_name(path, &config, "file")?;
+        upload.file(path, name).await?
+    };
+
+    let (root_builder, additional_data) = root_leaf_builder(top, &config, &upload.crlf_files);
+    let root = root_builder.build_root_leaf_with_totals(upload.totals, additional_data)?;
+    upload.sink.put(&root.hash, root.to_block_bytes()?).await?;
+
+    let mut leaves = upload.leaves;
+    let root_hash = root.hash.clone();
+    leaves.insert(root_hash.clone(), structure_only(root));
+
+    Ok(Dag {
+        root: root_hash,
+        leaves,
+        labels: None,
+        kind: Some(DagKind::Structure),
+    })
+}
+
+/// The leaf without its content
+fn structure_only(mut leaf: DagLeaf) -> DagLeaf {
+    leaf.content = None;
+    leaf
+}
+
+struct Upload<'a, S> {
+    sink: S,
+    config: &'a DagBuilderConfig,
+    /// Pushed leaves, content dropped
+    leaves: HashMap<String, DagLeaf>,
+    /// Totals over the pushed leaves, for the root
+    totals: DescendantTotals,
+    /// Bytes of the files read so far, for the size limits
+    read_bytes: AtomicU64,
+    /// Files whose CRLF line endings were normalized, for the root
+    crlf_files: BTreeSet<String>,
+}
+
+impl<S: AsyncBlockSink + Send> Upload<'_, S> {
+    /// Push a leaf's block unless a leaf with its CID was already pushed
+    async fn push(&mut self, leaf: DagLeaf) -> Result<()> {
+        if self.leaves.contains_key(&leaf.hash) {
+            return Ok(());
+        }
+
+        self.totals.add(&leaf)?;
+        self.sink.put(&leaf.hash, leaf.to_block_bytes()?).await?;
+        self.leaves.insert(leaf.hash.clone(), structure_only(leaf));
+        Ok(())
+    }
+
+    /// Push the leaves a build step added
+    async fn push_built(&mut self, builder: DagBuilder) -> Result<()> {
+        for leaf in builder.leaves.into_values() {
+            self.push(leaf).await?;
+        }
+        Ok(())
+    }
+
+    /// Build and push a file's chunks, returning the (unpushed) file leaf
+    async fn file(&mut self, path: &Path, rel_path: String) -> Result<DagLeaf> {
+        let size = tokio::fs::metadata(path).await?.len();
+        check_size_limits(path, size, &self.read_bytes, self.config)?;
+        let data = tokio::fs::read(path).await?;
+        let mut builder = DagBuilder::new();
+        let leaf = build_file_leaf(&rel_path, data, &mut builder, self.config)?;
+        self.crlf_files.extend(builder.crlf_files());
+        self.push_built(builder).await?;
+        Ok(leaf)
+    }
+
+    /// Build and push a directory's descendants, returning the (unpushed)
+    /// directory leaf
+    fn directory<'b>(
+        &'b mut self,
+        path: PathBuf,
+        base_path: &'b Path,
+        rel_path: String,
+    ) -> Pin<Box<dyn Future<Output = Result<DagLeaf>> + Send + 'b>>
+    where
+        S: 'b,
+    {
+        Box::pin(async move {
+            let mut entries = Vec::new();
+            let mut read_dir = tokio::fs::read_dir(&path).await?;
+            while let Some(entry) = read_dir.next_entry().await? {
+                entries.push(entry);
+            }
+
+            // Same order, symlink handling and name checks as the
+            // synchronous build
+            entries.sort_by_key(|e| e.file_name());
+            let mut classified = Vec::with_capacity(entries.len());
+            for entry in entries {
+                let entry_path = entry.path();
+                if let Some(kind) = classify_entry(&entry_path, self.config)? {
+                    utf8_name(&entry.file_name(), &entry_path, self.config)?;
+                    classified.push((entry_path, kind));
+                }
+            }
+
+            let mut child_hashes = Vec::with_capacity(classified.len());
+            for (entry_path, kind) in classified {
+                let child_rel_path = entry_path
+                    .strip_prefix(base_path)
+                    .map_err(|_| ScionicError::InvalidDag("Invalid path".to_string()))?
+                    .to_string_lossy()
+                    .to_string();
+
+                let child = match kind {
+                    EntryKind::Directory => {
+                        self.directory(entry_path, base_path, child_rel_path)
+                            .await?
+                    }
+                    EntryKind::File => self.file(&entry_path, child_rel_path).await?,
+                    EntryKind::Symlink(target) => {
+                        build_symlink_leaf(child_rel_path, &target, self.config)?
+                    }
+                };
+
+                child_hashes.push(child.hash.clone());
+                self.push(child).await?;
+            }
+
+            let mut builder = DagBuilder::new();
+            let leaf = build_directory_leaf(rel_path, child_hashes, &mut builder, self.config)?;
+            self.push_built(builder).await?;
+            Ok(leaf)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_dag_with_config;
+    use std::sync::{Arc, Mutex};
+
+    /// Sink collecting blocks in memory
+    #[derive(Clone, Default)]
+    struct MemorySink {
+        blocks: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        puts: Arc<Mutex<usize>>,
+    }
+
+    impl AsyncBlockSink for MemorySink {
+        async fn put(&mut self, cid: &str, bytes: Vec<u8>) -> Result<()> {
+            *self.puts.lock().unwrap() += 1;
+            self.blocks.lock().unwrap().insert(cid.to_string(), bytes);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_to_sink() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let dir = temp_dir.path().join("input");
+        std::fs::create_dir_all(dir.join("nested").join("empty"))?;
+        let chunked: Vec<u8> = (0..5_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(dir.join("small.txt"), b"small")?;
+        std::fs::write(dir.join("copy.txt"), b"small")?;
+        std::fs::write(dir.join("nested").join("chunked.bin"), &chunked)?;
+
+        let config = DagBuilderConfig::new()
+            .with_chunk_size(512)
+            .with_max_links_per_leaf(4)
+            .with_entry_counts()
+            .with_file_sizes();
+        let sink = MemorySink::default();
+        let dag = create_dag_streaming_to_sink(&dir, config.clone(), sink.clone()).await?;
+
+        // Same DAG as a regular build, minus the content
+        let expected = create_dag_with_config(&dir, config)?;
+        assert_eq!(dag.root, expected.root);
+        assert_eq!(dag.leaves.len(), expected.leaves.len());
+        assert!(dag.leaves.values().all(|leaf| leaf.content.is_none()));
+
+        // The structure verifies without its content
+        assert_eq!(dag.kind(), DagKind::Structure);
+        dag.verify()?;
+        assert!(dag.clone().as_full().verify().is_err());
+
+        // Every leaf's block was pushed exactly once
+        let blocks = sink.blocks.lock().unwrap().clone();
+        assert_eq!(blocks.len(), dag.leaves.len());
+        assert_eq!(*sink.puts.lock().unwrap(), dag.leaves.len());
+
+        // The pushed blocks make up a DAG that verifies, matching the
+        // returned structure
+        let mut leaves = HashMap::new();
+        for (cid, bytes) in &blocks {
+            let leaf = DagLeaf::from_block_bytes(cid, bytes)?;
+            let structure = &dag.leaves[cid];
+            assert_eq!(leaf.links, structure.links);
+            assert_eq!(leaf.content_hash, structure.content_hash);
+            leaves.insert(cid.clone(), leaf);
+        }
+        let rebuilt = Dag::from_parts(dag.root.clone(), leaves, None)?;
+        rebuilt.verify()?;
+        let file = rebuilt
+            .leaves
+            .values()
+            .find(|leaf| leaf.item_name == "nested/chunked.bin" && !leaf.is_link_group())
+            .unwrap();
+        assert_eq!(rebuilt.get_content_from_leaf(file)?, chunked);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+//! Statistics about a DAG's shape and storage
+
+use crate::error::{Result, ScionicError};
+use crate::types::{Dag, LeafType};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Number of leaves of each type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCounts {
+    pub files: usize,
+    pub directories: usize,
+    pub chunks: usize,
+    pub symlinks: usize,
+
+    /// Intermediate leaves from `max_links_per_leaf`, of any type
+    pub link_groups: usize,
+}
+
+impl TypeCounts {
+    /// Total number of leaves counted
+    pub fn total(&self) -> usize {
+        self.files + self.directories + self.chunks + self.symlinks + self.link_groups
+    }
+}
+
+/// How much of a chunked file's content is repeated across its chunks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkDedupReport {
+    /// Number of chunks the file is split into
+    pub total_chunks: usize,
+    /// Number of distinct chunk contents
+    pub unique_chunks: usize,
+    /// Bytes that would not need storing if identical chunks were kept once
+    pub bytes_saved: u64,
+}
+
+impl Dag {
+    /// Count leaves by type in one pass
+    ///
+    /// Also checks that the root is the only leaf no other leaf links to,
+    /// and that every leaf was counted.
+    pub fn count_by_type(&self) -> Result<TypeCounts> {
+        let mut counts = TypeCounts::default();
+        let mut linked = HashSet::new();
+
+        for leaf in self.leaves.values() {
+            match leaf.leaf_type {
+                _ if leaf.is_link_group() => counts.link_groups += 1,
+                LeafType::File => counts.files += 1,
+                LeafType::Directory => counts.directories += 1,
+                LeafType::Chunk => counts.chunks += 1,
+                LeafType::Symlink => counts.symlinks += 1,
+            }
+            linked.extend(leaf.links.iter().map(String::as_str));
+        }
+
+        if !self.leaves.contains_key(&self.root) {
+            return Err(ScionicError::MissingLeaf("Root leaf not found".to_string()));
+        }
+
+        let roots: Vec<&String> = self
+            .leaves
+            .keys()
+            .filter(|hash| !linked.contains(hash.as_str()))
+            .collect();
+        if roots != [&self.root] {
+            return Err(ScionicError::InvalidDag(format!(
+                "Expected exactly one root, found {}",
+                roots.len()
+            )));
+        }
+
+        if counts.total() != self.leaves.len() {
+            return Err(ScionicError::InvalidDag(format!(
+                "Counted {} leaves, expected {}",
+                counts.total(),
+                self.leaves.len()
+            )));
+        }
+
+        Ok(counts)
+    }
+
+    /// Total content bytes of the files and chunks reachable from a leaf
+    ///
+    /// Unlike the root's `dag_size`, this counts content only, without
+    /// serialization overhead.
+    pub fn subtree_size(&self, hash: &str) -> Result<u64> {
+        self.subtree_size_memo(hash, &mut HashMap::new())
+    }
+
+    /// Content size under every leaf reachable from the root, by hash
+    ///
+    /// Computed in one pass, for callers that query many nodes.
+    pub fn subtree_sizes(&self) -> Result<HashMap<String, u64>> {
+        let mut sizes = HashMap::new();
+        self.subtree_size_memo(&self.root, &mut sizes)?;
+        Ok(sizes)
+    }
+
+    /// Bytes of distinct content in the DAG
+    ///
+    /// Content held by several leaves is counted once. Leaves already share
+    /// a CID when their names match, e.g. chunks under
+    /// [`ChunkNaming::IndexOnly`](crate::ChunkNaming::IndexOnly), and the
+    /// root's `content_size` counts those once too; this also merges leaves
+    /// that differ only in name, by content hash.
+    pub fn unique_content_size(&self) -> u64 {
+        let mut seen = HashSet::new();
+        self.leaves
+            .values()
+            .filter_map(|leaf| {
+                let content = leaf.content.as_ref()?;
+                let key = leaf.content_hash.as_deref().unwrap_or(leaf.hash.as_bytes());
+                seen.insert(key).then_some(content.len() as u64)
+            })
+            .sum()
+    }
+
+    fn subtree_size_memo(&self, hash: &str, sizes: &mut HashMap<String, u64>) -> Result<u64> {
+        if let Some(size) = sizes.get(hash) {
+            return Ok(*size);
+        }
+
+        let leaf = self
+            .leaves
+            .get(hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(hash.to_string()))?;
+
+        let mut size = leaf.content.as_ref().map_or(0, |c| c.len() as u64);
+        for link in &leaf.links {
+            size += self.subtree_size_memo(link, sizes)?;
+        }
+
+        sizes.insert(hash.to_string(), size);
+        Ok(size)
+    }
+
+    /// Report intra-file chunk duplication for a file leaf
+    ///
+    /// Chunk names include their index, so identical chunks still get distinct
+    /// CIDs; duplicates are therefore found by content hash. A file that is
+    /// not chunked reports zero chunks.
+    pub fn chunk_dedup_report(&self, file_hash: &str) -> Result<ChunkDedupReport> {
+        let leaf = self
+            .leaves
+            .get(file_hash)
+            .ok_or_else(|| ScionicError::MissingLeaf(file_hash.to_string()))?;
+
+        if leaf.leaf_type != LeafType::File {
+            return Err(ScionicError::InvalidLeaf(format!(
+                "{} is not a file leaf",
+                file_hash
+            )));
+        }
+
+        let mut report = ChunkDedupReport::default();
+        let mut seen: HashSet<&[u8]> = HashSet::new();
+
+        for chunk in self.linked_leaves(leaf)? {
+            let content_hash = chunk.content_hash.as_deref().unwrap_or_default();
+            let size = chunk.content.as_ref().map_or(0, |c| c.len() as u64);
+
+            report.total_chunks += 1;
+            if !seen.insert(content_hash) {
+                report.bytes_saved += size;
+            } else {
+                report.unique_chunks += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// SHA-256 fingerprint of every file's path and content
+    ///
+    /// Hashes the sorted (path, whole-content hash) pairs from
+    /// [`flatten`](Self::flatten), so it changes when any file's bytes, path
+    /// or existence change, but not with chunking, the root's name or other
+    /// metadata that changes the root CID. Empty directories don't count.
+    pub fn data_digest(&self) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        for (path, content_hash) in self.flatten()? {
+            hasher.update((path.len() as u64).to_le_bytes());
+            hasher.update(path.as_bytes());
+            hasher.update((content_hash.len() as u64).to_le_bytes());
+            hasher.update(content_hash);
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// Find directories whose subtrees hold identical content
+    ///
+    /// Item names are full relative paths and part of each leaf's hash, so
+    /// identical directories at different paths never share a CID. Instead,
+    /// each directory gets a digest of its entries' names, types and content,
+    /// and every digest shared by more than one directory is reported (hex
+    /// encoded) with the sorted logical paths it appears at. The root's path
+    /// is empty. Directories with missing descendants are skipped.
+    pub fn identical_subtrees(&self) -> Vec<(String, Vec<PathBuf>)> {
+        let mut digests = HashMap::new();
+        let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+        for leaf in self.leaves.values() {
+            if leaf.leaf_type != LeafType::Directory || leaf.is_link_group() {
+                continue;
+            }
+            if let Some(digest) = self.subtree_digest(&leaf.hash, &mut digests) {
+                let path = if leaf.hash == self.root {
+                    PathBuf::new()
+                } else {
+                    PathBuf::from(&leaf.item_name)
+                };
+                groups.entry(hex::encode(digest)).or_default().push(path);
+            }
+        }
+
+        groups
+            .into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|(digest, mut paths)| {
+                paths.sort();
+                (digest, paths)
+            })
+            .collect()
+    }
+
+    /// Name-independent digest of a file's content or a directory's entries
+    fn subtree_digest(
+        &self,
+        hash: &str,
+        digests: &mut HashMap<String, Option<Vec<u8>>>,
+    ) -> Option<Vec<u8>> {
+        if let Some(digest) = digests.get(hash) {
+            return digest.clone();
+        }
+
+        let leaf = self.leaves.get(hash)?;
+        let digest = match leaf.leaf_type {
+            LeafType::File => self.file_content_hash(leaf).ok(),
+            LeafType::Symlink => leaf.content_hash.clone(),
+            LeafType::Chunk => None,
+            LeafType::Directory => {
+                let mut entries = Vec::with_capacity(leaf.links.len());
+                for child in self.linked_leaves(leaf).ok()? {
+                    let name = child.item_name.rsplit('/').next().unwrap_or_default();
+                    let tag = match child.leaf_type {
+                        LeafType::File => b'f',
+                        LeafType::Symlink => b'l',
+                        _ => b'd',
+                    };
+                    entries.push((name, tag, self.subtree_digest(&child.hash, digests)?));
+                }
+                entries.sort();
+
+                let mut hasher = Sha256::new();
+                for (name, tag, digest) in entries {
+                    hasher.update((name.len() as u64).to_le_bytes());
+                    hasher.update(name.as_bytes());
+                    hasher.update([tag]);
+                    hasher.update(digest);
+                }
+                Some(hasher.finalize().to_vec())
+            }
+        };
+
+        digests.insert(hash.to_string(), digest.clone());
+        digest
+    }
+}
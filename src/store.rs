@@ -0,0 +1,109 @@
+//! Block storage
+//!
+//! [`BlockStore`] abstracts where leaves live, so a receiver can verify
+//! incoming transmission packets and persist them one at a time instead of
+//! holding the whole DAG in memory.
+
+use crate::error::{Result, ScionicError};
+use crate::pool::LeafPool;
+use crate::proof::verify_child_in_parent;
+use crate::types::{Dag, DagKind, DagLeaf, TransmissionPacket};
+use std::collections::HashMap;
+
+/// Storage for leaves keyed by CID
+pub trait BlockStore {
+    /// Store a leaf under its CID
+    fn put(&mut self, leaf: DagLeaf) -> Result<()>;
+
+    /// Fetch the leaf stored under `cid`, if any
+    fn get(&self, cid: &str) -> Result<Option<DagLeaf>>;
+
+    /// Load the leaves reachable from `root` into a full DAG
+    ///
+    /// Fails with `MissingLeaf` if any reachable leaf isn't stored. The DAG
+    /// is not verified.
+    fn load_dag(&self, root: &str) -> Result<Dag> {
+        let mut leaves = HashMap::new();
+        let mut stack = vec![root.to_string()];
+
+        while let Some(hash) = stack.pop() {
+            if leaves.contains_key(&hash) {
+                continue;
+            }
+            let leaf = self
+                .get(&hash)?
+                .ok_or_else(|| ScionicError::MissingLeaf(hash.clone()))?;
+            stack.extend(leaf.links.iter().cloned());
+            leaves.insert(hash, leaf);
+        }
+
+        Ok(Dag {
+            root: root.to_string(),
+            leaves,
+            labels: None,
+            kind: Some(DagKind::Full),
+        })
+    }
+}
+
+impl BlockStore for LeafPool {
+    fn put(&mut self, leaf: DagLeaf) -> Result<()> {
+        LeafPool::put(self, leaf)?;
+        Ok(())
+    }
+
+    fn get(&self, cid: &str) -> Result<Option<DagLeaf>> {
+        Ok(LeafPool::get(self, cid).cloned())
+    }
+}
+
+/// Verify a transmission packet and persist its leaf to a store
+///
+/// `root` is the root CID the caller trusts. The root's packet has no
+/// parent hash and must carry that root; every other leaf's parent must
+/// already be stored, so packets arrive parents first and each leaf chains
+/// to the root. The leaf must be one of its parent's links and be committed
+/// to by its Merkle root, using the packet's proof for the leaf if it has
+/// one. The leaf's CID and content are checked before it is stored.
+pub fn apply_and_verify_to_store<S: BlockStore>(
+    packet: TransmissionPacket,
+    root: &str,
+    store: &mut S,
+) -> Result<()> {
+    let leaf = packet.leaf;
+
+    if packet.parent_hash.is_empty() {
+        if leaf.hash != root {
+            return Err(ScionicError::HashMismatch {
+                expected: root.to_string(),
+                got: leaf.hash,
+            });
+        }
+        leaf.verify_root_leaf()?;
+    } else {
+        leaf.verify_leaf()?;
+
+        let parent = store.get(&packet.parent_hash)?.ok_or_else(|| {
+            ScionicError::MissingLeaf(format!(
+                "Parent {} of {} not stored yet",
+                packet.parent_hash, leaf.hash
+            ))
+        })?;
+        if !parent.has_link(&leaf.hash) {
+            return Err(ScionicError::InvalidDag(format!(
+                "Parent {} does not link to child {}",
+                parent.hash, leaf.hash
+            )));
+        }
+
+        let branch = match packet.proofs.get(&leaf.hash) {
+            Some(branch) => Some(branch.clone()),
+            None => parent.get_branch(&leaf.hash)?,
+        };
+        verify_child_in_parent(&parent, &leaf.hash, branch.as_ref())?;
+    }
+
+    leaf.verify_content_fields()?;
+    leaf.verify_content_hash()?;
+    store.put(leaf)
+}
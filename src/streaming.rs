@@ -1,14 +1,26 @@
 use crate::error::{Result, ScionicError};
-use crate::types::{Dag, DagLeaf, DagLeafBuilder, LeafType, DEFAULT_CHUNK_SIZE};
-use std::collections::HashMap;
+use crate::leaf::DescendantTotals;
+use crate::types::{
+    ChunkNaming, Dag, DagKind, DagLeaf, DagLeafBuilder, HashAlgorithm, LeafType, CHUNK_NAMING_KEY,
+    DEFAULT_CHUNK_SIZE,
+};
+use multibase::Base;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Read;
 
 /// Streaming DAG builder for large files
 pub struct StreamingDagBuilder {
     file_name: String,
     chunk_size: usize,
+    chunk_naming: ChunkNaming,
+    cid_base: Base,
+    hash_algorithm: HashAlgorithm,
     chunks: Vec<DagLeaf>,
     chunk_count: usize,
+    discard_content: bool,
+    /// Totals over the distinct chunks so far, for the root
+    totals: DescendantTotals,
+    seen: HashSet<String>,
 }
 
 impl StreamingDagBuilder {
@@ -16,8 +28,14 @@ impl StreamingDagBuilder {
         Self {
             file_name: file_name.into(),
             chunk_size: DEFAULT_CHUNK_SIZE,
+            chunk_naming: ChunkNaming::default(),
+            cid_base: Base::Base32Lower,
+            hash_algorithm: HashAlgorithm::default(),
             chunks: Vec::new(),
             chunk_count: 0,
+            discard_content: false,
+            totals: DescendantTotals::default(),
+            seen: HashSet::new(),
         }
     }
 
@@ -26,6 +44,45 @@ impl StreamingDagBuilder {
         self
     }
 
+    pub fn with_chunk_naming(mut self, naming: ChunkNaming) -> Self {
+        self.chunk_naming = naming;
+        self
+    }
+
+    /// Multibase the CIDs are written in, as with
+    /// [`DagBuilderConfig::with_cid_base`](crate::DagBuilderConfig::with_cid_base)
+    pub fn with_cid_base(mut self, base: Base) -> Self {
+        self.cid_base = base;
+        self
+    }
+
+    /// Hash for CIDs, content hashes and merkle roots, as with
+    /// [`DagBuilderConfig::with_hash_algorithm`](crate::DagBuilderConfig::with_hash_algorithm)
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    /// Drop each chunk's content once it is hashed, so memory stays bounded
+    /// however large the file
+    ///
+    /// The root is unchanged, but the finalized DAG holds the structure only
+    /// and is marked [`DagKind::Structure`]: its chunk leaves have no
+    /// content, which has to be fetched on demand.
+    pub fn with_content_discard(mut self) -> Self {
+        self.discard_content = true;
+        self
+    }
+
+    /// Bytes of chunk content currently held
+    pub fn retained_content_size(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter_map(|chunk| chunk.content.as_ref())
+            .map(Vec::len)
+            .sum()
+    }
+
     /// Process a chunk of data and return the current root CID
     pub fn add_chunk(&mut self, data: Vec<u8>) -> Result<String> {
         if data.is_empty() {
@@ -33,12 +90,24 @@ impl StreamingDagBuilder {
         }
 
         // Create chunk leaf
-        let chunk_name = format!("{}/{}", self.file_name, self.chunk_count);
-        let chunk_leaf = DagLeafBuilder::new(chunk_name)
+        let chunk_name = self
+            .chunk_naming
+            .chunk_name(&self.file_name, self.chunk_count);
+        let mut chunk_leaf = DagLeafBuilder::new(chunk_name)
             .set_type(LeafType::Chunk)
+            .set_hash_algorithm(self.hash_algorithm)
+            .set_cid_base(self.cid_base)
             .set_data(data)
             .build_leaf(None)?;
 
+        // Count the chunk before its content can be dropped
+        if self.seen.insert(chunk_leaf.hash.clone()) {
+            self.totals.add(&chunk_leaf)?;
+        }
+        if self.discard_content {
+            chunk_leaf.content = None;
+        }
+
         self.chunks.push(chunk_leaf);
         self.chunk_count += 1;
 
@@ -53,7 +122,10 @@ impl StreamingDagBuilder {
         }
 
         // Build parent file leaf
-        let mut leaf_builder = DagLeafBuilder::new(self.file_name.clone()).set_type(LeafType::File);
+        let mut leaf_builder = DagLeafBuilder::new(self.file_name.clone())
+            .set_type(LeafType::File)
+            .set_hash_algorithm(self.hash_algorithm)
+            .set_cid_base(self.cid_base);
 
         for chunk in &self.chunks {
             leaf_builder = leaf_builder.add_link(chunk.hash.clone());
@@ -71,29 +143,44 @@ impl StreamingDagBuilder {
             ));
         }
 
-        let mut leaves = HashMap::new();
-
-        // Add all chunk leaves
-        for chunk in &self.chunks {
-            leaves.insert(chunk.hash.clone(), chunk.clone());
-        }
-
         // Build root file leaf
-        let mut root_builder = DagLeafBuilder::new(self.file_name.clone()).set_type(LeafType::File);
+        let mut root_builder = DagLeafBuilder::new(self.file_name.clone())
+            .set_type(LeafType::File)
+            .set_hash_algorithm(self.hash_algorithm)
+            .set_cid_base(self.cid_base);
 
         for chunk in &self.chunks {
             root_builder = root_builder.add_link(chunk.hash.clone());
         }
 
-        let root = root_builder.build_root_leaf(&leaves, None)?;
+        // Add all chunk leaves
+        let mut leaves = HashMap::new();
+        for chunk in self.chunks {
+            leaves.insert(chunk.hash.clone(), chunk);
+        }
+
+        // As in a regular build, a non-default naming is recorded on the root
+        let additional_data = (self.chunk_naming != ChunkNaming::default()).then(|| {
+            BTreeMap::from([(
+                CHUNK_NAMING_KEY.to_string(),
+                self.chunk_naming.as_str().to_string(),
+            )])
+        });
+        let root = root_builder.build_root_leaf_with_totals(self.totals, additional_data)?;
         let root_hash = root.hash.clone();
 
         leaves.insert(root_hash.clone(), root);
 
+        let kind = if self.discard_content {
+            DagKind::Structure
+        } else {
+            DagKind::Full
+        };
         Ok(Dag {
             root: root_hash,
             leaves,
             labels: None,
+            kind: Some(kind),
         })
     }
 
@@ -113,9 +200,8 @@ impl StreamingDagBuilder {
 
                     // If we've accumulated a full chunk, process it
                     if chunk_data.len() >= self.chunk_size {
-                        let cid = self.add_chunk(chunk_data.clone())?;
+                        let cid = self.add_chunk(std::mem::take(&mut chunk_data))?;
                         callback(&cid);
-                        chunk_data.clear();
                     }
                 }
             }
@@ -204,4 +290,96 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_streaming_hash_algorithm() -> Result<()> {
+        let data: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+
+        for algorithm in [HashAlgorithm::Sha512_256, HashAlgorithm::Blake3] {
+            let dag = StreamingDagBuilder::new("data.bin")
+                .with_chunk_size(1024)
+                .with_hash_algorithm(algorithm)
+                .stream_from_reader(Cursor::new(&data), |_| {})?;
+            dag.verify()?;
+            for leaf in dag.leaves.values() {
+                assert_eq!(leaf.hash_algorithm()?, algorithm);
+            }
+
+            // Same root as building the bytes with that algorithm
+            let config = crate::DagBuilderConfig::new()
+                .with_chunk_size(1024)
+                .with_hash_algorithm(algorithm);
+            let built = crate::create_dag_from_bytes("data.bin", &data, config)?;
+            assert_eq!(dag.root, built.root);
+        }
+
+        // Index-only naming is recorded on the root, as in a regular build
+        let dag = StreamingDagBuilder::new("data.bin")
+            .with_chunk_size(1024)
+            .with_chunk_naming(ChunkNaming::IndexOnly)
+            .stream_from_reader(Cursor::new(&data), |_| {})?;
+        dag.verify()?;
+        assert_eq!(dag.chunk_naming(), ChunkNaming::IndexOnly);
+        let config = crate::DagBuilderConfig::new()
+            .with_chunk_size(1024)
+            .with_chunk_naming(ChunkNaming::IndexOnly);
+        let built = crate::create_dag_from_bytes("data.bin", &data, config)?;
+        assert_eq!(dag.root, built.root);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_cid_base() -> Result<()> {
+        let mut builder = StreamingDagBuilder::new("test.txt").with_cid_base(Base::Base58Btc);
+        let cid = builder.add_chunk(b"chunk1".to_vec())?;
+        builder.add_chunk(b"chunk2".to_vec())?;
+        assert!(cid.starts_with('z'));
+
+        let dag = builder.finalize()?;
+        dag.verify()?;
+        assert!(dag.leaves.keys().all(|hash| hash.starts_with('z')));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_with_content_discard() -> Result<()> {
+        let chunk_size: usize = 1024 * 1024;
+        let chunk = |i: usize| -> Vec<u8> {
+            (0..chunk_size)
+                .map(|j| (j.wrapping_mul(31) ^ i) as u8)
+                .collect()
+        };
+
+        // 100MB, with no content held past the chunk being hashed
+        let mut discarding = StreamingDagBuilder::new("huge.bin")
+            .with_chunk_size(chunk_size)
+            .with_content_discard();
+        for i in 0..100 {
+            discarding.add_chunk(chunk(i % 7))?;
+            assert_eq!(discarding.retained_content_size(), 0);
+        }
+        let structure = discarding.finalize()?;
+        assert!(structure.leaves.values().all(|leaf| leaf.content.is_none()));
+        assert_eq!(structure.kind(), DagKind::Structure);
+        structure.verify()?;
+
+        // Same DAG as keeping the content, including the root's totals
+        let mut keeping = StreamingDagBuilder::new("huge.bin").with_chunk_size(chunk_size);
+        for i in 0..100 {
+            keeping.add_chunk(chunk(i % 7))?;
+        }
+        assert_eq!(keeping.retained_content_size(), 100 * chunk_size);
+        let full = keeping.finalize()?;
+        full.verify()?;
+
+        assert_eq!(structure.root, full.root);
+        assert_eq!(structure.leaves.len(), full.leaves.len());
+        let root = &structure.leaves[&structure.root];
+        assert_eq!(root.content_size, Some(100 * chunk_size as i64));
+        assert_eq!(root.dag_size, full.leaves[&full.root].dag_size);
+
+        Ok(())
+    }
 }
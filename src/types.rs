@@ -1,5 +1,7 @@
+use multibase::Base;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256, Sha512_256};
+use std::collections::{BTreeMap, HashMap};
 
 /// Custom serde module for Option<Vec<u8>> with bytes encoding
 mod serde_bytes_option {
@@ -31,6 +33,9 @@ pub enum LeafType {
     File,
     Chunk,
     Directory,
+    /// A symbolic link, whose content is the UTF-8 target path (see
+    /// [`SymlinkMode::StoreAsLink`])
+    Symlink,
 }
 
 impl std::fmt::Display for LeafType {
@@ -39,12 +44,14 @@ impl std::fmt::Display for LeafType {
             LeafType::File => write!(f, "file"),
             LeafType::Chunk => write!(f, "chunk"),
             LeafType::Directory => write!(f, "directory"),
+            LeafType::Symlink => write!(f, "symlink"),
         }
     }
 }
 
 /// A leaf in the Scionic Merkle DAG
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct DagLeaf {
     /// CID hash of this leaf
     #[serde(rename = "Hash")]
@@ -109,13 +116,18 @@ pub struct DagLeaf {
     #[serde(rename = "ParentHash", skip_serializing_if = "Option::is_none")]
     pub parent_hash: Option<String>,
 
-    /// Additional metadata
+    /// Additional metadata, kept sorted by key so serialization is stable
     #[serde(rename = "AdditionalData", skip_serializing_if = "Option::is_none")]
-    pub additional_data: Option<HashMap<String, String>>,
+    pub additional_data: Option<BTreeMap<String, String>>,
 
     /// Merkle proofs for partial DAG verification
     #[serde(rename = "stored_proofs", skip_serializing_if = "Option::is_none")]
     pub proofs: Option<HashMap<String, ClassicTreeBranch>>,
+
+    /// Fields not known to this implementation, kept so they round-trip
+    /// (never part of the hash)
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_cbor::Value>,
 }
 
 /// Classic Merkle tree branch/proof for a specific leaf
@@ -144,6 +156,7 @@ pub struct MerkleProof {
 
 /// The main Scionic Merkle DAG structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Dag {
     /// Root leaf hash
     #[serde(rename = "Root")]
@@ -156,6 +169,112 @@ pub struct Dag {
     /// Labels mapping (numeric labels to hashes)
     #[serde(rename = "Labels", skip_serializing_if = "Option::is_none")]
     pub labels: Option<HashMap<String, String>>,
+
+    /// Whether this DAG is full or partial, when known (not serialized;
+    /// inferred from the root's leaf count when unset)
+    #[serde(skip)]
+    pub kind: Option<DagKind>,
+}
+
+/// Progress reported while verifying a DAG
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyProgress {
+    /// Leaves verified so far
+    pub verified: usize,
+
+    /// Total number of leaves to verify
+    pub total: usize,
+}
+
+/// Which checks [`Dag::verify_with`] runs
+///
+/// The default runs every check, as [`Dag::verify`] does. Turning checks off
+/// trades safety for speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyOptions {
+    /// Recompute each leaf's CID and check its structural invariants
+    pub check_cids: bool,
+
+    /// Check stored content against its content hash
+    pub check_content: bool,
+
+    /// Check that every leaf hangs off the root, that no directory has two
+    /// entries with the same name and, in a full DAG, that every link
+    /// resolves
+    pub check_reachability: bool,
+
+    /// Check recorded file sizes and, in a full DAG, the root's leaf count
+    /// and content size
+    pub check_sizes: bool,
+
+    /// Check the Merkle proofs stored in a partial DAG
+    pub check_proofs: bool,
+}
+
+impl VerifyOptions {
+    /// Run no checks, as a base for enabling individual ones
+    pub fn none() -> Self {
+        Self {
+            check_cids: false,
+            check_content: false,
+            check_reachability: false,
+            check_sizes: false,
+            check_proofs: false,
+        }
+    }
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            check_cids: true,
+            check_content: true,
+            check_reachability: true,
+            check_sizes: true,
+            check_proofs: true,
+        }
+    }
+}
+
+/// How [`Dag::create_directory_with_mode`] handles per-item errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CreateDirectoryMode {
+    /// Stop at the first error
+    #[default]
+    AbortOnError,
+
+    /// Keep going and report every item that failed
+    BestEffort,
+}
+
+/// How building a DAG treats symbolic links inside a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkMode {
+    /// Store the file or directory the link points to
+    #[default]
+    Follow,
+
+    /// Leave symlinks out of the DAG
+    Skip,
+
+    /// Store the link itself as a [`LeafType::Symlink`] leaf holding its
+    /// target path
+    StoreAsLink,
+}
+
+/// Whether a DAG holds every leaf or only a verifiable subset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DagKind {
+    /// Every leaf is present
+    Full,
+
+    /// Only some leaves are present, verified with Merkle proofs
+    Partial,
+
+    /// Every leaf is present with its content dropped, as built by
+    /// streaming the blocks elsewhere; verified as a full DAG except for
+    /// content and the sizes it makes up
+    Structure,
 }
 
 /// Transmission packet for syncing individual leaves
@@ -175,22 +294,110 @@ pub struct TransmissionPacket {
 }
 
 /// Configuration for DAG building
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct DagBuilderConfig {
     /// Enable parallel processing
+    ///
+    /// Directory entries and chunks are hashed in parallel and merged back
+    /// in order, so the DAG is identical to a sequential build.
     pub enable_parallel: bool,
 
-    /// Maximum number of workers (0 = auto)
+    /// Maximum number of workers for a parallel build (0 = the global rayon
+    /// pool)
     pub max_workers: usize,
 
     /// Add timestamp to root
     pub timestamp_root: bool,
 
     /// Additional metadata for root
-    pub additional_data: HashMap<String, String>,
+    pub additional_data: BTreeMap<String, String>,
 
     /// Chunk size (None = use default, Some(0) = disable chunking)
     pub chunk_size: Option<usize>,
+
+    /// How chunk leaves are named (changes chunk CIDs); anything but the
+    /// default is recorded on the root
+    pub chunk_naming: ChunkNaming,
+
+    /// Record each directory's number of entries in its additional data
+    pub include_entry_counts: bool,
+
+    /// Item name for the root instead of the source path's file name
+    pub root_name: Option<String>,
+
+    /// Record each file's total size in its additional data
+    pub include_file_sizes: bool,
+
+    /// Merge a trailing chunk smaller than this fraction of the chunk size
+    /// into the previous chunk (0 = never; changes chunk CIDs)
+    pub coalesce_small_tail: f32,
+
+    /// Hash function for CIDs, content hashes and classic Merkle trees
+    pub hash_algorithm: HashAlgorithm,
+
+    /// Multibase CIDs are written in (base32 lower by default, as Go does).
+    /// Links and Merkle roots are over the CID strings, so this changes the
+    /// root.
+    pub cid_base: Base,
+
+    /// Split the links of any leaf with more than this many under a
+    /// balanced tree of intermediate link-group leaves (0 = unlimited;
+    /// changes CIDs)
+    pub max_links_per_leaf: usize,
+
+    /// Expected number of leaves (chunks included), used to pre-size the
+    /// leaf map of large builds
+    pub leaf_capacity_hint: Option<usize>,
+
+    /// Fail on file names that aren't valid UTF-8 instead of converting them
+    /// lossily (on by default)
+    pub require_utf8_names: bool,
+
+    /// Record the chunking parameters in the root's additional data
+    /// (changes the root CID)
+    pub record_chunk_params: bool,
+
+    /// How symlinks inside a directory are handled
+    pub symlink_mode: SymlinkMode,
+
+    /// Convert CRLF line endings to LF in text files without bare LFs before
+    /// hashing, and mark the root as normalized (changes content hashes and
+    /// CIDs)
+    pub normalize_line_endings: bool,
+
+    /// Fail the build on any file larger than this many bytes
+    pub max_file_size: Option<u64>,
+
+    /// Fail the build once the files read add up to more than this many
+    /// bytes
+    pub max_total_size: Option<u64>,
+}
+
+impl Default for DagBuilderConfig {
+    fn default() -> Self {
+        Self {
+            enable_parallel: false,
+            max_workers: 0,
+            timestamp_root: false,
+            additional_data: BTreeMap::new(),
+            chunk_size: None,
+            chunk_naming: ChunkNaming::default(),
+            include_entry_counts: false,
+            root_name: None,
+            include_file_sizes: false,
+            coalesce_small_tail: 0.0,
+            hash_algorithm: HashAlgorithm::default(),
+            cid_base: Base::Base32Lower,
+            max_links_per_leaf: 0,
+            leaf_capacity_hint: None,
+            require_utf8_names: true,
+            record_chunk_params: false,
+            symlink_mode: SymlinkMode::default(),
+            normalize_line_endings: false,
+            max_file_size: None,
+            max_total_size: None,
+        }
+    }
 }
 
 impl DagBuilderConfig {
@@ -213,8 +420,11 @@ impl DagBuilderConfig {
         self
     }
 
-    pub fn with_additional_data(mut self, data: HashMap<String, String>) -> Self {
-        self.additional_data = data;
+    pub fn with_additional_data(
+        mut self,
+        data: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        self.additional_data = data.into_iter().collect();
         self
     }
 
@@ -227,17 +437,250 @@ impl DagBuilderConfig {
         self.chunk_size = Some(0);
         self
     }
+
+    pub fn with_chunk_naming(mut self, naming: ChunkNaming) -> Self {
+        self.chunk_naming = naming;
+        self
+    }
+
+    pub fn with_entry_counts(mut self) -> Self {
+        self.include_entry_counts = true;
+        self
+    }
+
+    pub fn with_root_name(mut self, name: impl Into<String>) -> Self {
+        self.root_name = Some(name.into());
+        self
+    }
+
+    pub fn with_file_sizes(mut self) -> Self {
+        self.include_file_sizes = true;
+        self
+    }
+
+    pub fn with_coalesce_small_tail(mut self, fraction: f32) -> Self {
+        self.coalesce_small_tail = fraction;
+        self
+    }
+
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    pub fn with_cid_base(mut self, base: Base) -> Self {
+        self.cid_base = base;
+        self
+    }
+
+    pub fn with_max_links_per_leaf(mut self, max: usize) -> Self {
+        self.max_links_per_leaf = max;
+        self
+    }
+
+    pub fn with_leaf_capacity_hint(mut self, leaves: usize) -> Self {
+        self.leaf_capacity_hint = Some(leaves);
+        self
+    }
+
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    pub fn with_max_total_size(mut self, bytes: u64) -> Self {
+        self.max_total_size = Some(bytes);
+        self
+    }
+
+    pub fn with_require_utf8_names(mut self, require: bool) -> Self {
+        self.require_utf8_names = require;
+        self
+    }
+
+    pub fn with_chunk_params(mut self) -> Self {
+        self.record_chunk_params = true;
+        self
+    }
+
+    pub fn with_symlink_mode(mut self, mode: SymlinkMode) -> Self {
+        self.symlink_mode = mode;
+        self
+    }
+
+    pub fn with_normalized_line_endings(mut self) -> Self {
+        self.normalize_line_endings = true;
+        self
+    }
+}
+
+/// Hash function used throughout a DAG
+///
+/// The algorithm is recorded as the multihash code of every leaf's CID, so
+/// verification reads it back from the CID. Only SHA-256 is compatible with
+/// the Go implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgorithm {
+    /// SHA2-256 (multihash 0x12), matching the Go implementation
+    #[default]
+    Sha256,
+
+    /// SHA2-512/256 (multihash 0x1013)
+    Sha512_256,
+
+    /// BLAKE3 with 32-byte output (multihash 0x1e), faster on large content
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Multihash code identifying this algorithm in a CID
+    pub fn multihash_code(&self) -> u64 {
+        match self {
+            HashAlgorithm::Sha256 => 0x12,
+            HashAlgorithm::Sha512_256 => 0x1013,
+            HashAlgorithm::Blake3 => 0x1e,
+        }
+    }
+
+    /// Algorithm for a CID's multihash code, if supported
+    pub fn from_multihash_code(code: u64) -> Option<Self> {
+        match code {
+            0x12 => Some(HashAlgorithm::Sha256),
+            0x1013 => Some(HashAlgorithm::Sha512_256),
+            0x1e => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Hash `data`
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            HashAlgorithm::Sha512_256 => Sha512_256::digest(data).to_vec(),
+            HashAlgorithm::Blake3 => blake3::hash(data).as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Naming scheme for chunk leaves
+///
+/// The chunk name is part of the hashed leaf data, so switching schemes
+/// changes every chunk CID (and therefore the root).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkNaming {
+    /// `{file path}/{index}`, matching the Go implementation
+    #[default]
+    PathBased,
+
+    /// `{index}` only, so a chunk's CID depends on its content and position
+    /// but not on the file's path, and survives renames
+    IndexOnly,
+}
+
+impl ChunkNaming {
+    /// Item name for the chunk at `index` of the file at `file_path`
+    pub fn chunk_name(&self, file_path: &str, index: usize) -> String {
+        match self {
+            ChunkNaming::PathBased => format!("{}/{}", file_path, index),
+            ChunkNaming::IndexOnly => index.to_string(),
+        }
+    }
+
+    /// Name recorded under [`CHUNK_NAMING_KEY`]
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ChunkNaming::PathBased => "path",
+            ChunkNaming::IndexOnly => "index",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(ChunkNaming::PathBased),
+            "index" => Some(ChunkNaming::IndexOnly),
+            _ => None,
+        }
+    }
+}
+
+/// Chunking parameters a DAG was built with, recorded on its root when
+/// `record_chunk_params` is set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkParams {
+    /// Effective chunk size in bytes (0 = chunking disabled)
+    pub chunk_size: usize,
+
+    /// How chunk leaves were named
+    pub chunk_naming: ChunkNaming,
+
+    /// Fraction of the chunk size below which a trailing chunk was merged
+    pub coalesce_small_tail: f32,
+}
+
+impl ChunkParams {
+    /// The parameters a build with `config` uses
+    pub fn from_config(config: &DagBuilderConfig) -> Self {
+        Self {
+            chunk_size: config.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+            chunk_naming: config.chunk_naming,
+            coalesce_small_tail: config.coalesce_small_tail,
+        }
+    }
 }
 
 /// Chunk size configuration
 pub const DEFAULT_CHUNK_SIZE: usize = 2 * 1024 * 1024; // 2MB
 
+/// Additional data key holding a directory's number of entries
+pub const ENTRY_COUNT_KEY: &str = "entry_count";
+
+/// Additional data key holding a file's total content size
+pub const FILE_SIZE_KEY: &str = "file_size";
+
+/// Additional data key marking an intermediate leaf that holds some of its
+/// parent's links (see `max_links_per_leaf`)
+pub const LINK_GROUP_KEY: &str = "link_group";
+
+/// Additional data key holding an RFC 3339 creation time
+pub const TIMESTAMP_KEY: &str = "timestamp";
+
+/// Additional data key holding the CID of a previous version
+pub const PREV_KEY: &str = "prev";
+
+/// Additional data key holding Unix permission bits in octal
+pub const UNIX_MODE_KEY: &str = "unix_mode";
+
+/// Additional data key holding a MIME type
+pub const MIME_KEY: &str = "mime";
+
+/// Additional data key holding a content encoding such as `gzip`
+pub const CONTENT_ENCODING_KEY: &str = "content_encoding";
+
+/// Root additional data key holding the chunk size (see `record_chunk_params`)
+pub const CHUNK_SIZE_KEY: &str = "chunk_size";
+
+/// Root additional data key holding the chunk naming scheme
+pub const CHUNK_NAMING_KEY: &str = "chunk_naming";
+
+/// Root additional data key holding the small tail coalescing fraction
+pub const COALESCE_SMALL_TAIL_KEY: &str = "coalesce_small_tail";
+
+/// Root additional data key marking the line endings text files were
+/// normalized to (see `normalize_line_endings`)
+pub const LINE_ENDINGS_KEY: &str = "line_endings";
+
+/// Root additional data key listing, as a JSON array, the text files that
+/// had CRLF line endings before being normalized
+pub const CRLF_FILES_KEY: &str = "crlf_files";
+
 /// Builder for constructing DAG leaves
 pub struct DagLeafBuilder {
     pub(crate) item_name: String,
     pub(crate) leaf_type: Option<LeafType>,
     pub(crate) data: Option<Vec<u8>>,
     pub(crate) links: Vec<String>,
+    pub(crate) hash_algorithm: HashAlgorithm,
+    pub(crate) cid_base: Base,
 }
 
 impl DagLeafBuilder {
@@ -247,6 +690,8 @@ impl DagLeafBuilder {
             leaf_type: None,
             data: None,
             links: Vec::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            cid_base: Base::Base32Lower,
         }
     }
 
@@ -264,4 +709,14 @@ impl DagLeafBuilder {
         self.links.push(hash);
         self
     }
+
+    pub fn set_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    pub fn set_cid_base(mut self, base: Base) -> Self {
+        self.cid_base = base;
+        self
+    }
 }
@@ -0,0 +1,674 @@
+use multibase::Base;
+use scionic_merkle_tree_rs::{
+    create_dag, create_dag_from_bytes, create_dag_with_config, create_dags, ChunkNaming, Dag,
+    DagBuilderConfig, HashAlgorithm, LeafType, Result, ScionicError,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_fluent_builder_nested_dag() -> Result<()> {
+    let content: Vec<u8> = (0..100).collect();
+
+    let dag = Dag::builder()
+        .with_name("project")
+        .with_chunk_size(32)
+        .add_file("readme.txt", "hello")
+        .add_file("src/data.bin", content.clone())
+        .build()?;
+    dag.verify()?;
+
+    let temp_dir = TempDir::new()?;
+    let output = temp_dir.path().join("project");
+    dag.create_directory(&output)?;
+
+    assert_eq!(fs::read_to_string(output.join("readme.txt"))?, "hello");
+    assert_eq!(fs::read(output.join("src").join("data.bin"))?, content);
+
+    // Building the recreated tree from disk gives the same root
+    let from_disk = create_dag_with_config(&output, DagBuilderConfig::new().with_chunk_size(32))?;
+    assert_eq!(from_disk.root, dag.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_fluent_builder_rejects_conflicting_paths() {
+    let result = Dag::builder()
+        .add_file("a", "file")
+        .add_file("a/b.txt", "nested under a file")
+        .build();
+    assert!(result.is_err());
+
+    let result = Dag::builder()
+        .add_file("a.txt", "one")
+        .add_file("a.txt", "two")
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_graft_subtree() -> Result<()> {
+    let base = Dag::builder()
+        .with_name("site")
+        .add_file("index.html", "<html></html>")
+        .add_dir("docs")
+        .build()?;
+
+    let subtree = Dag::builder()
+        .with_name("manual")
+        .add_file("intro.md", "# Intro")
+        .add_file("guide/setup.md", "# Setup")
+        .build()?;
+
+    let config = DagBuilderConfig::new();
+    let grafted = base.graft("docs/sub", &subtree, &config)?;
+    grafted.verify()?;
+    assert_ne!(grafted.root, base.root);
+
+    let temp_dir = TempDir::new()?;
+    let output = temp_dir.path().join("site");
+    grafted.create_directory(&output)?;
+
+    assert_eq!(
+        fs::read_to_string(output.join("index.html"))?,
+        "<html></html>"
+    );
+    assert_eq!(
+        fs::read_to_string(output.join("docs/sub/intro.md"))?,
+        "# Intro"
+    );
+    assert_eq!(
+        fs::read_to_string(output.join("docs/sub/guide/setup.md"))?,
+        "# Setup"
+    );
+
+    // The insertion point must be free and its parent must exist
+    assert!(grafted.graft("docs/sub", &subtree, &config).is_err());
+    assert!(base.graft("missing/sub", &subtree, &config).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_create_dag_from_entries_matches_disk() -> Result<()> {
+    use scionic_merkle_tree_rs::create_dag_from_entries;
+    use std::io::{Cursor, Read};
+
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("readme.txt", b"hello".to_vec()),
+        ("docs/guide.md", b"# Guide".to_vec()),
+        ("docs/deep/exact.bin", vec![1u8; 64]),
+        ("data/large.bin", (0..200u8).collect()),
+        ("data/tail.bin", vec![2u8; 65]),
+        ("empty.txt", Vec::new()),
+    ];
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("upload");
+    for (path, data) in &files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, data)?;
+    }
+
+    for config in [
+        DagBuilderConfig::new().with_chunk_size(64),
+        DagBuilderConfig::new()
+            .with_chunk_size(64)
+            .with_coalesce_small_tail(0.5)
+            .with_file_sizes(),
+        DagBuilderConfig::new(),
+    ] {
+        let config = config.with_root_name("upload");
+        let entries = files.iter().map(|(path, data)| {
+            let reader: Box<dyn Read> = Box::new(Cursor::new(data.clone()));
+            (path.to_string(), reader)
+        });
+
+        let dag = create_dag_from_entries(entries, config.clone())?;
+        dag.verify()?;
+        assert_eq!(dag.root, create_dag_with_config(&dir, config)?.root);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_skeleton_keeps_only_directories() -> Result<()> {
+    use scionic_merkle_tree_rs::LeafType;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("project");
+    fs::create_dir_all(dir.join("src").join("nested"))?;
+    fs::create_dir_all(dir.join("docs"))?;
+    fs::create_dir_all(dir.join("empty"))?;
+    fs::write(dir.join("readme.txt"), "readme")?;
+    fs::write(dir.join("src").join("main.rs"), "fn main() {}")?;
+    fs::write(
+        dir.join("src").join("nested").join("big.bin"),
+        vec![0u8; 200],
+    )?;
+    fs::write(dir.join("docs").join("guide.md"), "guide")?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(64)
+        .with_entry_counts();
+    let dag = create_dag_with_config(&dir, config)?;
+
+    let skeleton = dag.skeleton()?;
+    skeleton.verify()?;
+    assert_ne!(skeleton.root, dag.root);
+    assert!(skeleton
+        .leaves
+        .values()
+        .all(|leaf| leaf.leaf_type == LeafType::Directory));
+    assert_eq!(skeleton.leaves.len(), 5);
+
+    let output = temp_dir.path().join("output");
+    skeleton.create_directory(&output)?;
+    for sub in ["src", "src/nested", "docs", "empty"] {
+        let path = output.join(sub);
+        assert!(path.is_dir(), "{} should exist", sub);
+        assert_eq!(
+            fs::read_dir(&path)?
+                .filter(|e| e.as_ref().unwrap().path().is_file())
+                .count(),
+            0
+        );
+    }
+    assert!(!output.join("readme.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_update_paths_matches_full_rebuild() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("project");
+    fs::create_dir_all(dir.join("src").join("nested"))?;
+    fs::create_dir(dir.join("docs"))?;
+    fs::write(dir.join("readme.md"), "readme")?;
+    fs::write(dir.join("src").join("main.rs"), "fn main() {}")?;
+    fs::write(
+        dir.join("src").join("nested").join("data.bin"),
+        vec![1u8; 100],
+    )?;
+    fs::write(dir.join("docs").join("guide.md"), "guide")?;
+
+    let config = || {
+        DagBuilderConfig::new()
+            .with_chunk_size(32)
+            .with_entry_counts()
+    };
+    let base = create_dag_with_config(&dir, config())?;
+
+    // Change two files' content, one of them chunked
+    let main = dir.join("src").join("main.rs");
+    let data = dir.join("src").join("nested").join("data.bin");
+    fs::write(&main, "fn main() { println!(\"changed\"); }")?;
+    fs::write(&data, vec![2u8; 150])?;
+
+    let updated = base.update_paths(&[&main, &data], &dir, config())?;
+    let rebuilt = create_dag_with_config(&dir, config())?;
+    updated.verify()?;
+    assert_eq!(updated.root, rebuilt.root);
+    assert_eq!(updated.leaves.len(), rebuilt.leaves.len());
+    assert!(rebuilt
+        .leaves
+        .keys()
+        .all(|hash| updated.leaves.contains_key(hash)));
+
+    // Added and removed files update the entry counts
+    let added = dir.join("docs").join("new.md");
+    let removed = dir.join("readme.md");
+    fs::write(&added, "new")?;
+    fs::remove_file(&removed)?;
+
+    let updated = updated.update_paths(&[&added, &removed], &dir, config())?;
+    let rebuilt = create_dag_with_config(&dir, config())?;
+    updated.verify()?;
+    assert_eq!(updated.root, rebuilt.root);
+    assert_eq!(updated.leaves.len(), rebuilt.leaves.len());
+
+    // Files can't be added to directories the DAG doesn't have
+    fs::create_dir(dir.join("other"))?;
+    let orphan = dir.join("other").join("file.txt");
+    fs::write(&orphan, "orphan")?;
+    assert!(updated.update_paths(&[&orphan], &dir, config()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_leaf_capacity_hint() -> Result<()> {
+    use scionic_merkle_tree_rs::dag::DagBuilder;
+
+    assert!(DagBuilder::with_capacity(1000).leaves.capacity() >= 1000);
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..20 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content {}", i))?;
+    }
+    fs::write(dir.join("sub").join("large.bin"), vec![9u8; 1000])?;
+
+    let config = || DagBuilderConfig::new().with_chunk_size(64);
+    let plain = create_dag_with_config(&dir, config())?;
+    let hinted = create_dag_with_config(&dir, config().with_leaf_capacity_hint(5000))?;
+
+    assert_eq!(hinted.root, plain.root);
+    assert_eq!(hinted.leaves.len(), plain.leaves.len());
+    assert!(hinted.leaves.capacity() >= 5000);
+    hinted.verify()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_parallel_build_is_deterministic() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    for d in 0..4 {
+        let sub = dir.join(format!("dir{}", d)).join("nested");
+        fs::create_dir_all(&sub)?;
+        for f in 0..6 {
+            fs::write(sub.join(format!("file{}.txt", f)), format!("content {}", f))?;
+        }
+        let chunked: Vec<u8> = (0..5_000u32).map(|i| (i * (d + 1) % 251) as u8).collect();
+        fs::write(dir.join(format!("dir{}", d)).join("chunked.bin"), chunked)?;
+    }
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(256)
+        .with_max_links_per_leaf(4)
+        .with_entry_counts()
+        .with_file_sizes();
+    let sequential = create_dag_with_config(&dir, config.clone())?;
+
+    for i in 0..50 {
+        let parallel_config = if i % 2 == 0 {
+            config.clone().with_parallel()
+        } else {
+            config.clone().with_parallel().with_workers(i % 7 + 2)
+        };
+        let parallel = create_dag_with_config(&dir, parallel_config)?;
+
+        assert_eq!(parallel.root, sequential.root, "iteration {}", i);
+        assert_eq!(parallel.leaves.len(), sequential.leaves.len());
+        for (hash, leaf) in &sequential.leaves {
+            assert_eq!(parallel.leaves[hash].links, leaf.links);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_create_dags_batch() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut dirs = Vec::new();
+    for d in 0..8 {
+        let dir = temp_dir.path().join(format!("dir{}", d));
+        fs::create_dir_all(dir.join("sub"))?;
+        fs::write(dir.join("a.txt"), format!("a {}", d))?;
+        fs::write(dir.join("sub").join("b.txt"), format!("b {}", d))?;
+        dirs.push(dir);
+    }
+    let missing = temp_dir.path().join("missing");
+
+    let mut paths: Vec<&Path> = dirs.iter().map(|dir| dir.as_path()).collect();
+    paths.insert(3, &missing);
+    let results = create_dags(&paths, DagBuilderConfig::new().with_workers(3));
+
+    assert_eq!(results.len(), paths.len());
+    for (path, result) in paths.iter().zip(&results) {
+        if *path == missing {
+            assert!(matches!(result, Err(ScionicError::PathNotFound(_))));
+            continue;
+        }
+        let dag = result.as_ref().unwrap();
+        dag.verify()?;
+        assert_eq!(dag.root, create_dag(path, false)?.root);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_create_dag_from_bytes_matches_file() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let small = b"small content".to_vec();
+    let large: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(temp_dir.path().join("small.txt"), &small)?;
+    fs::write(temp_dir.path().join("large.bin"), &large)?;
+
+    let dag = create_dag_from_bytes("small.txt", &small, DagBuilderConfig::new())?;
+    dag.verify()?;
+    assert_eq!(dag.leaves[&dag.root].leaf_type, LeafType::File);
+    assert_eq!(dag.leaves[&dag.root].item_name, "small.txt");
+    assert_eq!(
+        dag.root,
+        create_dag(temp_dir.path().join("small.txt"), false)?.root
+    );
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_from_bytes("large.bin", &large, config.clone())?;
+    dag.verify()?;
+    assert_eq!(dag.leaves.len(), 11);
+    let expected = create_dag_with_config(temp_dir.path().join("large.bin"), config)?;
+    assert_eq!(dag.root, expected.root);
+
+    assert!(matches!(
+        create_dag_from_bytes("", &small, DagBuilderConfig::new()),
+        Err(ScionicError::InvalidName(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_size_limits() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), vec![b'a'; 400])?;
+    fs::write(dir.join("b.txt"), vec![b'b'; 300])?;
+    fs::write(dir.join("sub").join("c.txt"), vec![b'c'; 300])?;
+
+    // No limits by default
+    let config = DagBuilderConfig::new();
+    assert_eq!(config.max_file_size, None);
+    assert_eq!(config.max_total_size, None);
+    let unlimited = create_dag_with_config(&dir, config)?;
+    unlimited.verify()?;
+
+    // Limits that aren't reached leave the DAG unchanged
+    for config in [
+        DagBuilderConfig::new()
+            .with_max_file_size(400)
+            .with_max_total_size(1000),
+        DagBuilderConfig::new()
+            .with_max_file_size(400)
+            .with_max_total_size(1000)
+            .with_parallel(),
+    ] {
+        assert_eq!(create_dag_with_config(&dir, config)?.root, unlimited.root);
+    }
+
+    for parallel in [false, true] {
+        let config = || {
+            let config = DagBuilderConfig::new();
+            if parallel {
+                config.with_parallel()
+            } else {
+                config
+            }
+        };
+        assert!(matches!(
+            create_dag_with_config(&dir, config().with_max_file_size(399)),
+            Err(ScionicError::InvalidDag(_))
+        ));
+        assert!(matches!(
+            create_dag_with_config(&dir, config().with_max_total_size(999)),
+            Err(ScionicError::InvalidDag(_))
+        ));
+    }
+
+    // A single-file build is held to both limits too
+    let file = dir.join("a.txt");
+    assert!(create_dag_with_config(&file, DagBuilderConfig::new().with_max_file_size(400)).is_ok());
+    assert!(matches!(
+        create_dag_with_config(&file, DagBuilderConfig::new().with_max_total_size(100)),
+        Err(ScionicError::InvalidDag(_))
+    ));
+
+    // So are builds from memory
+    let data = vec![b'a'; 400];
+    let limited = DagBuilderConfig::new().with_max_file_size(399);
+    assert!(matches!(
+        create_dag_from_bytes("a.txt", &data, limited.clone()),
+        Err(ScionicError::InvalidDag(_))
+    ));
+    assert!(matches!(
+        Dag::builder()
+            .with_config(limited)
+            .add_file("a.txt", data)
+            .build(),
+        Err(ScionicError::InvalidDag(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_size_limits_from_entries() -> Result<()> {
+    use scionic_merkle_tree_rs::create_dag_from_entries;
+    use std::io::{Cursor, Read};
+
+    let entries = || {
+        [("a.txt", 400), ("b.txt", 300), ("sub/c.txt", 300)]
+            .into_iter()
+            .map(|(path, size)| {
+                let reader: Box<dyn Read> = Box::new(Cursor::new(vec![b'x'; size]));
+                (path.to_string(), reader)
+            })
+    };
+
+    // Chunked as read, and read in full without chunking
+    for config in [
+        DagBuilderConfig::new().with_chunk_size(128),
+        DagBuilderConfig::new().without_chunking(),
+    ] {
+        create_dag_from_entries(
+            entries(),
+            config
+                .clone()
+                .with_max_file_size(400)
+                .with_max_total_size(1000),
+        )?
+        .verify()?;
+        assert!(matches!(
+            create_dag_from_entries(entries(), config.clone().with_max_file_size(399)),
+            Err(ScionicError::InvalidDag(_))
+        ));
+        assert!(matches!(
+            create_dag_from_entries(entries(), config.with_max_total_size(999)),
+            Err(ScionicError::InvalidDag(_))
+        ));
+    }
+
+    // A reader is given up on once it crosses a limit, even if it never ends
+    let endless: Box<dyn Read> = Box::new(std::io::repeat(b'x'));
+    assert!(matches!(
+        create_dag_from_entries(
+            std::iter::once(("endless.bin".to_string(), endless)),
+            DagBuilderConfig::new()
+                .with_chunk_size(128)
+                .with_max_total_size(10_000),
+        ),
+        Err(ScionicError::InvalidDag(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_add_and_remove_file_match_full_rebuild() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("project");
+    fs::create_dir_all(dir.join("src").join("nested"))?;
+    fs::write(dir.join("readme.md"), "readme")?;
+    fs::write(dir.join("src").join("main.rs"), "fn main() {}")?;
+    fs::write(
+        dir.join("src").join("nested").join("old.bin"),
+        vec![3u8; 100],
+    )?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(32)
+        .with_entry_counts();
+    let mut dag = create_dag_with_config(&dir, config.clone())?;
+
+    // A chunked file added in memory matches writing it and rebuilding
+    let content: Vec<u8> = (0..200u8).collect();
+    dag.add_file("src/nested", "new.bin", content.clone(), &config)?;
+    fs::write(dir.join("src").join("nested").join("new.bin"), &content)?;
+    let rebuilt = create_dag_with_config(&dir, config.clone())?;
+    dag.verify()?;
+    assert_eq!(dag.root, rebuilt.root);
+    assert_eq!(dag.leaves.len(), rebuilt.leaves.len());
+
+    dag.add_file("", "notes.txt", b"notes".to_vec(), &config)?;
+    fs::write(dir.join("notes.txt"), "notes")?;
+    assert_eq!(dag.root, create_dag_with_config(&dir, config.clone())?.root);
+
+    // Removing a chunked file drops its chunks too
+    dag.remove_file("src/nested/old.bin", &config)?;
+    fs::remove_file(dir.join("src").join("nested").join("old.bin"))?;
+    let rebuilt = create_dag_with_config(&dir, config.clone())?;
+    dag.verify()?;
+    assert_eq!(dag.root, rebuilt.root);
+    assert_eq!(dag.leaves.len(), rebuilt.leaves.len());
+    assert!(dag
+        .leaves
+        .values()
+        .all(|leaf| !leaf.item_name.contains("old.bin")));
+
+    // Failed edits leave the DAG untouched
+    let root = dag.root.clone();
+    assert!(matches!(
+        dag.add_file("src", "main.rs", b"again".to_vec(), &config),
+        Err(ScionicError::InvalidDag(_))
+    ));
+    assert!(matches!(
+        dag.add_file("missing", "file.txt", b"orphan".to_vec(), &config),
+        Err(ScionicError::PathNotFound(_))
+    ));
+    assert!(matches!(
+        dag.add_file("src", "a/b", Vec::new(), &config),
+        Err(ScionicError::InvalidName(_))
+    ));
+    assert!(matches!(
+        dag.remove_file("src/missing.rs", &config),
+        Err(ScionicError::PathNotFound(_))
+    ));
+    assert!(matches!(
+        dag.remove_file("src", &config),
+        Err(ScionicError::InvalidType(_))
+    ));
+    assert_eq!(dag.root, root);
+
+    Ok(())
+}
+
+#[test]
+fn test_graft_matches_disk_build() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let site = temp_dir.path().join("site");
+    let manual = temp_dir.path().join("manual");
+    fs::create_dir_all(site.join("docs"))?;
+    fs::create_dir_all(manual.join("guide"))?;
+    fs::write(site.join("index.html"), "<html></html>")?;
+    fs::write(manual.join("intro.md"), "# Intro")?;
+    fs::write(manual.join("guide").join("setup.md"), "# Setup")?;
+    let chunked: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(manual.join("guide").join("data.bin"), &chunked)?;
+    for i in 0..6 {
+        fs::write(manual.join(format!("page{}.md", i)), format!("page {}", i))?;
+    }
+    fs::write(manual.join("win.txt"), "one\r\ntwo\r\n")?;
+    // The graft takes `docs` over `max_links_per_leaf` below
+    for i in 0..3 {
+        fs::write(site.join("docs").join(format!("doc{}.md", i)), "doc")?;
+    }
+
+    for config in [
+        DagBuilderConfig::new().with_chunk_size(512),
+        DagBuilderConfig::new()
+            .with_chunk_size(512)
+            .with_max_links_per_leaf(3)
+            .with_entry_counts(),
+        DagBuilderConfig::new()
+            .with_chunk_size(512)
+            .with_chunk_naming(ChunkNaming::IndexOnly),
+        DagBuilderConfig::new()
+            .with_chunk_size(512)
+            .with_normalized_line_endings(),
+    ] {
+        let base = create_dag_with_config(&site, config.clone())?;
+        let subtree = create_dag_with_config(&manual, config.clone())?;
+        if config.normalize_line_endings {
+            assert_eq!(subtree.crlf_files(), ["win.txt".to_string()].into());
+        }
+        let grafted = base.graft("docs/sub", &subtree, &config)?;
+        grafted.verify()?;
+
+        // Descendants are named under the graft path
+        assert_eq!(grafted.extract_file("docs/sub/guide/data.bin")?, chunked);
+
+        // Same as building the combined tree from disk
+        let combined = temp_dir.path().join("combined");
+        copy_dir(&site, &combined)?;
+        copy_dir(&manual, &combined.join("docs").join("sub"))?;
+        let rebuilt = create_dag_with_config(&combined, config.clone().with_root_name("site"))?;
+        assert_eq!(grafted.root, rebuilt.root);
+        assert_eq!(grafted.leaves.len(), rebuilt.leaves.len());
+        assert_eq!(grafted.crlf_files(), rebuilt.crlf_files());
+        fs::remove_dir_all(&combined)?;
+    }
+
+    // Leaves built with other settings can't be mixed in
+    let config = DagBuilderConfig::new().with_chunk_size(512);
+    let base = create_dag_with_config(&site, config.clone())?;
+    for other in [
+        config.clone().with_hash_algorithm(HashAlgorithm::Blake3),
+        config.clone().with_cid_base(Base::Base58Btc),
+        config.clone().with_chunk_naming(ChunkNaming::IndexOnly),
+        config.clone().with_normalized_line_endings(),
+    ] {
+        let subtree = create_dag_with_config(&manual, other)?;
+        assert!(matches!(
+            base.graft("docs/sub", &subtree, &config),
+            Err(ScionicError::InvalidDag(_))
+        ));
+    }
+
+    // A single-file subtree's chunks follow the file
+    let base = create_dag_with_config(&site, DagBuilderConfig::new().with_chunk_size(512))?;
+    let file = create_dag_with_config(
+        manual.join("guide").join("data.bin"),
+        DagBuilderConfig::new().with_chunk_size(512),
+    )?;
+    let grafted = base.graft(
+        "docs/data.bin",
+        &file,
+        &DagBuilderConfig::new().with_chunk_size(512),
+    )?;
+    grafted.verify()?;
+    assert_eq!(grafted.extract_file("docs/data.bin")?, chunked);
+    fs::copy(
+        manual.join("guide").join("data.bin"),
+        site.join("docs").join("data.bin"),
+    )?;
+    let rebuilt = create_dag_with_config(&site, DagBuilderConfig::new().with_chunk_size(512))?;
+    assert_eq!(grafted.root, rebuilt.root);
+
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
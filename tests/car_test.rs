@@ -0,0 +1,317 @@
+use multibase::Base;
+use scionic_merkle_tree_rs::{
+    create_dag_with_config, CarDag, Dag, DagBuilderConfig, DagLeaf, Result, ScionicError,
+};
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor};
+use tempfile::TempDir;
+
+#[test]
+fn test_write_car_streaming_roundtrip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("subdir"))?;
+
+    fs::write(dir.join("small.txt"), b"small file")?;
+    let large: Vec<u8> = (0..10_000).map(|i| (i % 256) as u8).collect();
+    fs::write(dir.join("subdir").join("large.bin"), &large)?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_with_config(&dir, config)?;
+
+    let car_path = temp_dir.path().join("archive.car");
+    dag.write_car(BufWriter::new(File::create(&car_path)?))?;
+
+    let loaded = Dag::from_car(&fs::read(&car_path)?)?;
+    assert_eq!(loaded.root, dag.root);
+    assert_eq!(loaded.leaves.len(), dag.leaves.len());
+    loaded.verify()?;
+
+    let output = temp_dir.path().join("output");
+    loaded.create_directory(&output)?;
+    assert_eq!(fs::read(output.join("subdir").join("large.bin"))?, large);
+
+    Ok(())
+}
+
+#[test]
+fn test_car_roundtrip_keeps_cid_base() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("small.txt"), b"small file")?;
+    fs::write(dir.join("large.bin"), vec![7u8; 5000])?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(1024)
+        .with_cid_base(Base::Base58Btc);
+    let dag = create_dag_with_config(&dir, config)?;
+    assert!(dag.root.starts_with('z'));
+
+    // The archive holds binary CIDs; reading it back keeps the base
+    let loaded = Dag::from_car(&dag.to_car()?)?;
+    assert_eq!(loaded.root, dag.root);
+    assert_eq!(loaded.leaves.len(), dag.leaves.len());
+    loaded.verify()?;
+
+    let mut car = CarDag::open(Cursor::new(dag.to_car()?))?;
+    assert_eq!(car.root(), dag.root);
+    for hash in dag.leaves.keys() {
+        assert!(car.contains(hash));
+        assert_eq!(car.get_leaf(hash)?.hash, *hash);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_car_root_block_first() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+    for i in 0..3 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content {}", i))?;
+    }
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new())?;
+    let car = dag.to_car()?;
+
+    // Reading from any Read implementation works the same as from a slice
+    let loaded = Dag::read_car(std::io::Cursor::new(&car))?;
+    assert_eq!(loaded.root, dag.root);
+
+    // The root block precedes every other block
+    let position = |hash: &str| {
+        let cid = cid::Cid::try_from(hash).unwrap().to_bytes();
+        car.windows(cid.len())
+            .rposition(|window| window == cid.as_slice())
+            .unwrap()
+    };
+    let root_position = position(&dag.root);
+    for hash in dag.leaves.keys().filter(|hash| **hash != dag.root) {
+        assert!(root_position < position(hash));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_car_rejects_corrupted_block() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("test.txt");
+    fs::write(&file, b"some content")?;
+
+    let dag = create_dag_with_config(&file, DagBuilderConfig::new())?;
+    let mut car = dag.to_car()?;
+
+    // Truncating the archive mid-block must not produce a DAG
+    car.truncate(car.len() - 5);
+    assert!(Dag::from_car(&car).is_err());
+
+    // A leaf edited in place keeps its stored hash but no longer matches it
+    let dir = temp_dir.path().join("dir");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a.txt"), b"a")?;
+    fs::write(dir.join("b.txt"), b"b")?;
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new())?;
+    for edit in [
+        (|leaf: &mut DagLeaf| leaf.item_name.push('x')) as fn(&mut DagLeaf),
+        |leaf| leaf.content = Some(b"EVIL".to_vec()),
+    ] {
+        let mut tampered = dag.clone();
+        let leaf = tampered
+            .leaves
+            .values_mut()
+            .find(|leaf| leaf.item_name == "a.txt")
+            .unwrap();
+        edit(leaf);
+        assert!(Dag::from_car(&tampered.to_car()?).is_err());
+    }
+    assert_eq!(Dag::from_car(&dag.to_car()?)?.root, dag.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_open_from_car_reads_lazily() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+
+    let large: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("large.bin"), &large)?;
+    fs::write(dir.join("small.txt"), b"small file")?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(1024))?;
+
+    let car_path = temp_dir.path().join("archive.car");
+    dag.write_car(BufWriter::new(File::create(&car_path)?))?;
+
+    let mut car = Dag::open_from_car(&car_path)?;
+    assert_eq!(car.root(), dag.root);
+    assert_eq!(car.len(), dag.leaves.len());
+
+    let root = car.get_leaf(&dag.root)?;
+    let mut files = Vec::new();
+    for link in &root.links {
+        let leaf = car.get_leaf(link)?;
+        files.push((leaf.item_name.clone(), car.read_file(link)?));
+    }
+    files.sort();
+
+    assert_eq!(
+        files,
+        vec![
+            ("large.bin".to_string(), large),
+            ("small.txt".to_string(), b"small file".to_vec()),
+        ]
+    );
+
+    assert!(car.get_leaf("bafireimissing").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_open_from_car_rejects_tampered_block() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("file.txt");
+    fs::write(&file, b"original content")?;
+
+    let dag = create_dag_with_config(&file, DagBuilderConfig::new())?;
+    let mut car = dag.to_car()?;
+
+    // Flip a byte inside the content without changing any lengths
+    let position = car
+        .windows(8)
+        .position(|window| window == b"original")
+        .expect("content in CAR");
+    car[position] = b'O';
+
+    let car_path = temp_dir.path().join("tampered.car");
+    fs::write(&car_path, &car)?;
+
+    let mut lazy = Dag::open_from_car(&car_path)?;
+    assert!(lazy.get_leaf(&dag.root).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_blocks_verify_against_cids() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("subdir"))?;
+    fs::write(dir.join("small.txt"), b"small file")?;
+    fs::write(dir.join("subdir").join("large.bin"), vec![3u8; 5000])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(1024))?;
+    let blocks = dag.to_blocks()?;
+
+    assert_eq!(blocks.len(), dag.leaves.len());
+    assert_eq!(blocks[0].0, dag.root);
+
+    for (cid, data) in &blocks {
+        let leaf = DagLeaf::from_block_bytes(cid, data)?;
+        if *cid == dag.root {
+            leaf.verify_root_leaf()?;
+        } else {
+            leaf.verify_leaf()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_from_blocks_roundtrip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("subdir"))?;
+    fs::write(dir.join("small.txt"), b"small file")?;
+    fs::write(dir.join("subdir").join("large.bin"), vec![3u8; 5000])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(1024))?;
+    let blocks = dag.to_blocks()?;
+
+    // Block order doesn't matter
+    let rebuilt = Dag::from_blocks(&dag.root, blocks.iter().rev().cloned())?;
+    rebuilt.verify()?;
+    assert!(!rebuilt.is_partial());
+    assert_eq!(rebuilt.root, dag.root);
+    assert_eq!(rebuilt.leaves.len(), dag.leaves.len());
+
+    // A block under another leaf's CID is rejected
+    let mut swapped = blocks.clone();
+    swapped[1].1 = blocks[2].1.clone();
+    assert!(matches!(
+        Dag::from_blocks(&dag.root, swapped),
+        Err(ScionicError::HashMismatch { .. })
+    ));
+
+    // So is a block whose stored CID no longer matches its fields
+    let mut tampered = blocks.clone();
+    let mut leaf = DagLeaf::from_block_bytes(&tampered[1].0, &tampered[1].1)?;
+    leaf.item_name.push('x');
+    tampered[1].1 = leaf.to_block_bytes()?;
+    assert!(Dag::from_blocks(&dag.root, tampered).is_err());
+
+    // And one whose content was replaced, which the CID doesn't cover
+    let mut tampered = blocks.clone();
+    let (cid, data) = tampered
+        .iter_mut()
+        .find(|(cid, data)| {
+            DagLeaf::from_block_bytes(cid, data)
+                .unwrap()
+                .content
+                .is_some()
+        })
+        .unwrap();
+    let mut leaf = DagLeaf::from_block_bytes(cid, data)?;
+    leaf.content = Some(b"EVIL!".to_vec());
+    *data = leaf.to_block_bytes()?;
+    assert!(matches!(
+        Dag::from_blocks(&dag.root, tampered),
+        Err(ScionicError::ContentHashMismatch)
+    ));
+
+    // The root block must be among them
+    assert!(Dag::from_blocks(&dag.root, blocks.into_iter().skip(1)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_partial_car_verifies_against_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("subdir"))?;
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content {}", i))?;
+        fs::write(
+            dir.join("subdir").join(format!("nested{}.txt", i)),
+            format!("nested {}", i),
+        )?;
+    }
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new())?;
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "subdir/nested2.txt")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+
+    let car = dag.partial_car(std::slice::from_ref(&file_hash))?;
+    assert!(car.len() < dag.to_car()?.len());
+
+    let loaded = Dag::from_car(&car)?;
+    assert_eq!(loaded.root, dag.root);
+    assert!(loaded.is_partial());
+    assert!(loaded.leaves.contains_key(&file_hash));
+    assert_eq!(loaded.leaves.len(), 3);
+    loaded.verify_strict()?;
+
+    Ok(())
+}
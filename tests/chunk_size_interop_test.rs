@@ -19,12 +19,9 @@ fn find_go_repo() -> Option<PathBuf> {
         std::env::current_dir().ok()?.join("../Scionic-Merkle-Tree"),
     ];
 
-    for path in candidates {
-        if path.exists() && path.join("go.mod").exists() {
-            return Some(path);
-        }
-    }
-    None
+    candidates
+        .into_iter()
+        .find(|path| path.exists() && path.join("go.mod").exists())
 }
 
 fn test_helper_available() -> bool {
@@ -57,7 +54,7 @@ fn create_dag_with_go_chunk_size(
     Command::new("go")
         .env("PATH", set_go_path())
         .current_dir(&go_repo)
-        .args(&[
+        .args([
             "run",
             "cmd/test_helper_chunk.go",
             "create",
@@ -76,7 +73,7 @@ fn create_dag_with_rust_chunk_size(
     chunk_size: usize,
 ) -> Result<String> {
     let output = Command::new("cargo")
-        .args(&[
+        .args([
             "run",
             "--example",
             "create_with_chunk_size",
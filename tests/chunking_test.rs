@@ -0,0 +1,618 @@
+use scionic_merkle_tree_rs::{
+    chunk_byte_range, chunk_count_for, create_dag_with_config, CarDag, ChunkNaming, ChunkParams,
+    Dag, DagBuilderConfig, LeafType, Result, ScionicError,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Cursor;
+use tempfile::TempDir;
+
+fn chunk_hashes(dag: &Dag) -> HashSet<String> {
+    dag.leaves
+        .values()
+        .filter(|leaf| leaf.leaf_type == LeafType::Chunk)
+        .map(|leaf| leaf.hash.clone())
+        .collect()
+}
+
+#[test]
+fn test_index_only_chunk_naming_survives_rename() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    let content: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("before.bin"), &content)?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(1024)
+        .with_chunk_naming(ChunkNaming::IndexOnly);
+
+    let dag1 = create_dag_with_config(&dir, config.clone())?;
+    dag1.verify()?;
+
+    fs::rename(dir.join("before.bin"), dir.join("after.bin"))?;
+    let dag2 = create_dag_with_config(&dir, config)?;
+    dag2.verify()?;
+
+    let chunks1 = chunk_hashes(&dag1);
+    assert_eq!(chunks1.len(), 5);
+    assert_eq!(chunks1, chunk_hashes(&dag2));
+
+    // The naming is recorded on the root without `with_chunk_params`
+    assert_eq!(dag1.chunk_naming(), ChunkNaming::IndexOnly);
+    assert_eq!(dag1.chunk_params(), None);
+
+    // The rename is still visible at the file level
+    assert_ne!(dag1.root, dag2.root);
+
+    // Content reassembles unchanged
+    let output = temp_dir.path().join("output");
+    dag2.create_directory(&output)?;
+    assert_eq!(fs::read(output.join("after.bin"))?, content);
+
+    Ok(())
+}
+
+#[test]
+fn test_path_based_chunk_naming_changes_on_rename() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    let content: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("before.bin"), &content)?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+
+    let dag1 = create_dag_with_config(&dir, config.clone())?;
+    fs::rename(dir.join("before.bin"), dir.join("after.bin"))?;
+    let dag2 = create_dag_with_config(&dir, config)?;
+
+    assert!(chunk_hashes(&dag1).is_disjoint(&chunk_hashes(&dag2)));
+
+    // The default naming isn't recorded, so default roots are unchanged
+    assert_eq!(dag1.chunk_naming(), ChunkNaming::PathBased);
+    let root = &dag1.leaves[&dag1.root];
+    assert!(root.additional_data.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_inline_file_collapses_chunks() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("nested"))?;
+
+    let content: Vec<u8> = (0..200).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("nested").join("small.bin"), &content)?;
+    fs::write(dir.join("other.txt"), "other")?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(16);
+    let dag = create_dag_with_config(&dir, config)?;
+    assert!(!chunk_hashes(&dag).is_empty());
+
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.leaf_type == LeafType::File && !leaf.links.is_empty())
+        .map(|leaf| leaf.hash.clone())
+        .expect("chunked file leaf");
+
+    let inlined = dag.inline_file(&file_hash)?;
+    inlined.verify()?;
+
+    assert_ne!(inlined.root, dag.root);
+    assert!(chunk_hashes(&inlined).is_empty());
+    assert_eq!(inlined.leaves.len(), 4); // root, nested, small.bin, other.txt
+
+    let output = temp_dir.path().join("output");
+    inlined.create_directory(&output)?;
+    assert_eq!(fs::read(output.join("nested").join("small.bin"))?, content);
+    assert_eq!(fs::read_to_string(output.join("other.txt"))?, "other");
+
+    Ok(())
+}
+
+#[test]
+fn test_inline_file_at_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("small.bin");
+    let content: Vec<u8> = (0..100).collect();
+    fs::write(&file, &content)?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(16);
+    let dag = create_dag_with_config(&file, config)?;
+
+    let inlined = dag.inline_file(&dag.root)?;
+    inlined.verify()?;
+
+    assert_eq!(inlined.leaves.len(), 1);
+    let root_leaf = &inlined.leaves[&inlined.root];
+    assert_eq!(root_leaf.content.as_deref(), Some(content.as_slice()));
+
+    // Same as building the file without chunking
+    let unchunked = create_dag_with_config(&file, DagBuilderConfig::new())?;
+    assert_eq!(inlined.root, unchunked.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_chunk_dedup_report() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("image.bin");
+
+    // Ten 16-byte blocks: zero regions repeated around two distinct blocks
+    let mut content = Vec::new();
+    for i in 0..10 {
+        let byte = match i {
+            3 => 1,
+            7 => 2,
+            _ => 0,
+        };
+        content.extend(std::iter::repeat_n(byte, 16));
+    }
+    fs::write(&file, &content)?;
+
+    let dag = create_dag_with_config(&file, DagBuilderConfig::new().with_chunk_size(16))?;
+    let report = dag.chunk_dedup_report(&dag.root)?;
+
+    assert_eq!(report.total_chunks, 10);
+    assert_eq!(report.unique_chunks, 3);
+    assert!(report.unique_chunks < report.total_chunks);
+    assert_eq!(report.bytes_saved, 7 * 16);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_reassembly_catches_dropped_chunk() -> Result<()> {
+    use scionic_merkle_tree_rs::ScionicError;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("large.bin"), vec![5u8; 100])?;
+    fs::write(dir.join("small.txt"), "small")?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(16)
+        .with_file_sizes();
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+    dag.verify_reassembly()?;
+
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.leaf_type == LeafType::File && !leaf.links.is_empty())
+        .map(|leaf| leaf.hash.clone())
+        .expect("chunked file leaf");
+    assert_eq!(dag.leaves[&file_hash].file_size(), Some(100));
+
+    let mut tampered = dag.clone();
+    tampered.leaves.get_mut(&file_hash).unwrap().links.pop();
+    match tampered.verify_reassembly() {
+        Err(ScionicError::SizeMismatch { expected, got }) => {
+            assert_eq!((expected, got), (100, 96));
+        }
+        result => panic!("expected SizeMismatch, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_coalesce_small_tail() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("data.bin");
+    let content: Vec<u8> = (0..3 * 100 + 1).map(|i| i as u8).collect();
+    fs::write(&file, &content)?;
+
+    let chunk_lengths = |dag: &Dag| -> Vec<usize> {
+        dag.leaves[&dag.root]
+            .links
+            .iter()
+            .map(|link| dag.leaves[link].content.as_ref().unwrap().len())
+            .collect()
+    };
+
+    // Without the policy the single trailing byte gets its own chunk
+    let plain = create_dag_with_config(&file, DagBuilderConfig::new().with_chunk_size(100))?;
+    let mut lengths = chunk_lengths(&plain);
+    lengths.sort();
+    assert_eq!(lengths, vec![1, 100, 100, 100]);
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(100)
+        .with_coalesce_small_tail(0.1);
+    let coalesced = create_dag_with_config(&file, config.clone())?;
+    coalesced.verify()?;
+    let mut lengths = chunk_lengths(&coalesced);
+    lengths.sort();
+    assert_eq!(lengths, vec![100, 100, 101]);
+    assert_ne!(coalesced.root, plain.root);
+
+    // Deterministic, and the content reassembles unchanged
+    assert_eq!(create_dag_with_config(&file, config)?.root, coalesced.root);
+    assert_eq!(coalesced.read_range(0, content.len() as u64)?, content);
+
+    Ok(())
+}
+
+#[test]
+fn test_no_empty_chunks() -> Result<()> {
+    use scionic_merkle_tree_rs::{create_dag_from_entries, DagLeafBuilder, ScionicError};
+    use std::io::{Cursor, Read};
+
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("data.bin");
+
+    for len in 0..=100 {
+        let content: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        fs::write(&file, &content)?;
+
+        for tail in [0.0, 0.5, 1.0] {
+            let config = DagBuilderConfig::new()
+                .with_chunk_size(16)
+                .with_coalesce_small_tail(tail);
+            let from_disk = create_dag_with_config(&file, config.clone())?;
+
+            let reader: Box<dyn Read> = Box::new(Cursor::new(content.clone()));
+            let entries = std::iter::once(("data.bin".to_string(), reader));
+            let from_reader = create_dag_from_entries(entries, config)?;
+
+            for dag in [&from_disk, &from_reader] {
+                assert!(dag
+                    .leaves
+                    .values()
+                    .filter(|leaf| leaf.leaf_type == LeafType::Chunk)
+                    .all(|leaf| !leaf.content.as_ref().unwrap().is_empty()));
+                dag.verify()?;
+            }
+        }
+    }
+
+    let empty_chunk = DagLeafBuilder::new("data.bin/0")
+        .set_type(LeafType::Chunk)
+        .set_data(Vec::new())
+        .build_leaf(None)?;
+    match empty_chunk.verify_leaf() {
+        Err(ScionicError::InvalidLeaf(message)) => assert!(message.starts_with("empty chunk")),
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_max_links_per_leaf() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("many"))?;
+
+    // 5,000 chunks of 16 bytes
+    let content: Vec<u8> = (0..80_000u32).map(|i| (i % 253) as u8).collect();
+    fs::write(dir.join("large.bin"), &content)?;
+    for i in 0..150 {
+        fs::write(
+            dir.join("many").join(format!("file{:03}.txt", i)),
+            i.to_string(),
+        )?;
+    }
+
+    let max_links = 64;
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(16)
+        .with_entry_counts()
+        .with_max_links_per_leaf(max_links);
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+
+    assert!(dag
+        .leaves
+        .values()
+        .all(|leaf| leaf.links.len() <= max_links));
+    let counts = dag.count_by_type()?;
+    assert_eq!(counts.chunks, 5000);
+    assert_eq!(counts.files, 151);
+    assert!(counts.link_groups > 0);
+
+    let output = temp_dir.path().join("output");
+    dag.create_directory(&output)?;
+    assert_eq!(fs::read(output.join("large.bin"))?, content);
+    assert_eq!(fs::read_dir(output.join("many"))?.count(), 150);
+    assert_eq!(
+        fs::read_to_string(output.join("many").join("file149.txt"))?,
+        "149"
+    );
+
+    // A file root is split the same way
+    let file_dag = create_dag_with_config(
+        dir.join("large.bin"),
+        DagBuilderConfig::new()
+            .with_chunk_size(16)
+            .with_max_links_per_leaf(max_links),
+    )?;
+    file_dag.verify()?;
+    assert!(file_dag
+        .leaves
+        .values()
+        .all(|leaf| leaf.links.len() <= max_links));
+    assert_eq!(file_dag.get_total_size()?, content.len() as u64);
+    assert_eq!(file_dag.read_range(1000, 1999)?, &content[1000..2000]);
+
+    // Without a limit the file links every chunk directly
+    let unlimited = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+    assert!(unlimited
+        .leaves
+        .values()
+        .any(|leaf| leaf.links.len() == 5000));
+
+    Ok(())
+}
+
+#[test]
+fn test_link_groups_across_operations() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..10 {
+        fs::write(dir.join(format!("file{}.txt", i)), i.to_string())?;
+        fs::create_dir(dir.join(format!("dir{}", i)))?;
+        fs::write(dir.join(format!("dir{}", i)).join("x.txt"), "x")?;
+    }
+    // 16 identical chunks, split under groups
+    fs::write(dir.join("sub").join("zeros.bin"), vec![0u8; 16 * 64])?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(64)
+        .with_entry_counts()
+        .with_max_links_per_leaf(4);
+    let mut dag = create_dag_with_config(&dir, config.clone())?;
+    dag.verify()?;
+
+    // Entry counts cover the entries, not the groups
+    let root = dag.leaves.get(&dag.root).unwrap();
+    assert_eq!(root.entry_count(), Some(21));
+    assert!(root.links.len() <= 4);
+
+    // Groups aren't counted as chunks
+    let zeros = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "sub/zeros.bin" && !leaf.is_link_group())
+        .unwrap();
+    let report = dag.chunk_dedup_report(&zeros.hash)?;
+    assert_eq!(report.total_chunks, 16);
+    assert_eq!(report.unique_chunks, 1);
+    assert_eq!(report.bytes_saved, 15 * 64);
+
+    // A CAR read looks through the groups
+    let mut car = CarDag::open(Cursor::new(dag.to_car()?))?;
+    assert_eq!(car.read_file(&zeros.hash)?, vec![0u8; 16 * 64]);
+
+    // The skeleton drops the files and verifies
+    let skeleton = dag.skeleton()?;
+    skeleton.verify()?;
+    assert_eq!(
+        skeleton.leaves.get(&skeleton.root).unwrap().entry_count(),
+        Some(11)
+    );
+
+    // Edits below groups match a full rebuild
+    fs::write(dir.join("file3.txt"), "changed")?;
+    fs::write(dir.join("dir7").join("x.txt"), "changed")?;
+    let updated = dag.update_paths(
+        &[&dir.join("file3.txt"), &dir.join("dir7").join("x.txt")],
+        &dir,
+        config.clone(),
+    )?;
+    updated.verify()?;
+    assert_eq!(
+        updated.root,
+        create_dag_with_config(&dir, config.clone())?.root
+    );
+    dag = updated;
+
+    dag.add_file("", "extra.txt", b"extra".to_vec(), &config)?;
+    fs::write(dir.join("extra.txt"), "extra")?;
+    dag.verify()?;
+    assert_eq!(dag.root, create_dag_with_config(&dir, config.clone())?.root);
+
+    dag.remove_file("file5.txt", &config)?;
+    fs::remove_file(dir.join("file5.txt"))?;
+    dag.verify()?;
+    assert_eq!(dag.root, create_dag_with_config(&dir, config.clone())?.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_read_file_range_fetches_only_overlapping_chunks() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+    let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("video.bin"), &content)?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(1024)
+        .with_file_sizes();
+    let dag = create_dag_with_config(&dir, config)?;
+    let file = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "video.bin")
+        .unwrap();
+    let chunks = file.links.clone();
+    assert_eq!(chunks.len(), 10);
+
+    assert_eq!(
+        dag.read_file_range(&file.hash, 2000, 3500)?,
+        &content[2000..3500]
+    );
+    assert_eq!(
+        dag.read_file_range(&file.hash, 9000, 20_000)?,
+        &content[9000..]
+    );
+    assert!(dag.read_file_range(&file.hash, 5000, 5000)?.is_empty());
+    assert!(dag.read_file_range(&file.hash, 20, 10).is_err());
+
+    // [2000, 3500) spans the boundaries at 2048 and 3072: chunks 1 to 3
+    let partial = dag.get_partial(&chunks[1..=3], false)?;
+    assert_eq!(
+        partial.read_file_range(&file.hash, 2000, 3500)?,
+        &content[2000..3500]
+    );
+    assert!(matches!(
+        partial.read_file_range(&file.hash, 0, 100),
+        Err(ScionicError::MissingLeaf(hash)) if hash == chunks[0]
+    ));
+
+    // With only the last chunk, its offset comes from the file size
+    let tail = dag.get_partial(&chunks[9..], false)?;
+    assert_eq!(
+        tail.read_file_range(&file.hash, 9500, 10_000)?,
+        &content[9500..]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_record_chunk_params() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("data.bin");
+    let content: Vec<u8> = (0..5_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&file, &content)?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(1000)
+        .with_chunk_naming(ChunkNaming::IndexOnly)
+        .with_coalesce_small_tail(0.25);
+    let plain = create_dag_with_config(&file, config.clone())?;
+    assert_eq!(plain.chunk_params(), None);
+
+    let recorded = create_dag_with_config(&file, config.with_chunk_params())?;
+    recorded.verify()?;
+    assert_ne!(recorded.root, plain.root);
+    assert_eq!(
+        recorded.chunk_params(),
+        Some(ChunkParams {
+            chunk_size: 1000,
+            chunk_naming: ChunkNaming::IndexOnly,
+            coalesce_small_tail: 0.25,
+        })
+    );
+
+    // The default chunk size is recorded as the effective size
+    let defaults = create_dag_with_config(&file, DagBuilderConfig::new().with_chunk_params())?;
+    let params = defaults.chunk_params().unwrap();
+    assert_eq!(params, ChunkParams::from_config(&DagBuilderConfig::new()));
+    assert_eq!(
+        params.chunk_size,
+        scionic_merkle_tree_rs::DEFAULT_CHUNK_SIZE
+    );
+
+    // Rebuilding from the recorded parameters reproduces the DAG
+    let params = recorded.chunk_params().unwrap();
+    let rebuilt = create_dag_with_config(
+        &file,
+        DagBuilderConfig::new()
+            .with_chunk_size(params.chunk_size)
+            .with_chunk_naming(params.chunk_naming)
+            .with_coalesce_small_tail(params.coalesce_small_tail)
+            .with_chunk_params(),
+    )?;
+    assert_eq!(rebuilt.root, recorded.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_chunk_count_and_ranges_match_builder() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let chunk_size = 100;
+
+    for size in [0u64, 1, 99, 100, 101, 199, 200, 201, 999, 1000, 1001] {
+        let content: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        let file = temp_dir.path().join(format!("file{}.bin", size));
+        fs::write(&file, &content)?;
+
+        let dag =
+            create_dag_with_config(&file, DagBuilderConfig::new().with_chunk_size(chunk_size))?;
+        let root = &dag.leaves[&dag.root];
+        let count = chunk_count_for(size, chunk_size);
+        assert_eq!(root.links.len(), count, "size {}", size);
+
+        for (index, link) in root.links.iter().enumerate() {
+            let (start, end) = chunk_byte_range(index, size, chunk_size);
+            assert_eq!(
+                dag.leaves[link].content.as_deref(),
+                Some(&content[start as usize..end as usize]),
+                "size {} chunk {}",
+                size,
+                index
+            );
+        }
+        if count > 0 {
+            assert_eq!(chunk_byte_range(count - 1, size, chunk_size).1, size);
+        }
+    }
+
+    assert_eq!(chunk_count_for(1_000, 0), 0);
+    assert_eq!(chunk_byte_range(5, 250, 100), (250, 250));
+
+    Ok(())
+}
+
+#[test]
+fn test_identical_files_share_chunks() -> Result<()> {
+    const SIZE: usize = 3 * 1024 * 1024;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    let content: Vec<u8> = (0..SIZE).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("first.bin"), &content)?;
+    fs::write(dir.join("second.bin"), &content)?;
+
+    // Index-only names make the two files' chunks the same leaves
+    let config = DagBuilderConfig::new().with_chunk_naming(ChunkNaming::IndexOnly);
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+    assert_eq!(chunk_hashes(&dag).len(), 2);
+    assert_eq!(dag.leaves.len(), 5);
+
+    let files: Vec<_> = dag
+        .leaves
+        .values()
+        .filter(|leaf| leaf.leaf_type == LeafType::File)
+        .collect();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].links, files[1].links);
+    assert_eq!(dag.leaves[&dag.root].links.len(), 2);
+
+    // Shared chunks count once in the root's content size
+    let root = &dag.leaves[&dag.root];
+    assert_eq!(root.content_size, Some(SIZE as i64));
+    assert_eq!(dag.unique_content_size(), SIZE as u64);
+    assert_eq!(dag.subtree_size(&dag.root)?, 2 * SIZE as u64);
+
+    let output = temp_dir.path().join("output");
+    dag.create_directory(&output)?;
+    assert_eq!(fs::read(output.join("first.bin"))?, content);
+    assert_eq!(fs::read(output.join("second.bin"))?, content);
+
+    // Path-based names keep separate chunk leaves, but the same content
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new())?;
+    assert_eq!(chunk_hashes(&dag).len(), 4);
+    assert_eq!(dag.leaves[&dag.root].content_size, Some(2 * SIZE as i64));
+    assert_eq!(dag.unique_content_size(), SIZE as u64);
+
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use scionic_merkle_tree_rs::{create_dag, LeafType, Result};
+use scionic_merkle_tree_rs::{
+    create_dag, create_dag_with_config, Dag, DagBuilderConfig, LeafType, Result, ScionicError,
+};
 use std::fs;
 use tempfile::TempDir;
 
@@ -202,6 +204,16 @@ fn test_directory_with_only_subdirectories() -> Result<()> {
 
     assert_eq!(file_count, 0);
 
+    // Recreating keeps the empty subdirectories
+    let output = temp_dir.path().join("output");
+    dag.create_directory(&output)?;
+    for name in ["sub1", "sub2", "sub3"] {
+        let sub = output.join(name);
+        assert!(sub.is_dir(), "{} missing", name);
+        assert_eq!(fs::read_dir(&sub)?.count(), 0);
+    }
+    assert_eq!(fs::read_dir(&output)?.count(), 3);
+
     Ok(())
 }
 
@@ -222,3 +234,717 @@ fn test_very_small_file() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_directory_entry_counts() -> Result<()> {
+    use scionic_merkle_tree_rs::{create_dag_with_config, Dag, DagBuilderConfig};
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    let subdir = dir.join("subdir");
+    fs::create_dir(&subdir)?;
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(subdir.join("nested.txt"), "nested")?;
+    fs::create_dir(dir.join("empty"))?;
+
+    let config = DagBuilderConfig::new().with_entry_counts();
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+
+    for leaf in dag.leaves.values() {
+        if leaf.leaf_type == LeafType::Directory {
+            assert_eq!(leaf.entry_count(), Some(leaf.links.len()));
+        } else {
+            assert_eq!(leaf.entry_count(), None);
+        }
+    }
+    assert_eq!(dag.leaves[&dag.root].entry_count(), Some(6));
+
+    // Counts change the hash, so the option is opt-in
+    let plain = create_dag(&dir, false)?;
+    assert_ne!(plain.root, dag.root);
+    assert_eq!(plain.leaves[&plain.root].entry_count(), None);
+
+    // Counts survive serialization and still verify
+    let loaded = Dag::from_cbor(&dag.to_cbor()?)?;
+    loaded.verify()?;
+    assert_eq!(loaded.leaves[&loaded.root].entry_count(), Some(6));
+
+    // A tampered count is rejected
+    let mut tampered = dag.clone();
+    let root = tampered.leaves.get_mut(&tampered.root).unwrap();
+    root.additional_data
+        .as_mut()
+        .unwrap()
+        .insert("entry_count".to_string(), "7".to_string());
+    assert!(tampered.verify().is_err());
+
+    // User data can't override the computed count
+    let config = DagBuilderConfig::new()
+        .with_entry_counts()
+        .with_additional_data([("entry_count".to_string(), "7".to_string())]);
+    let overridden = create_dag_with_config(&dir, config)?;
+    overridden.verify()?;
+    assert_eq!(overridden.root, dag.root);
+
+    // Nor a file root's computed size
+    let file = dir.join("file0.txt");
+    let sized = create_dag_with_config(&file, DagBuilderConfig::new().with_file_sizes())?;
+    let config = DagBuilderConfig::new()
+        .with_file_sizes()
+        .with_additional_data([("file_size".to_string(), "1000".to_string())]);
+    let overridden = create_dag_with_config(&file, config)?;
+    overridden.verify()?;
+    assert_eq!(overridden.root, sized.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_root_name_override() -> Result<()> {
+    use scionic_merkle_tree_rs::{create_dag_with_config, DagBuilderConfig};
+
+    let temp_dir = TempDir::new()?;
+    let first = temp_dir.path().join("build-output");
+    let second = temp_dir.path().join("dist");
+
+    for dir in [&first, &second] {
+        fs::create_dir(dir)?;
+        fs::write(dir.join("app.js"), "console.log('hi')")?;
+    }
+
+    // Different directory names give different roots by default
+    assert_ne!(
+        create_dag(&first, false)?.root,
+        create_dag(&second, false)?.root
+    );
+
+    let config = DagBuilderConfig::new().with_root_name("my-app");
+    let dag1 = create_dag_with_config(&first, config.clone())?;
+    let dag2 = create_dag_with_config(&second, config)?;
+    dag1.verify()?;
+
+    assert_eq!(dag1.root, dag2.root);
+    assert_eq!(dag1.leaves[&dag1.root].item_name, "my-app");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_file_name_is_rejected() -> Result<()> {
+    use scionic_merkle_tree_rs::ScionicError;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("valid.txt"), "valid")?;
+
+    // Two names that would both become "bad\u{FFFD}.txt" when converted lossily
+    let first = dir.join(OsStr::from_bytes(b"bad\xff.txt"));
+    let second = dir.join(OsStr::from_bytes(b"bad\xfe.txt"));
+    if fs::write(&first, "first").is_err() || fs::write(&second, "second").is_err() {
+        eprintln!("Skipping: filesystem rejects non-UTF-8 names");
+        return Ok(());
+    }
+
+    match create_dag(&dir, false) {
+        // The first offending name by byte order, with its raw bytes
+        Err(ScionicError::InvalidName(name)) => assert!(name.contains(r#"b"bad\xfe.txt""#)),
+        result => panic!("expected InvalidName, got {:?}", result.map(|dag| dag.root)),
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_lossy_names_when_utf8_not_required() -> Result<()> {
+    use scionic_merkle_tree_rs::ScionicError;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join(OsStr::from_bytes(b"caf\xe9.txt"));
+    if fs::write(&file, "content").is_err() {
+        eprintln!("Skipping: filesystem rejects non-UTF-8 names");
+        return Ok(());
+    }
+
+    // A non-UTF-8 root name is rejected too
+    match create_dag_with_config(&file, DagBuilderConfig::new()) {
+        Err(ScionicError::InvalidName(name)) => assert!(name.contains(r#"b"caf\xe9.txt""#)),
+        result => panic!("expected InvalidName, got {:?}", result.map(|dag| dag.root)),
+    }
+
+    let config = DagBuilderConfig::new().with_require_utf8_names(false);
+    let dag = create_dag_with_config(temp_dir.path(), config)?;
+    dag.verify()?;
+    assert!(dag
+        .leaves
+        .values()
+        .any(|leaf| leaf.item_name == "caf\u{FFFD}.txt"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_modes_round_trip() -> Result<()> {
+    use scionic_merkle_tree_rs::SymlinkMode;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("real.txt"), "real content")?;
+    std::os::unix::fs::symlink("real.txt", dir.join("link.txt"))?;
+
+    let build = |mode: SymlinkMode, name: &str| -> Result<(Dag, std::path::PathBuf)> {
+        let config = DagBuilderConfig::new().with_symlink_mode(mode);
+        let dag = create_dag_with_config(&dir, config)?;
+        dag.verify()?;
+        let output = temp_dir.path().join(name);
+        dag.create_directory(&output)?;
+        assert_eq!(fs::read(output.join("real.txt"))?, b"real content");
+        Ok((dag, output))
+    };
+
+    // Following stores the link as a copy of the file
+    let (_, output) = build(SymlinkMode::Follow, "follow")?;
+    let link = output.join("link.txt");
+    assert!(!fs::symlink_metadata(&link)?.file_type().is_symlink());
+    assert_eq!(fs::read(&link)?, b"real content");
+
+    // Skipping leaves it out
+    let (dag, output) = build(SymlinkMode::Skip, "skip")?;
+    assert!(!output.join("link.txt").exists());
+    assert!(dag.leaves.values().all(|leaf| leaf.item_name != "link.txt"));
+
+    // Storing it as a link recreates the link itself
+    let (dag, output) = build(SymlinkMode::StoreAsLink, "store")?;
+    let leaf = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "link.txt")
+        .unwrap();
+    assert_eq!(leaf.leaf_type, LeafType::Symlink);
+    assert_eq!(leaf.symlink_target()?, "real.txt");
+    let link = output.join("link.txt");
+    assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+    assert_eq!(fs::read_link(&link)?, std::path::Path::new("real.txt"));
+    assert_eq!(fs::read(&link)?, b"real content");
+
+    Ok(())
+}
+
+#[test]
+fn test_normalized_line_endings() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let crlf = temp_dir.path().join("crlf");
+    let lf = temp_dir.path().join("lf");
+    fs::create_dir(&crlf)?;
+    fs::create_dir(&lf)?;
+    fs::write(crlf.join("notes.txt"), "one\r\ntwo\r\nthree\r")?;
+    fs::write(lf.join("notes.txt"), "one\ntwo\nthree\r")?;
+    // Binary content is left as is, and so is text mixing CRLF and bare LF
+    fs::write(crlf.join("data.bin"), b"\0\r\n")?;
+    fs::write(lf.join("data.bin"), b"\0\r\n")?;
+    fs::write(crlf.join("mixed.txt"), "one\r\ntwo\nthree\r\n")?;
+    fs::write(lf.join("mixed.txt"), "one\r\ntwo\nthree\r\n")?;
+
+    let file_hash = |dag: &Dag| {
+        dag.leaves
+            .values()
+            .find(|leaf| leaf.item_name == "notes.txt")
+            .map(|leaf| leaf.hash.clone())
+            .unwrap()
+    };
+
+    let plain_crlf = create_dag_with_config(&crlf, DagBuilderConfig::new())?;
+    let plain_lf = create_dag_with_config(&lf, DagBuilderConfig::new())?;
+    assert_ne!(file_hash(&plain_crlf), file_hash(&plain_lf));
+    assert!(!plain_crlf.line_endings_normalized());
+
+    let config = DagBuilderConfig::new()
+        .with_root_name("notes")
+        .with_chunk_size(4)
+        .with_normalized_line_endings();
+    let normalized_crlf = create_dag_with_config(&crlf, config.clone())?;
+    let normalized_lf = create_dag_with_config(&lf, config.clone())?;
+    normalized_crlf.verify()?;
+    normalized_crlf.verify_reserved_keys()?;
+    assert_eq!(file_hash(&normalized_crlf), file_hash(&normalized_lf));
+    assert!(normalized_crlf.line_endings_normalized());
+
+    // Only the root records which files had CRLF
+    assert_ne!(normalized_crlf.root, normalized_lf.root);
+    assert_eq!(
+        normalized_crlf.crlf_files().into_iter().collect::<Vec<_>>(),
+        ["notes.txt"]
+    );
+    assert!(normalized_lf.crlf_files().is_empty());
+
+    let output = temp_dir.path().join("output");
+    normalized_crlf.create_directory(&output)?;
+    assert_eq!(fs::read(output.join("notes.txt"))?, b"one\ntwo\nthree\r");
+    assert_eq!(fs::read(output.join("data.bin"))?, b"\0\r\n");
+
+    let restored = temp_dir.path().join("restored");
+    normalized_crlf.create_directory_restoring_line_endings(&restored)?;
+    assert_eq!(
+        fs::read(restored.join("notes.txt"))?,
+        fs::read(crlf.join("notes.txt"))?
+    );
+    assert_eq!(fs::read(restored.join("data.bin"))?, b"\0\r\n");
+    assert_eq!(
+        fs::read(restored.join("mixed.txt"))?,
+        fs::read(crlf.join("mixed.txt"))?
+    );
+    assert_eq!(
+        fs::read(output.join("mixed.txt"))?,
+        fs::read(crlf.join("mixed.txt"))?
+    );
+
+    // Edits keep the list in step with a full rebuild
+    let mut edited = normalized_lf.clone();
+    edited.add_file("", "more.txt", b"a\r\nb".to_vec(), &config)?;
+    fs::write(lf.join("more.txt"), "a\r\nb")?;
+    edited.verify()?;
+    assert_eq!(
+        edited.root,
+        create_dag_with_config(&lf, config.clone())?.root
+    );
+    assert!(edited.crlf_files().contains("more.txt"));
+    edited.remove_file("more.txt", &config)?;
+    assert_eq!(edited.root, normalized_lf.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_fetch_order_by_priority() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("site");
+    fs::create_dir_all(dir.join("assets"))?;
+    fs::create_dir_all(dir.join("pages").join("nested"))?;
+    fs::write(dir.join("a.png"), vec![1u8; 3000])?;
+    fs::write(dir.join("index.html"), "<html>index</html>")?;
+    fs::write(dir.join("assets").join("logo.jpg"), vec![2u8; 3000])?;
+    fs::write(dir.join("pages").join("b.png"), vec![3u8; 10])?;
+    fs::write(
+        dir.join("pages").join("nested").join("page.html"),
+        "<html>page</html>",
+    )?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_with_config(&dir, config)?;
+    let order = dag.fetch_order(|leaf| {
+        if leaf.item_name.ends_with(".html") {
+            10
+        } else {
+            0
+        }
+    });
+
+    // Every leaf exactly once, root first
+    assert_eq!(order.len(), dag.leaves.len());
+    assert_eq!(order[0], dag.root);
+    let position: std::collections::HashMap<&str, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(index, hash)| (hash.as_str(), index))
+        .collect();
+
+    // Parents before children
+    for leaf in dag.leaves.values() {
+        for link in &leaf.links {
+            assert!(position[leaf.hash.as_str()] < position[link.as_str()]);
+        }
+    }
+
+    // Pages before images
+    let positions_of = |suffixes: &[&str]| -> Vec<usize> {
+        dag.leaves
+            .values()
+            .filter(|leaf| {
+                suffixes
+                    .iter()
+                    .any(|suffix| leaf.item_name.ends_with(suffix))
+            })
+            .map(|leaf| position[leaf.hash.as_str()])
+            .collect()
+    };
+    let pages = positions_of(&[".html"]);
+    let images = positions_of(&[".png", ".jpg"]);
+    assert_eq!(pages.len(), 2);
+    assert_eq!(images.len(), 3);
+    assert!(pages.iter().max() < images.iter().min());
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_file() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("archive");
+    fs::create_dir_all(dir.join("docs").join("deep"))?;
+    let chunked: Vec<u8> = (0..5_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("top.txt"), "top level")?;
+    fs::write(dir.join("docs").join("deep").join("nested.txt"), "nested")?;
+    fs::write(dir.join("docs").join("chunked.bin"), &chunked)?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(512)
+        .with_max_links_per_leaf(3);
+    let dag = create_dag_with_config(&dir, config)?;
+
+    assert_eq!(dag.extract_file("top.txt")?, b"top level");
+    assert_eq!(dag.extract_file("docs/deep/nested.txt")?, b"nested");
+    assert_eq!(dag.extract_file("docs/chunked.bin")?, chunked);
+
+    assert!(matches!(
+        dag.extract_file("docs/missing.txt"),
+        Err(ScionicError::MissingLeaf(_))
+    ));
+    assert!(matches!(
+        dag.extract_file("top.txt/more"),
+        Err(ScionicError::MissingLeaf(_))
+    ));
+    assert!(matches!(
+        dag.extract_file("docs/deep"),
+        Err(ScionicError::InvalidType(_))
+    ));
+
+    // A file root is found by its own name
+    let file_dag = create_dag(dir.join("top.txt"), false)?;
+    assert_eq!(file_dag.extract_file("top.txt")?, b"top level");
+    assert!(file_dag.extract_file("other.txt").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_create_directory_best_effort() -> Result<()> {
+    use scionic_merkle_tree_rs::CreateDirectoryMode;
+
+    let temp_dir = TempDir::new()?;
+    let input = temp_dir.path().join("input");
+    fs::create_dir(&input)?;
+    fs::create_dir(input.join("subdir"))?;
+    fs::write(input.join("a.txt"), "a")?;
+    fs::write(input.join("b.txt"), "b")?;
+    fs::write(input.join("subdir").join("c.txt"), "c")?;
+
+    let dag = create_dag(&input, false)?;
+
+    // A directory where b.txt should go makes writing that one file fail
+    let strict_output = temp_dir.path().join("strict");
+    fs::create_dir_all(strict_output.join("b.txt"))?;
+    assert!(dag.create_directory(&strict_output).is_err());
+
+    let output = temp_dir.path().join("output");
+    fs::create_dir_all(output.join("b.txt"))?;
+
+    let failures = dag.create_directory_with_mode(&output, CreateDirectoryMode::BestEffort)?;
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, output.join("b.txt"));
+
+    assert_eq!(fs::read_to_string(output.join("a.txt"))?, "a");
+    assert_eq!(
+        fs::read_to_string(output.join("subdir").join("c.txt"))?,
+        "c"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_count_by_type() -> Result<()> {
+    use scionic_merkle_tree_rs::{create_dag_with_config, DagBuilderConfig, TypeCounts};
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("subdir"))?;
+    fs::write(dir.join("a.txt"), "a")?;
+    fs::write(dir.join("subdir").join("b.txt"), "b")?;
+    fs::write(dir.join("subdir").join("large.bin"), vec![1u8; 100])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+    let counts = dag.count_by_type()?;
+
+    assert_eq!(
+        counts,
+        TypeCounts {
+            files: 3,
+            directories: 2,
+            chunks: 7,
+            symlinks: 0,
+            link_groups: 0,
+        }
+    );
+    assert_eq!(counts.total(), dag.leaves.len());
+
+    // A stray leaf that nothing links to is a second root
+    let mut stray = dag.clone();
+    let other = create_dag(dir.join("a.txt"), false)?;
+    stray.leaves.extend(other.leaves);
+    assert!(stray.count_by_type().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_identical_subtrees() -> Result<()> {
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    for copy in ["copy1", "nested/copy2"] {
+        let sub = dir.join(copy);
+        fs::create_dir_all(sub.join("inner"))?;
+        fs::write(sub.join("a.txt"), "same a")?;
+        fs::write(sub.join("inner").join("b.txt"), "same b")?;
+    }
+    fs::create_dir_all(dir.join("different"))?;
+    fs::write(dir.join("different").join("a.txt"), "other a")?;
+
+    let dag = create_dag(&dir, false)?;
+    let groups = dag.identical_subtrees();
+
+    let paths: Vec<&Vec<PathBuf>> = groups.iter().map(|(_, paths)| paths).collect();
+    assert!(paths.contains(&&vec![
+        PathBuf::from("copy1"),
+        PathBuf::from("nested/copy2")
+    ]));
+    assert!(paths.contains(&&vec![
+        PathBuf::from("copy1/inner"),
+        PathBuf::from("nested/copy2/inner")
+    ]));
+    assert_eq!(groups.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_subtree_size() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("sub").join("deeper"))?;
+    fs::write(dir.join("top.txt"), "top level")?;
+    fs::write(dir.join("sub").join("a.txt"), "alpha")?;
+    fs::write(
+        dir.join("sub").join("deeper").join("big.bin"),
+        vec![1u8; 3000],
+    )?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_with_config(&dir, config)?;
+
+    let sub = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "sub")
+        .unwrap();
+    assert_eq!(dag.subtree_size(&sub.hash)?, 5 + 3000);
+    assert_eq!(dag.subtree_size(&dag.root)?, 9 + 5 + 3000);
+
+    let sizes = dag.subtree_sizes()?;
+    assert_eq!(sizes.len(), dag.leaves.len());
+    assert_eq!(sizes[&sub.hash], 5 + 3000);
+
+    assert!(dag.subtree_size("bafireimissing").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_top_level_entries() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("src").join("nested"))?;
+    fs::create_dir(dir.join("empty"))?;
+    fs::write(dir.join("readme.md"), "readme")?;
+    fs::write(dir.join("big.bin"), vec![1u8; 300])?;
+    fs::write(dir.join("src").join("main.rs"), "fn main() {}")?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(64))?;
+    let entries = dag.top_level()?;
+
+    let names: Vec<(&str, LeafType)> = entries
+        .iter()
+        .map(|(name, leaf_type, _)| (name.as_str(), leaf_type.clone()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("big.bin", LeafType::File),
+            ("empty", LeafType::Directory),
+            ("readme.md", LeafType::File),
+            ("src", LeafType::Directory),
+        ]
+    );
+
+    let mut hashes: Vec<&String> = entries.iter().map(|(_, _, hash)| hash).collect();
+    let mut links: Vec<&String> = dag.leaves[&dag.root].links.iter().collect();
+    hashes.sort();
+    links.sort();
+    assert_eq!(hashes, links);
+
+    // A single file has no top-level entries
+    let file_dag = create_dag(dir.join("readme.md"), false)?;
+    assert!(file_dag.top_level()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_common_ancestor() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("src").join("a"))?;
+    fs::create_dir_all(dir.join("src").join("b").join("deep"))?;
+    fs::create_dir(dir.join("docs"))?;
+    fs::write(dir.join("src").join("a").join("one.rs"), "one")?;
+    fs::write(dir.join("src").join("b").join("deep").join("two.rs"), "two")?;
+    fs::write(dir.join("docs").join("readme.md"), "readme")?;
+
+    let dag = create_dag(&dir, false)?;
+    let find = |name: &str| {
+        dag.leaves
+            .values()
+            .find(|leaf| leaf.item_name == name)
+            .map(|leaf| leaf.hash.clone())
+            .unwrap()
+    };
+
+    let one = find("src/a/one.rs");
+    let two = find("src/b/deep/two.rs");
+    let src = find("src");
+    assert_eq!(dag.common_ancestor(&one, &two)?, src);
+    assert_eq!(dag.common_ancestor(&two, &one)?, src);
+
+    // Different top-level directories only share the root
+    assert_eq!(
+        dag.common_ancestor(&one, &find("docs/readme.md"))?,
+        dag.root
+    );
+
+    // A directory contains itself, and identical inputs return the node
+    assert_eq!(dag.common_ancestor(&src, &two)?, src);
+    assert_eq!(dag.common_ancestor(&one, &one)?, one);
+
+    assert!(dag.common_ancestor(&one, "missing").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_iter_with_depth() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("a").join("b"))?;
+    fs::write(dir.join("top.txt"), "top")?;
+    fs::write(dir.join("a").join("mid.txt"), "mid")?;
+    fs::write(dir.join("a").join("b").join("deep.bin"), vec![5u8; 40])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+    let entries: Vec<(usize, String, LeafType)> = dag
+        .iter_with_depth()
+        .map(|(depth, path, leaf)| {
+            (
+                depth,
+                path.to_string_lossy().replace('\\', "/"),
+                leaf.leaf_type.clone(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        entries,
+        vec![
+            (0, "".to_string(), LeafType::Directory),
+            (1, "a".to_string(), LeafType::Directory),
+            (2, "a/b".to_string(), LeafType::Directory),
+            (3, "a/b/deep.bin".to_string(), LeafType::File),
+            (4, "a/b/deep.bin/0".to_string(), LeafType::Chunk),
+            (4, "a/b/deep.bin/1".to_string(), LeafType::Chunk),
+            (4, "a/b/deep.bin/2".to_string(), LeafType::Chunk),
+            (2, "a/mid.txt".to_string(), LeafType::File),
+            (1, "top.txt".to_string(), LeafType::File),
+        ]
+    );
+    assert_eq!(entries.len(), dag.leaves.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_data_digest() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "alpha")?;
+    fs::write(dir.join("sub").join("large.bin"), vec![3u8; 500])?;
+
+    let dag = create_dag(&dir, false)?;
+    let digest = dag.data_digest()?;
+
+    // Different chunking, metadata and root name change the root but not the data
+    let restructured = create_dag_with_config(
+        &dir,
+        DagBuilderConfig::new()
+            .with_chunk_size(64)
+            .with_entry_counts()
+            .with_root_name("renamed"),
+    )?;
+    assert_ne!(restructured.root, dag.root);
+    assert_eq!(restructured.data_digest()?, digest);
+
+    // Editing a single byte changes it
+    fs::write(dir.join("a.txt"), "alphA")?;
+    let edited = create_dag(&dir, false)?;
+    assert_ne!(edited.data_digest()?, digest);
+
+    Ok(())
+}
+
+#[test]
+fn test_into_parts_and_from_parts() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("nested"))?;
+    fs::write(dir.join("a.txt"), "a")?;
+    fs::write(dir.join("nested").join("b.txt"), "b")?;
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+    let original = dag.clone();
+
+    let (root, leaves, labels) = dag.into_parts();
+    assert_eq!(root, original.root);
+    assert_eq!(leaves.len(), original.leaves.len());
+    assert_eq!(labels, original.labels);
+
+    let rebuilt = Dag::from_parts(root.clone(), leaves.clone(), labels)?;
+    rebuilt.verify()?;
+    assert!(!rebuilt.is_partial());
+    assert_eq!(rebuilt.root, original.root);
+    assert_eq!(rebuilt.labels, original.labels);
+
+    // The root must be among the leaves
+    let mut without_root = leaves;
+    without_root.remove(&root);
+    assert!(matches!(
+        Dag::from_parts(root, without_root, None),
+        Err(ScionicError::MissingLeaf(_))
+    ));
+
+    Ok(())
+}
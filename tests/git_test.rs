@@ -0,0 +1,74 @@
+#![cfg(feature = "git")]
+
+use scionic_merkle_tree_rs::{
+    create_dag_from_git, create_dag_with_config, DagBuilderConfig, Result,
+};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(repo: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(repo)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[test]
+fn test_git_tree_matches_checkout() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo_path = temp_dir.path().join("repo");
+    fs::create_dir(&repo_path)?;
+
+    if git(&repo_path, &["init", "-q"]).is_none() {
+        eprintln!("Skipping: git not available");
+        return Ok(());
+    }
+
+    fs::create_dir_all(repo_path.join("src").join("nested"))?;
+    fs::write(repo_path.join("README.md"), "# Project")?;
+    fs::write(repo_path.join("src").join("main.rs"), "fn main() {}")?;
+    fs::write(
+        repo_path.join("src").join("nested").join("data.bin"),
+        vec![9u8; 100],
+    )?;
+    fs::write(repo_path.join("run.sh"), "#!/bin/sh\necho hi\n")?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let script = repo_path.join("run.sh");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+    }
+
+    git(&repo_path, &["add", "."]).expect("git add");
+    git(&repo_path, &["commit", "-q", "-m", "initial"]).expect("git commit");
+    let tree = git(&repo_path, &["rev-parse", "HEAD^{tree}"]).expect("tree id");
+
+    let checkout = temp_dir.path().join("checkout");
+    fs::create_dir(&checkout)?;
+    let work_tree = format!("--work-tree={}", checkout.display());
+    git(&repo_path, &[&work_tree, "checkout", "HEAD", "--", "."]).expect("git checkout");
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(32)
+        .with_root_name("project");
+
+    let repo = gix::open(&repo_path).expect("open repo");
+    let tree_oid = gix::ObjectId::from_hex(tree.as_bytes()).expect("tree oid");
+    let from_git = create_dag_from_git(&repo, tree_oid, config.clone())?;
+    from_git.verify()?;
+
+    let from_checkout = create_dag_with_config(&checkout, config)?;
+    assert_eq!(from_git.root, from_checkout.root);
+
+    Ok(())
+}
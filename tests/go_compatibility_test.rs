@@ -14,12 +14,9 @@ fn find_go_repo_path() -> Option<PathBuf> {
         std::env::current_dir().ok()?.join("../Scionic-Merkle-Tree"),
     ];
 
-    for path in candidates {
-        if path.join("go.mod").exists() {
-            return Some(path);
-        }
-    }
-    None
+    candidates
+        .into_iter()
+        .find(|path| path.join("go.mod").exists())
 }
 
 /// Check if test_helper.go is available (local addition, not in official repo)
@@ -45,7 +42,7 @@ fn create_dag_with_go(
 ) -> std::io::Result<std::process::Output> {
     let go_path = find_go_repo_path().expect("Go repo not found");
     Command::new("go")
-        .args(&[
+        .args([
             "run",
             go_path.join("tests/test_helper.go").to_str().unwrap(),
             "create",
@@ -59,7 +56,7 @@ fn create_dag_with_go(
 fn verify_dag_with_go(cbor_path: &str) -> std::io::Result<std::process::Output> {
     let go_path = find_go_repo_path().expect("Go repo not found");
     Command::new("go")
-        .args(&[
+        .args([
             "run",
             go_path.join("tests/test_helper.go").to_str().unwrap(),
             "verify",
@@ -94,8 +91,7 @@ fn test_rust_creates_go_reads() -> Result<()> {
     // Try to verify with Go
     let result = verify_dag_with_go(cbor_path.to_str().unwrap());
 
-    if result.is_ok() {
-        let output = result.unwrap();
+    if let Ok(output) = result {
         println!(
             "Go verification output: {:?}",
             String::from_utf8_lossy(&output.stdout)
@@ -212,7 +208,7 @@ fn test_cid_format() -> Result<()> {
     );
 
     // All leaf hashes should also be valid CIDs
-    for (hash, _) in &dag.leaves {
+    for hash in dag.leaves.keys() {
         assert!(
             hash.starts_with("bafi"),
             "Leaf hash should be CIDv1 CBOR format: {}",
@@ -301,3 +297,103 @@ fn test_content_addressing_is_deterministic() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_link_cids_are_cbor_v1() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input = temp_dir.path().join("input");
+    fs::create_dir(&input)?;
+    fs::create_dir(input.join("subdir"))?;
+    fs::write(input.join("a.txt"), "a")?;
+    fs::write(input.join("subdir").join("b.txt"), "b")?;
+
+    let dag = create_dag(&input, false)?;
+
+    let root_cid = dag.root_cid()?;
+    assert_eq!(root_cid.version(), cid::Version::V1);
+    assert_eq!(root_cid.codec(), 0x51);
+    assert_eq!(root_cid.to_string(), dag.root);
+
+    let mut link_count = 0;
+    for leaf in dag.leaves.values() {
+        let cids = leaf.link_cids()?;
+        assert_eq!(cids.len(), leaf.links.len());
+
+        for (cid, link) in cids.iter().zip(&leaf.links) {
+            assert_eq!(cid.version(), cid::Version::V1);
+            assert_eq!(cid.codec(), 0x51);
+            assert_eq!(&cid.to_string(), link);
+        }
+        link_count += cids.len();
+    }
+    assert_eq!(link_count, dag.leaves.len() - 1);
+
+    // Malformed links are reported rather than silently accepted
+    let mut broken = dag.leaves[&dag.root].clone();
+    broken.links.push("not-a-cid".to_string());
+    assert!(broken.link_cids().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_rejects_unexpected_cid_codec() -> Result<()> {
+    use scionic_merkle_tree_rs::ScionicError;
+
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("test.txt");
+    fs::write(&file, "test")?;
+
+    let dag = create_dag(&file, false)?;
+    let root_leaf = &dag.leaves[&dag.root];
+
+    // Same digest, but claimed as DAG-CBOR (0x71) instead of CBOR (0x51)
+    let original = dag.root_cid()?;
+    let relabelled = cid::Cid::new_v1(0x71, *original.hash()).to_string();
+
+    let mut tampered = root_leaf.clone();
+    tampered.hash = relabelled;
+
+    match tampered.verify_root_leaf() {
+        Err(ScionicError::InvalidCid(_)) => {}
+        result => panic!("expected InvalidCid, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_configurable_cid_base() -> Result<()> {
+    use multibase::Base;
+    use scionic_merkle_tree_rs::{create_dag_with_config, DagBuilderConfig};
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "a")?;
+    fs::write(dir.join("sub").join("large.bin"), vec![5u8; 100])?;
+    let config = DagBuilderConfig::new().with_chunk_size(32);
+
+    // The default base32 lower with the 0x51 codec gives Go's "bafi" CIDs
+    let dag = create_dag_with_config(&dir, config.clone())?;
+    assert!(dag.root.starts_with("bafi"));
+    assert_eq!(dag.leaves[&dag.root].cid_base()?, Base::Base32Lower);
+
+    let upper = create_dag_with_config(&dir, config.with_cid_base(Base::Base32Upper))?;
+    upper.verify()?;
+    assert!(upper.root.starts_with("BAFI"));
+    assert!(upper.leaves.keys().all(|hash| hash.starts_with('B')));
+    // Links are hashed as strings, so the root differs beyond its spelling
+    assert_ne!(upper.root.to_lowercase(), dag.root);
+
+    // Round-trips keep the base and still verify
+    for loaded in [
+        Dag::from_cbor(&upper.to_cbor()?)?,
+        Dag::from_json(&upper.to_json()?)?,
+    ] {
+        assert_eq!(loaded.root, upper.root);
+        loaded.verify()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+/// Golden vector tests for cross-implementation compatibility
+///
+/// Canonical inputs are built deterministically (fixed names and content, no
+/// timestamps) and their roots compared with the vectors in `tests/golden`,
+/// so an unintended root change is caught without a Go toolchain.
+///
+/// `roots.txt` and the `.cbor` files were produced by this implementation,
+/// so they guard against regressions. Only regenerate them for an intended
+/// format change:
+///   SCIONIC_UPDATE_GOLDENS=1 cargo test --test golden_test
+///
+/// `go_roots.txt` holds the roots the Go implementation produces for the
+/// same inputs and chunk sizes, which is what locks compatibility. With the
+/// Go repository checked out next to this one (see the interop tests),
+/// record them with:
+///   SCIONIC_UPDATE_GO_GOLDENS=1 cargo test --test golden_test
+use scionic_merkle_tree_rs::{
+    create_dag_with_config, Dag, DagBuilderConfig, Result, DEFAULT_CHUNK_SIZE,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+fn updating() -> bool {
+    std::env::var_os("SCIONIC_UPDATE_GOLDENS").is_some()
+}
+
+fn updating_go() -> bool {
+    std::env::var_os("SCIONIC_UPDATE_GO_GOLDENS").is_some()
+}
+
+/// A canonical input and the DAG built from it
+struct Vector {
+    name: &'static str,
+    input: PathBuf,
+    chunk_size: usize,
+    dag: Dag,
+}
+
+/// Deterministic, non-repeating content so chunks don't deduplicate
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| ((i * 31 + i / 251) % 251) as u8).collect()
+}
+
+/// Build each canonical input under `base`
+fn build_canonical(base: &Path) -> Result<Vec<Vector>> {
+    let mut vectors = Vec::new();
+    let mut add = |name, input: PathBuf, chunk_size| -> Result<()> {
+        let config = DagBuilderConfig::new().with_chunk_size(chunk_size);
+        let dag = create_dag_with_config(&input, config)?;
+        vectors.push(Vector {
+            name,
+            input,
+            chunk_size,
+            dag,
+        });
+        Ok(())
+    };
+
+    let file = base.join("hello.txt");
+    fs::write(&file, "Hello, Scionic Merkle Tree!\n")?;
+    add("file", file, DEFAULT_CHUNK_SIZE)?;
+
+    let dir = base.join("tree");
+    fs::create_dir_all(dir.join("docs").join("nested"))?;
+    fs::create_dir(dir.join("empty"))?;
+    fs::write(dir.join("readme.md"), "# Tree\n")?;
+    fs::write(dir.join("docs").join("a.txt"), "alpha")?;
+    fs::write(dir.join("docs").join("b.txt"), "beta")?;
+    fs::write(dir.join("docs").join("nested").join("c.bin"), pattern(300))?;
+    add("directory", dir, DEFAULT_CHUNK_SIZE)?;
+
+    // Chunked files at and just past a chunk boundary
+    for (name, len) in [
+        ("chunked_exact", 4 * 1024),
+        ("chunked_plus_one", 4 * 1024 + 1),
+    ] {
+        let file = base.join(format!("{}.bin", name));
+        fs::write(&file, pattern(len))?;
+        add(name, file, 1024)?;
+    }
+
+    Ok(vectors)
+}
+
+fn read_roots(file: &str) -> Result<BTreeMap<String, String>> {
+    let text = fs::read_to_string(golden_dir().join(file))?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (name, root) = line.split_once(' ')?;
+            Some((name.to_string(), root.trim().to_string()))
+        })
+        .collect())
+}
+
+fn write_goldens(vectors: &[Vector]) -> Result<()> {
+    let dir = golden_dir();
+    fs::create_dir_all(&dir)?;
+
+    let mut roots = String::from("# vector root\n");
+    for vector in vectors {
+        roots.push_str(&format!("{} {}\n", vector.name, vector.dag.root));
+        vector
+            .dag
+            .save_to_file(dir.join(format!("{}.cbor", vector.name)))?;
+    }
+    fs::write(dir.join("roots.txt"), roots)?;
+
+    Ok(())
+}
+
+fn find_go_repo() -> Option<PathBuf> {
+    let candidates = [
+        PathBuf::from("/workspace/Scionic-Merkle-Tree"),
+        PathBuf::from("../Scionic-Merkle-Tree"),
+        std::env::current_dir().ok()?.join("../Scionic-Merkle-Tree"),
+    ];
+
+    candidates
+        .into_iter()
+        .find(|path| path.join("cmd/test_helper_chunk.go").exists())
+}
+
+/// Build every vector with the Go implementation and record its roots
+fn write_go_goldens(vectors: &[Vector], out_dir: &Path) -> Result<()> {
+    let go_repo = find_go_repo().expect("Go repository with cmd/test_helper_chunk.go not found");
+
+    let mut roots = String::from("# vector root, as built by the Go implementation\n");
+    for vector in vectors {
+        let output = out_dir.join(format!("{}.go.cbor", vector.name));
+        let status = Command::new("go")
+            .current_dir(&go_repo)
+            .args(["run", "cmd/test_helper_chunk.go", "create"])
+            .arg(&vector.input)
+            .arg(&output)
+            .arg(vector.chunk_size.to_string())
+            .status()?;
+        assert!(status.success(), "Go build failed for {}", vector.name);
+
+        let root = Dag::load_from_file(&output)?.root;
+        roots.push_str(&format!("{} {}\n", vector.name, root));
+    }
+    fs::write(golden_dir().join("go_roots.txt"), roots)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_roots_match_golden_vectors() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let vectors = build_canonical(temp_dir.path())?;
+
+    if updating() {
+        return write_goldens(&vectors);
+    }
+
+    let roots = read_roots("roots.txt")?;
+    assert_eq!(roots.len(), vectors.len());
+    for vector in &vectors {
+        vector.dag.verify()?;
+        assert_eq!(
+            Some(&vector.dag.root),
+            roots.get(vector.name),
+            "root mismatch for {}",
+            vector.name
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_roots_match_go_vectors() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let vectors = build_canonical(temp_dir.path())?;
+
+    if updating_go() {
+        return write_go_goldens(&vectors, temp_dir.path());
+    }
+
+    let go_roots = read_roots("go_roots.txt")?;
+    if go_roots.is_empty() {
+        eprintln!("Skipping: no Go-produced roots recorded in go_roots.txt");
+        return Ok(());
+    }
+
+    assert_eq!(go_roots.len(), vectors.len());
+    for vector in &vectors {
+        assert_eq!(
+            Some(&vector.dag.root),
+            go_roots.get(vector.name),
+            "root differs from Go for {}",
+            vector.name
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_golden_dags_load_and_verify() -> Result<()> {
+    if updating() {
+        return Ok(());
+    }
+
+    for (name, root) in read_roots("roots.txt")? {
+        let dag = Dag::load_from_file(golden_dir().join(format!("{}.cbor", name)))?;
+        dag.verify()?;
+        assert_eq!(dag.root, root, "stored DAG root mismatch for {}", name);
+    }
+
+    Ok(())
+}
@@ -14,7 +14,7 @@
 /// Requires: Go implementation at /workspace/Scionic-Merkle-Tree
 use scionic_merkle_tree_rs::{create_dag, Dag, Result};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::process::Command;
 use tempfile::TempDir;
 
@@ -25,12 +25,9 @@ fn find_go_repo() -> Option<PathBuf> {
         std::env::current_dir().ok()?.join("../Scionic-Merkle-Tree"),
     ];
 
-    for path in candidates {
-        if path.exists() && path.join("go.mod").exists() {
-            return Some(path);
-        }
-    }
-    None
+    candidates
+        .into_iter()
+        .find(|path| path.exists() && path.join("go.mod").exists())
 }
 
 fn test_helper_available() -> bool {
@@ -75,7 +72,7 @@ fn test_go_creates_rust_reads() -> Result<()> {
     let output = Command::new("go")
         .env("PATH", set_go_path())
         .current_dir(&go_repo)
-        .args(&[
+        .args([
             "run",
             "cmd/test_helper.go",
             "create",
@@ -145,7 +142,7 @@ fn test_rust_creates_go_reads() -> Result<()> {
     let output = Command::new("go")
         .env("PATH", set_go_path())
         .current_dir(&go_repo)
-        .args(&[
+        .args([
             "run",
             "cmd/test_helper.go",
             "verify",
@@ -192,7 +189,7 @@ fn test_same_input_same_root() -> Result<()> {
     let output = Command::new("go")
         .env("PATH", set_go_path())
         .current_dir(&go_repo)
-        .args(&[
+        .args([
             "run",
             "cmd/test_helper.go",
             "info",
@@ -1,4 +1,5 @@
-use scionic_merkle_tree_rs::{create_dag, Result};
+use scionic_merkle_tree_rs::{create_dag, Dag, DagLeafBuilder, LeafType, Result};
+use std::collections::HashMap;
 use std::fs;
 use tempfile::TempDir;
 
@@ -60,7 +61,7 @@ fn test_label_traversal_order() -> Result<()> {
     }
 
     // Verify root is NOT in labels
-    for (_, hash) in labels {
+    for hash in labels.values() {
         assert_ne!(hash, &dag.root, "Root should not be in labels map");
     }
 
@@ -161,3 +162,191 @@ fn test_clear_labels() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_partial_for_labels() -> Result<()> {
+    use scionic_merkle_tree_rs::merkle_tree::verify_proof;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    let subdir = dir.join("subdir");
+    fs::create_dir(&subdir)?;
+    for i in 0..8 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+        fs::write(
+            subdir.join(format!("nested{}.txt", i)),
+            format!("nested{}", i),
+        )?;
+    }
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+
+    let requested = dag.get_hashes_by_label_range(3, 6)?;
+    let partial = dag.partial_for_labels(3, 6)?;
+
+    assert_eq!(partial.root, dag.root);
+    assert!(partial.is_partial());
+    assert!(partial.leaves.len() < dag.leaves.len());
+    for hash in &requested {
+        assert!(partial.leaves.contains_key(hash));
+    }
+
+    partial.verify()?;
+
+    // Every present child of a multi-link parent is proven by that parent
+    for parent in partial.leaves.values() {
+        if parent.links.len() <= 1 {
+            continue;
+        }
+        let proofs = parent.proofs.as_ref().expect("Parent should carry proofs");
+        let merkle_root = parent.classic_merkle_root.as_ref().unwrap();
+        for link in parent
+            .links
+            .iter()
+            .filter(|l| partial.leaves.contains_key(*l))
+        {
+            let branch = &proofs[link];
+            verify_proof(link.as_bytes(), &branch.proof, merkle_root)?;
+        }
+    }
+
+    // Invalid ranges are rejected
+    assert!(dag.partial_for_labels(0, 2).is_err());
+    assert!(dag.partial_for_labels(5, 3).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_labels_are_hash_neutral() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    for i in 0..3 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let labelled = create_dag(&dir, false)?.with_labels()?;
+    assert!(labelled.labels.is_some());
+
+    let mut stripped = labelled.clone();
+    stripped.strip_labels();
+    assert!(stripped.labels.is_none());
+
+    let with_labels = labelled.to_cbor()?;
+    let without_labels = stripped.to_cbor()?;
+    assert!(without_labels.len() < with_labels.len());
+
+    let reloaded_with = Dag::from_cbor(&with_labels)?;
+    let reloaded_without = Dag::from_cbor(&without_labels)?;
+    reloaded_with.verify()?;
+    reloaded_without.verify()?;
+
+    assert_eq!(reloaded_with.root, reloaded_without.root);
+    assert_eq!(reloaded_with.labels, labelled.labels);
+    assert!(reloaded_without.labels.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_calculate_labels_from_offset() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut dags = Vec::new();
+    for name in ["a", "b"] {
+        let dir = temp_dir.path().join(name);
+        fs::create_dir(&dir)?;
+        for i in 0..5 {
+            fs::write(dir.join(format!("{}{}.txt", name, i)), name.repeat(i + 1))?;
+        }
+        dags.push(create_dag(&dir, false)?);
+    }
+
+    let mut dag_b = dags.pop().unwrap();
+    let mut dag_a = dags.pop().unwrap();
+
+    dag_a.calculate_labels()?;
+    let (a_first, a_last) = dag_a.label_range().unwrap();
+    assert_eq!((a_first, a_last), (1, dag_a.leaves.len() - 1));
+
+    dag_b.calculate_labels_from(a_last + 1)?;
+    let (b_first, b_last) = dag_b.label_range().unwrap();
+    assert_eq!(
+        (b_first, b_last),
+        (a_last + 1, a_last + dag_b.leaves.len() - 1)
+    );
+
+    // Same traversal order, shifted by the offset
+    let mut unshifted = dag_b.clone();
+    unshifted.calculate_labels()?;
+    for label in b_first..=b_last {
+        assert_eq!(
+            dag_b.get_hashes_by_label_range(label, label)?,
+            unshifted.get_hashes_by_label_range(label - a_last, label - a_last)?
+        );
+    }
+
+    // Range queries resolve within each namespace only
+    let hashes = dag_b.get_hashes_by_label_range(b_first, b_last)?;
+    assert_eq!(hashes.len(), dag_b.leaves.len() - 1);
+    for (i, hash) in hashes.iter().enumerate() {
+        assert_eq!(dag_b.get_label(hash)?, (b_first + i).to_string());
+        assert!(dag_a.get_label(hash).is_err());
+    }
+    assert_eq!(dag_a.get_hashes_by_label_range(1, a_last)?.len(), a_last);
+    assert!(dag_a.get_hashes_by_label_range(b_first, b_last).is_err());
+    assert!(dag_b.get_hashes_by_label_range(1, a_last).is_err());
+    assert!(dag_b
+        .get_hashes_by_label_range(b_first, b_last + 1)
+        .is_err());
+    assert_eq!(dag_b.get_label(&dag_b.root)?, "0");
+
+    // Serving a range from the offset namespace still verifies
+    let partial = dag_b.partial_for_labels(b_first + 1, b_first + 2)?;
+    partial.verify()?;
+
+    assert!(dag_b.calculate_labels_from(0).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_calculate_labels_on_deep_dag() -> Result<()> {
+    const DEPTH: usize = 5000;
+
+    // Built by hand, as a path this deep is too long for the filesystem
+    let mut leaves = HashMap::new();
+    let mut path = "d/".repeat(DEPTH);
+    let file = DagLeafBuilder::new(format!("{}file.txt", path))
+        .set_type(LeafType::File)
+        .set_data(b"deep".to_vec())
+        .build_leaf(None)?;
+    let mut child = file.hash.clone();
+    leaves.insert(file.hash.clone(), file);
+
+    for _ in 1..DEPTH {
+        path.truncate(path.len() - 2);
+        let dir = DagLeafBuilder::new(path.trim_end_matches('/'))
+            .set_type(LeafType::Directory)
+            .add_link(child)
+            .build_leaf(None)?;
+        child = dir.hash.clone();
+        leaves.insert(dir.hash.clone(), dir);
+    }
+
+    let root = DagLeafBuilder::new("root")
+        .set_type(LeafType::Directory)
+        .add_link(child)
+        .build_root_leaf(&leaves, None)?;
+    let root_hash = root.hash.clone();
+    leaves.insert(root.hash.clone(), root);
+
+    let mut dag = Dag::from_parts(root_hash, leaves, None)?;
+    dag.calculate_labels()?;
+    assert_eq!(dag.label_range(), Some((1, DEPTH)));
+
+    Ok(())
+}
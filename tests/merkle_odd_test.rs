@@ -1,7 +1,10 @@
 /// Test that our merkle tree building matches Go's txaty/go-merkletree
 /// for odd numbers of leaves (which duplicate the last node)
-use scionic_merkle_tree_rs::merkle_tree::build_merkle_root;
+use scionic_merkle_tree_rs::merkle_tree::{build_merkle_root, merkle_root_for_links};
+use scionic_merkle_tree_rs::{create_dag_with_config, DagBuilderConfig, Result};
 use sha2::{Digest, Sha256};
+use std::fs;
+use tempfile::TempDir;
 
 #[test]
 fn test_merkle_root_with_3_leaves() {
@@ -118,3 +121,32 @@ fn test_merkle_root_with_even_leaves() {
         "Merkle root with even leaves should work correctly"
     );
 }
+
+#[test]
+fn test_merkle_root_for_links_matches_stored_roots() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("single"))?;
+
+    for i in 0..5 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(dir.join("single").join("only.txt"), "only child")?;
+    fs::write(dir.join("chunked.bin"), vec![7u8; 100])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+
+    for leaf in dag.leaves.values() {
+        assert_eq!(
+            merkle_root_for_links(&leaf.links),
+            leaf.classic_merkle_root,
+            "merkle root mismatch for {}",
+            leaf.item_name
+        );
+    }
+
+    assert_eq!(merkle_root_for_links(&[]), None);
+
+    Ok(())
+}
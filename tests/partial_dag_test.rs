@@ -1,4 +1,7 @@
-use scionic_merkle_tree_rs::{create_dag, LeafType, Result};
+use scionic_merkle_tree_rs::{
+    create_dag, create_dag_with_config, Dag, DagBuilderConfig, DagKind, LeafType, Result,
+    ScionicError,
+};
 use std::fs;
 use tempfile::TempDir;
 
@@ -190,3 +193,369 @@ fn test_partial_dag_verification_with_proofs() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_dag_kind_is_explicit() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let dag = create_dag(&dir, false)?;
+    assert_eq!(dag.kind(), DagKind::Full);
+
+    let file_hash = dag.leaves[&dag.root].links[0].clone();
+    let partial = dag.get_partial(&[file_hash], false)?;
+    assert_eq!(partial.kind(), DagKind::Partial);
+    assert!(partial.is_partial());
+
+    // Tampering with the leaf count fails verification instead of
+    // flipping the DAG into partial mode
+    let mut tampered = dag.clone();
+    tampered.leaves.get_mut(&tampered.root).unwrap().leaf_count = Some(100);
+    assert_eq!(tampered.kind(), DagKind::Full);
+    assert!(tampered.verify().is_err());
+
+    // Without a marker (e.g. after deserializing) the kind is inferred
+    let loaded = Dag::from_cbor(&dag.to_cbor()?)?;
+    assert_eq!(loaded.kind, None);
+    assert_eq!(loaded.kind(), DagKind::Full);
+    assert_eq!(loaded.as_partial().kind(), DagKind::Partial);
+
+    Ok(())
+}
+
+#[test]
+fn test_partial_by_glob() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("docs").join("old"))?;
+    fs::create_dir(dir.join("images"))?;
+
+    fs::write(dir.join("readme.txt"), "top level")?;
+    fs::write(dir.join("docs").join("guide.txt"), "guide")?;
+    fs::write(
+        dir.join("docs").join("old").join("notes.txt"),
+        vec![b'n'; 5000],
+    )?;
+    fs::write(dir.join("docs").join("guide.md"), "markdown")?;
+    fs::write(dir.join("images").join("photo.jpg"), vec![0xffu8; 300])?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_with_config(&dir, config)?;
+
+    let partial = dag.partial_by_glob("**/*.txt")?;
+    assert_eq!(partial.root, dag.root);
+    assert!(partial.is_partial());
+    partial.verify()?;
+
+    let mut files: Vec<&str> = partial
+        .leaves
+        .values()
+        .filter(|l| l.leaf_type == LeafType::File)
+        .map(|l| l.item_name.as_str())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec!["docs/guide.txt", "docs/old/notes.txt", "readme.txt"]
+    );
+
+    // Chunked matches carry their chunks so the content can be read back
+    let notes = partial
+        .leaves
+        .values()
+        .find(|l| l.item_name == "docs/old/notes.txt")
+        .unwrap();
+    assert_eq!(notes.links.len(), 5);
+    assert!(notes.links.iter().all(|l| partial.leaves.contains_key(l)));
+
+    assert!(dag.partial_by_glob("**/*.png").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_partial_rejects_unreachable_leaf() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    for i in 0..4 {
+        fs::write(
+            dir.join("a").join(format!("file{}.txt", i)),
+            format!("a{}", i),
+        )?;
+        fs::write(
+            dir.join("b").join(format!("file{}.txt", i)),
+            format!("b{}", i),
+        )?;
+    }
+
+    let dag = create_dag(&dir, false)?;
+    let find = |name: &str| {
+        dag.leaves
+            .values()
+            .find(|leaf| leaf.item_name == name)
+            .map(|leaf| leaf.hash.clone())
+            .unwrap()
+    };
+
+    let partial = dag.get_partial(&[find("a/file0.txt")], false)?;
+    partial.verify()?;
+
+    // A valid leaf from the same DAG whose parent directory isn't present
+    let mut tampered = partial.clone();
+    let stray = dag.leaves[&find("b/file1.txt")].clone();
+    tampered.leaves.insert(stray.hash.clone(), stray);
+
+    match tampered.verify() {
+        Err(ScionicError::InvalidDag(msg)) => assert!(msg.contains("not reachable")),
+        result => panic!("expected InvalidDag, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_partial_rejects_proof_for_unlinked_child() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    for i in 0..4 {
+        fs::write(
+            dir.join("a").join(format!("file{}.txt", i)),
+            format!("a{}", i),
+        )?;
+        fs::write(
+            dir.join("b").join(format!("file{}.txt", i)),
+            format!("b{}", i),
+        )?;
+    }
+
+    let dag = create_dag(&dir, false)?;
+    let find = |name: &str| {
+        dag.leaves
+            .values()
+            .find(|leaf| leaf.item_name == name)
+            .map(|leaf| leaf.hash.clone())
+            .unwrap()
+    };
+
+    let partial = dag.partial_by_glob("a/file0.txt")?;
+    partial.verify()?;
+
+    // A genuine proof from directory b, attached to directory a
+    let a = find("a");
+    let foreign = find("b/file1.txt");
+    let branch = dag.leaves[&find("b")].get_branch(&foreign)?.unwrap();
+
+    let mut tampered = partial.clone();
+    tampered
+        .leaves
+        .get_mut(&a)
+        .unwrap()
+        .proofs
+        .get_or_insert_with(Default::default)
+        .insert(foreign.clone(), branch);
+
+    match tampered.verify() {
+        Err(ScionicError::MissingLink(msg)) => {
+            assert!(msg.contains(&a));
+            assert!(msg.contains(&foreign));
+        }
+        result => panic!("expected MissingLink, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_partial_rejects_tampered_proof_sibling() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    for i in 0..6 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let dag = create_dag(&dir, false)?;
+    let partial = dag.partial_by_glob("file3.txt")?;
+    partial.verify()?;
+
+    // A single flipped sibling byte no longer hashes up to the Merkle root
+    let mut tampered = partial.clone();
+    let branch = tampered
+        .leaves
+        .get_mut(&dag.root)
+        .unwrap()
+        .proofs
+        .as_mut()
+        .unwrap()
+        .values_mut()
+        .next()
+        .unwrap();
+    branch.proof.siblings[0][0] ^= 0xff;
+
+    assert!(matches!(tampered.verify(), Err(ScionicError::InvalidProof)));
+
+    Ok(())
+}
+
+#[test]
+fn test_file_prefix() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    let content: Vec<u8> = (0..100u8).collect();
+    fs::write(dir.join("data.bin"), &content)?;
+    fs::write(dir.join("other.txt"), "other")?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(16)
+        .with_max_links_per_leaf(3);
+    let dag = create_dag_with_config(&dir, config)?;
+    let file = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "data.bin" && !leaf.is_link_group())
+        .unwrap();
+
+    // 20 bytes fall within the first two chunks
+    let prefix = dag.file_prefix(&file.hash, 20)?;
+    assert!(prefix.is_partial());
+    prefix.verify_as(&dag.root)?;
+
+    let chunks: Vec<_> = prefix
+        .leaves
+        .values()
+        .filter(|leaf| leaf.leaf_type == LeafType::Chunk)
+        .collect();
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(prefix.read_file_range(&file.hash, 0, 32)?, &content[..32]);
+    assert!(prefix.read_file_range(&file.hash, 0, 33).is_err());
+
+    assert!(dag.file_prefix(&dag.root, 20).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_combine_skeleton_with_packet_stream() -> Result<()> {
+    use scionic_merkle_tree_rs::PacketAssembler;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("docs").join("deep"))?;
+    fs::create_dir_all(dir.join("media"))?;
+    for i in 0..5 {
+        fs::write(
+            dir.join("docs").join(format!("note{}.txt", i)),
+            format!("note {}", i),
+        )?;
+    }
+    fs::write(dir.join("docs").join("deep").join("leaf.txt"), "leaf")?;
+    let chunked: Vec<u8> = (0..3_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("media").join("video.bin"), &chunked)?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(256)
+        .with_max_links_per_leaf(3);
+    let dag = create_dag_with_config(&dir, config)?;
+
+    // The skeleton: every directory with its path to the root and proofs
+    let directories: Vec<String> = dag
+        .leaves
+        .values()
+        .filter(|leaf| leaf.leaf_type == LeafType::Directory)
+        .map(|leaf| leaf.hash.clone())
+        .collect();
+    let skeleton = dag.get_partial(&directories, false)?;
+    skeleton.verify_as(&dag.root)?;
+
+    // Parents first, skipping what the skeleton holds
+    let packets: std::collections::HashMap<_, _> = dag
+        .get_leaf_sequence()
+        .into_iter()
+        .map(|packet| (packet.leaf.hash.clone(), packet))
+        .collect();
+    let stream: Vec<_> = dag
+        .fetch_order(|_| 0)
+        .into_iter()
+        .filter(|hash| !skeleton.leaves.contains_key(hash))
+        .map(|hash| packets[&hash].clone())
+        .collect();
+    assert!(!stream.is_empty());
+
+    let mut assembler = PacketAssembler::new(skeleton.clone())?;
+    assert!(!assembler.is_complete());
+    for (i, packet) in stream.iter().enumerate() {
+        let complete = assembler.apply(packet.clone())?;
+        assert_eq!(complete, i == stream.len() - 1);
+    }
+    assert!(assembler.missing().is_empty());
+    let assembled = assembler.into_dag();
+    assert_eq!(assembled.kind(), DagKind::Full);
+    assembled.verify_as(&dag.root)?;
+    assert_eq!(assembled.leaves.len(), dag.leaves.len());
+
+    // A chunk before its file is refused, and a truncated stream stays
+    // partial
+    let mut assembler = PacketAssembler::new(skeleton.clone())?;
+    let chunk = stream
+        .iter()
+        .find(|packet| packet.leaf.leaf_type == LeafType::Chunk)
+        .unwrap();
+    assert!(matches!(
+        assembler.apply(chunk.clone()),
+        Err(ScionicError::MissingLeaf(_))
+    ));
+    let partial = Dag::combine_partials_streaming(skeleton, stream[..2].to_vec())?;
+    assert_eq!(partial.kind(), DagKind::Partial);
+    partial.verify_as(&dag.root)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_get_partial_attaches_proofs() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    for i in 0..10 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let dag = create_dag(&dir, false)?;
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "file4.txt")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+
+    // Only the root and the file remain; the nine siblings are gone
+    let partial = dag.get_partial(std::slice::from_ref(&file_hash), false)?;
+    assert_eq!(partial.leaves.len(), 2);
+    let proofs = partial.leaves[&dag.root].proofs.as_ref().unwrap();
+    assert_eq!(proofs.len(), 1);
+    assert_eq!(proofs[&file_hash].leaf, file_hash);
+    partial.verify_as(&dag.root)?;
+
+    // The proof is what ties the file to the root
+    let mut tampered = partial.clone();
+    let root = tampered.leaves.get_mut(&dag.root).unwrap();
+    root.proofs
+        .as_mut()
+        .unwrap()
+        .get_mut(&file_hash)
+        .unwrap()
+        .proof
+        .siblings[0][0] ^= 0xff;
+    assert!(matches!(tampered.verify(), Err(ScionicError::InvalidProof)));
+
+    Ok(())
+}
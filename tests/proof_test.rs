@@ -0,0 +1,180 @@
+use scionic_merkle_tree_rs::{create_dag, verify_labeled_leaf, Result, ScionicError};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_prove_label_verifies_against_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    let subdir = dir.join("subdir");
+    fs::create_dir(&subdir)?;
+
+    for i in 0..5 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+        fs::write(
+            subdir.join(format!("nested{}.txt", i)),
+            format!("nested{}", i),
+        )?;
+    }
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+
+    let label_count = dag.labels.as_ref().unwrap().len();
+    let root = dag.root.clone();
+
+    // Every label, including the root (0), proves against just the root string
+    for label in 0..=label_count {
+        let proof = dag.prove_label(label)?;
+        proof.verify(&root)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_prove_label_rejects_wrong_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    for i in 0..3 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+
+    let proof = dag.prove_label(2)?;
+    let result = proof.verify("bafireiwrongroot");
+    assert!(matches!(result, Err(ScionicError::HashMismatch { .. })));
+
+    Ok(())
+}
+
+#[test]
+fn test_prove_label_detects_tampered_branch() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+
+    let mut proof = dag.prove_label(1)?;
+    let branch = proof.path[0].branch.as_mut().expect("Expected a branch");
+    branch.proof.siblings[0] = serde_bytes::ByteBuf::from(vec![0u8; 32]);
+
+    assert!(matches!(
+        proof.verify(&dag.root),
+        Err(ScionicError::InvalidProof)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_prove_label_detects_forged_content() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+
+    // The CID covers the content hash, not the content itself
+    let mut proof = dag.prove_label(1)?;
+    proof.leaf.content = Some(b"EVIL".to_vec());
+    assert!(matches!(
+        proof.verify(&dag.root),
+        Err(ScionicError::ContentHashMismatch)
+    ));
+
+    let mut proof = dag.prove_label(1)?;
+    proof.leaf.content = None;
+    assert!(proof.verify(&dag.root).is_err());
+
+    // Same for a root holding its file inline
+    let file = temp_dir.path().join("single.txt");
+    fs::write(&file, "content")?;
+    let mut dag = create_dag(&file, false)?;
+    dag.calculate_labels()?;
+    let mut proof = dag.prove_label(0)?;
+    assert!(proof.path.is_empty());
+    proof.verify(&dag.root)?;
+    proof.leaf.content = Some(b"EVIL".to_vec());
+    assert!(matches!(
+        proof.verify(&dag.root),
+        Err(ScionicError::ContentHashMismatch)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_prove_label_without_labels() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("test.txt");
+    fs::write(&file, "content")?;
+
+    let dag = create_dag(&file, false)?;
+    assert!(dag.prove_label(1).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_labeled_leaf_standalone() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+
+    for i in 0..5 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let mut dag = create_dag(&dir, false)?;
+    dag.calculate_labels()?;
+
+    // The client only keeps the trusted root leaf's merkle root
+    let root = dag.root.clone();
+    let merkle_root = dag.leaves[&root].classic_merkle_root.clone().unwrap();
+
+    for label in 1..=5 {
+        let hash = &dag.get_hashes_by_label_range(label, label)?[0];
+        let leaf = dag.leaves[hash].clone();
+        let branch = dag.leaves[&root].get_branch(hash)?.unwrap();
+        verify_labeled_leaf(&leaf, &branch, &merkle_root)?;
+
+        // Tampered content no longer matches the CID
+        let mut tampered = leaf.clone();
+        tampered.content = Some(b"forged".to_vec());
+        assert!(verify_labeled_leaf(&tampered, &branch, &merkle_root).is_err());
+
+        // A branch for another leaf is rejected
+        let other = &dag.get_hashes_by_label_range(label % 5 + 1, label % 5 + 1)?[0];
+        let other_branch = dag.leaves[&root].get_branch(other)?.unwrap();
+        assert!(matches!(
+            verify_labeled_leaf(&leaf, &other_branch, &merkle_root),
+            Err(ScionicError::InvalidProof)
+        ));
+
+        // So is the wrong parent merkle root
+        assert!(matches!(
+            verify_labeled_leaf(&leaf, &branch, &[0u8; 32]),
+            Err(ScionicError::InvalidProof)
+        ));
+    }
+
+    Ok(())
+}
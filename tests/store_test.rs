@@ -0,0 +1,127 @@
+use scionic_merkle_tree_rs::{
+    apply_and_verify_to_store, create_dag_with_config, BlockStore, DagBuilderConfig, DagLeaf,
+    LeafPool, Result, ScionicError,
+};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Block store keeping one file per leaf
+struct FileStore {
+    dir: PathBuf,
+}
+
+impl BlockStore for FileStore {
+    fn put(&mut self, leaf: DagLeaf) -> Result<()> {
+        fs::write(self.dir.join(&leaf.hash), leaf.to_block_bytes()?)?;
+        Ok(())
+    }
+
+    fn get(&self, cid: &str) -> Result<Option<DagLeaf>> {
+        let path = self.dir.join(cid);
+        if !path.exists() {
+            return Ok(None);
+        }
+        DagLeaf::from_block_bytes(cid, &fs::read(path)?).map(Some)
+    }
+}
+
+#[test]
+fn test_sync_packets_into_store() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..5 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(dir.join("sub").join("large.bin"), vec![4u8; 100])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+
+    // Parents first, so each packet's link is checked against its parent
+    let mut packets = dag.get_leaf_sequence();
+    packets.sort_by_key(|p| {
+        (
+            !p.parent_hash.is_empty(),
+            p.leaf.item_name.matches('/').count(),
+        )
+    });
+
+    let store_dir = temp_dir.path().join("store");
+    fs::create_dir(&store_dir)?;
+    let mut store = FileStore { dir: store_dir };
+
+    for packet in packets {
+        apply_and_verify_to_store(packet, &dag.root, &mut store)?;
+    }
+
+    let loaded = store.load_dag(&dag.root)?;
+    loaded.verify()?;
+    assert_eq!(loaded.leaves.len(), dag.leaves.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_store_rejects_unlinked_child() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a.txt"), "alpha")?;
+    fs::write(dir.join("b.txt"), "beta")?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new())?;
+    let other_dir = temp_dir.path().join("other");
+    fs::create_dir(&other_dir)?;
+    fs::write(other_dir.join("c.txt"), "gamma")?;
+    let other = create_dag_with_config(&other_dir, DagBuilderConfig::new())?;
+
+    let mut store = LeafPool::new();
+    let mut packets = dag.get_leaf_sequence();
+    packets.sort_by_key(|p| !p.parent_hash.is_empty());
+    let root_packet = packets.remove(0);
+
+    // Another DAG's root isn't the trusted one
+    let other_root = other
+        .get_leaf_sequence()
+        .into_iter()
+        .find(|p| p.parent_hash.is_empty())
+        .unwrap();
+    assert!(matches!(
+        apply_and_verify_to_store(other_root, &dag.root, &mut store),
+        Err(ScionicError::HashMismatch { .. })
+    ));
+
+    // Children can't arrive before their parent
+    assert!(matches!(
+        apply_and_verify_to_store(packets[0].clone(), &dag.root, &mut store),
+        Err(ScionicError::MissingLeaf(_))
+    ));
+    assert!(store.is_empty());
+
+    apply_and_verify_to_store(root_packet, &dag.root, &mut store)?;
+
+    // Content that doesn't match its hash
+    let mut tampered = packets[0].clone();
+    tampered.leaf.content = Some(b"tampered".to_vec());
+    assert!(matches!(
+        apply_and_verify_to_store(tampered, &dag.root, &mut store),
+        Err(ScionicError::ContentHashMismatch)
+    ));
+
+    // A valid leaf claiming a parent that doesn't link to it
+    let mut stray = other
+        .get_leaf_sequence()
+        .into_iter()
+        .find(|p| !p.parent_hash.is_empty())
+        .unwrap();
+    stray.parent_hash = dag.root.clone();
+
+    assert!(matches!(
+        apply_and_verify_to_store(stray, &dag.root, &mut store),
+        Err(ScionicError::InvalidDag(_))
+    ));
+    assert_eq!(store.len(), 1);
+
+    Ok(())
+}
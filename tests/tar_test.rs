@@ -0,0 +1,68 @@
+#![cfg(feature = "tar")]
+
+use scionic_merkle_tree_rs::{create_dag_with_config, DagBuilderConfig, Result};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_tar_round_trip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("nested").join("deeper"))?;
+    fs::create_dir(input_dir.join("empty"))?;
+
+    let chunked: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(input_dir.join("small.txt"), b"small file")?;
+    fs::write(input_dir.join("nested").join("chunked.bin"), &chunked)?;
+    fs::write(
+        input_dir.join("nested").join("deeper").join("empty.txt"),
+        b"",
+    )?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_with_config(&input_dir, config)?;
+    dag.verify()?;
+
+    let mut archive = Vec::new();
+    dag.to_tar(&mut archive)?;
+
+    let output_dir = temp_dir.path().join("output");
+    tar::Archive::new(archive.as_slice()).unpack(&output_dir)?;
+
+    assert_eq!(fs::read(output_dir.join("small.txt"))?, b"small file");
+    assert_eq!(
+        fs::read(output_dir.join("nested").join("chunked.bin"))?,
+        chunked
+    );
+    assert!(fs::read(output_dir.join("nested").join("deeper").join("empty.txt"))?.is_empty());
+    assert!(output_dir.join("empty").is_dir());
+
+    // The extracted tree builds the same DAG
+    let rebuilt = create_dag_with_config(
+        &output_dir,
+        DagBuilderConfig::new()
+            .with_chunk_size(1024)
+            .with_root_name("input"),
+    )?;
+    assert_eq!(rebuilt.root, dag.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_tar_single_file() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file_path = temp_dir.path().join("single.txt");
+    fs::write(&file_path, b"just one file")?;
+
+    let dag = create_dag_with_config(&file_path, DagBuilderConfig::new())?;
+
+    let mut archive = Vec::new();
+    dag.to_tar(&mut archive)?;
+
+    let output_dir = temp_dir.path().join("output");
+    tar::Archive::new(archive.as_slice()).unpack(&output_dir)?;
+
+    assert_eq!(fs::read(output_dir.join("single.txt"))?, b"just one file");
+    Ok(())
+}
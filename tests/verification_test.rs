@@ -1,4 +1,7 @@
-use scionic_merkle_tree_rs::{create_dag, Dag, LeafType, Result};
+use scionic_merkle_tree_rs::{
+    create_dag, create_dag_with_config, Dag, DagBuilderConfig, LeafType, Result, ScionicError,
+    VerifyOptions,
+};
 use std::fs;
 use tempfile::TempDir;
 
@@ -238,3 +241,1040 @@ fn test_empty_file_has_valid_hash() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_additional_data_survives_roundtrips() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("file.txt"), "content")?;
+
+    let mut metadata = std::collections::BTreeMap::new();
+    metadata.insert("zeta".to_string(), "last".to_string());
+    metadata.insert("alpha".to_string(), "first".to_string());
+    metadata.insert("middle".to_string(), "with spaces and ünïcode".to_string());
+
+    let config = DagBuilderConfig::new().with_additional_data(metadata.clone());
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+
+    let from_cbor = Dag::from_cbor(&dag.to_cbor()?)?;
+    let from_json = Dag::from_json(&dag.to_json()?)?;
+
+    for roundtripped in [&from_cbor, &from_json] {
+        roundtripped.verify()?;
+        assert_eq!(roundtripped.root, dag.root);
+
+        let root_leaf = &roundtripped.leaves[&roundtripped.root];
+        assert_eq!(root_leaf.additional_data.as_ref(), Some(&metadata));
+    }
+
+    // Chaining both formats must also be lossless
+    let chained = Dag::from_json(&from_cbor.to_json()?)?;
+    chained.verify()?;
+    assert_eq!(chained.root, dag.root);
+
+    Ok(())
+}
+
+#[test]
+fn test_dag_size_contributions_sum_to_dag_size() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("subdir"))?;
+
+    fs::write(dir.join("small.txt"), "small")?;
+    let large: Vec<u8> = (0..5000).map(|i| (i % 256) as u8).collect();
+    fs::write(dir.join("subdir").join("large.bin"), large)?;
+
+    let config = DagBuilderConfig::new().with_chunk_size(1024);
+    let dag = create_dag_with_config(&dir, config)?;
+
+    let root = &dag.leaves[&dag.root];
+    let mut total = root.root_dag_size_contribution()?;
+    for (hash, leaf) in &dag.leaves {
+        if hash != &dag.root {
+            total += leaf.dag_size_contribution()?;
+        }
+    }
+
+    assert_eq!(Some(total), root.dag_size);
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_leaf_fields_roundtrip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("test.txt");
+    fs::write(&file, "content")?;
+
+    let dag = create_dag(&file, false)?;
+
+    // Simulate a newer implementation adding a field to the root leaf
+    let mut value: serde_json::Value = serde_json::from_slice(&dag.to_json()?).unwrap();
+    value["Leafs"][&dag.root]["FutureField"] = serde_json::json!("from the future");
+    let with_extra = Dag::from_json(&serde_json::to_vec(&value).unwrap())?;
+
+    with_extra.verify()?;
+    assert_eq!(with_extra.root, dag.root);
+
+    let expected = serde_cbor::Value::Text("from the future".to_string());
+    let root_leaf = &with_extra.leaves[&with_extra.root];
+    assert_eq!(root_leaf.extra.get("FutureField"), Some(&expected));
+
+    // The field survives re-serialization in both formats
+    let from_cbor = Dag::from_cbor(&with_extra.to_cbor()?)?;
+    let from_json = Dag::from_json(&with_extra.to_json()?)?;
+    for roundtripped in [&from_cbor, &from_json] {
+        roundtripped.verify()?;
+        let root_leaf = &roundtripped.leaves[&roundtripped.root];
+        assert_eq!(root_leaf.extra.get("FutureField"), Some(&expected));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_as_expected_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a.txt"), "content a")?;
+    fs::write(dir.join("b.txt"), "content b")?;
+
+    let dag = create_dag(&dir, false)?;
+    dag.verify_as(&dag.root)?;
+
+    let other_file = temp_dir.path().join("other.txt");
+    fs::write(&other_file, "other")?;
+    let other = create_dag(&other_file, false)?;
+
+    match dag.verify_as(&other.root) {
+        Err(ScionicError::HashMismatch { expected, got }) => {
+            assert_eq!(expected, other.root);
+            assert_eq!(got, dag.root);
+        }
+        result => panic!("expected HashMismatch, got {:?}", result),
+    }
+
+    // A child substituted from another DAG, with the parent's link
+    // rewritten to it, doesn't change the root's CID but no longer makes up
+    // its merkle root
+    let evil_dir = temp_dir.path().join("evil");
+    fs::create_dir(&evil_dir)?;
+    fs::write(evil_dir.join("b.txt"), "EVIL")?;
+    let evil = create_dag(&evil_dir, false)?;
+    let find_b = |dag: &scionic_merkle_tree_rs::Dag| {
+        dag.leaves
+            .values()
+            .find(|leaf| leaf.item_name == "b.txt")
+            .unwrap()
+            .clone()
+    };
+    let (original, substitute) = (find_b(&dag), find_b(&evil));
+
+    let mut tampered = dag.clone();
+    tampered.leaves.remove(&original.hash);
+    tampered
+        .leaves
+        .insert(substitute.hash.clone(), substitute.clone());
+    let root = tampered.leaves.get_mut(&dag.root).unwrap();
+    for link in &mut root.links {
+        if *link == original.hash {
+            *link = substitute.hash.clone();
+        }
+    }
+
+    match tampered.verify_as(&dag.root) {
+        Err(ScionicError::InvalidLeaf(msg)) => assert!(msg.contains("merkle root")),
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+    assert!(tampered.verify().is_err());
+    assert!(tampered.verify_strict().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_link_count_mismatch_is_reported() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("subdir"))?;
+    fs::write(dir.join("subdir").join("a.txt"), "content a")?;
+    fs::write(dir.join("subdir").join("b.txt"), "content b")?;
+
+    let dag = create_dag(&dir, false)?;
+
+    let mut tampered = dag.clone();
+    let subdir = tampered
+        .leaves
+        .values_mut()
+        .find(|leaf| leaf.item_name == "subdir")
+        .expect("subdir leaf");
+    subdir.current_link_count = 3;
+
+    match tampered.verify() {
+        Err(ScionicError::InvalidLeaf(message)) => {
+            assert_eq!(message, "link count mismatch: stored 3, actual 2");
+        }
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_flatten_maps_paths_to_content_hashes() -> Result<()> {
+    use sha2::{Digest, Sha256};
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("subdir"))?;
+
+    let large: Vec<u8> = (0..5000).map(|i| (i % 256) as u8).collect();
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("small.txt", b"small".to_vec()),
+        ("empty.txt", Vec::new()),
+        ("subdir/large.bin", large),
+    ];
+    for (path, content) in &files {
+        fs::write(dir.join(path), content)?;
+    }
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(1024))?;
+    let flat = dag.flatten()?;
+
+    assert_eq!(flat.len(), files.len());
+    for (path, content) in &files {
+        let expected = Sha256::digest(content).to_vec();
+        assert_eq!(
+            flat.get(*path),
+            Some(&expected),
+            "hash mismatch for {}",
+            path
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_root_gate() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a.txt"), "content a")?;
+    fs::write(dir.join("b.txt"), "content b")?;
+
+    let dag = create_dag(&dir, false)?;
+    dag.verify_root()?;
+
+    // Only the root is checked, so a missing child doesn't matter here
+    let mut without_child = dag.clone();
+    let child = dag.leaves[&dag.root].links[0].clone();
+    without_child.leaves.remove(&child);
+    without_child.verify_root()?;
+
+    let mut tampered = dag.clone();
+    let root = tampered.leaves.get_mut(&tampered.root).unwrap();
+    root.item_name = "renamed".to_string();
+    assert!(tampered.verify_root().is_err());
+
+    let mut no_count = dag.clone();
+    no_count.leaves.get_mut(&no_count.root).unwrap().leaf_count = None;
+    assert!(no_count.verify_root().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_with_progress() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("subdir"))?;
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(dir.join("subdir").join("large.bin"), vec![3u8; 5000])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(1024))?;
+
+    let mut calls = 0;
+    dag.verify_with_progress(|progress| {
+        calls += 1;
+        assert_eq!(progress.verified, calls);
+        assert_eq!(progress.total, dag.leaves.len());
+    })?;
+    assert_eq!(calls, dag.leaves.len());
+
+    // Progress reporting doesn't change the outcome
+    let mut tampered = dag.clone();
+    tampered.leaves.get_mut(&tampered.root).unwrap().item_name = "renamed".to_string();
+    assert!(tampered.verify_with_progress(|_| {}).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_equivalent_ignoring_names() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let first = temp_dir.path().join("first");
+    let second = temp_dir.path().join("second");
+
+    for dir in [&first, &second] {
+        fs::create_dir(dir)?;
+        fs::create_dir(dir.join("subdir"))?;
+        fs::write(dir.join("a.txt"), "content a")?;
+        fs::write(dir.join("subdir").join("b.txt"), "content b")?;
+    }
+
+    let dag1 = create_dag(&first, false)?;
+    let dag2 = create_dag(&second, false)?;
+    assert_ne!(dag1.root, dag2.root);
+    assert!(dag1.equivalent_ignoring_names(&dag2));
+
+    // Chunking differently doesn't change the content
+    let chunked = create_dag_with_config(&second, DagBuilderConfig::new().with_chunk_size(4))?;
+    assert!(dag1.equivalent_ignoring_names(&chunked));
+
+    fs::write(second.join("subdir").join("b.txt"), "changed b")?;
+    let changed = create_dag(&second, false)?;
+    assert!(!dag1.equivalent_ignoring_names(&changed));
+
+    Ok(())
+}
+
+#[test]
+fn test_additional_data_serialization_is_order_independent() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("test.txt");
+    fs::write(&file, "content")?;
+
+    let pairs: Vec<(String, String)> = ["zeta", "alpha", "middle", "beta", "omega"]
+        .iter()
+        .map(|key| (key.to_string(), format!("value for {}", key)))
+        .collect();
+    let reversed: Vec<_> = pairs.iter().rev().cloned().collect();
+
+    let dag1 = create_dag_with_config(&file, DagBuilderConfig::new().with_additional_data(pairs))?;
+    let dag2 = create_dag_with_config(
+        &file,
+        DagBuilderConfig::new().with_additional_data(reversed),
+    )?;
+
+    assert_eq!(dag1.root, dag2.root);
+    assert_eq!(dag1.to_json()?, dag2.to_json()?);
+    assert_eq!(dag1.to_cbor()?, dag2.to_cbor()?);
+
+    // Stored metadata is in key order, matching the hashed representation
+    let keys: Vec<_> = dag1.leaves[&dag1.root]
+        .additional_data
+        .as_ref()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    assert_eq!(keys, ["alpha", "beta", "middle", "omega", "zeta"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_strict_rejects_proofs_in_full_dag() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    for i in 0..4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+
+    let dag = create_dag(&dir, false)?;
+    dag.verify_strict()?;
+
+    // A bogus proof on the root still passes plain verification
+    let root_leaf = &dag.leaves[&dag.root];
+    let child = root_leaf.links[0].clone();
+    let mut branch = root_leaf.get_branch(&child)?.unwrap();
+    branch.proof.siblings[0][0] ^= 0xff;
+
+    let mut tampered = dag.clone();
+    tampered
+        .leaves
+        .get_mut(&dag.root)
+        .unwrap()
+        .proofs
+        .get_or_insert_with(Default::default)
+        .insert(child, branch);
+    tampered.verify()?;
+
+    match tampered.verify_strict() {
+        Err(ScionicError::InvalidDag(msg)) => assert!(msg.contains("carries proofs")),
+        result => panic!("expected InvalidDag, got {:?}", result),
+    }
+
+    // Stored proofs in a partial DAG must verify
+    let partial = dag.clone().with_labels()?.partial_for_labels(1, 2)?;
+    partial.verify_strict()?;
+
+    let mut bad_partial = partial.clone();
+    let proofs = bad_partial
+        .leaves
+        .get_mut(&dag.root)
+        .unwrap()
+        .proofs
+        .as_mut()
+        .unwrap();
+    for branch in proofs.values_mut() {
+        branch.proof.siblings[0][0] ^= 0xff;
+    }
+    assert!(matches!(
+        bad_partial.verify_strict(),
+        Err(ScionicError::InvalidProof)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_sha512_256_dag() -> Result<()> {
+    use scionic_merkle_tree_rs::HashAlgorithm;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..5 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(dir.join("sub").join("large.bin"), vec![3u8; 100])?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(16)
+        .with_hash_algorithm(HashAlgorithm::Sha512_256);
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+
+    for leaf in dag.leaves.values() {
+        let cid = cid::Cid::try_from(leaf.hash.as_str()).unwrap();
+        assert_eq!(cid.hash().code(), 0x1013);
+        assert_eq!(leaf.hash_algorithm()?, HashAlgorithm::Sha512_256);
+    }
+
+    // Differs from the SHA-256 DAG of the same tree
+    let sha256 = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+    assert_ne!(dag.root, sha256.root);
+
+    // Merkle proofs use the same algorithm
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "file2.txt")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+    dag.prove_leaf(&file_hash)?.verify(&dag.root)?;
+    let partial = dag.get_partial(&[file_hash], false)?;
+    partial.verify()?;
+
+    let mut tampered = dag.clone();
+    let chunk = tampered
+        .leaves
+        .values_mut()
+        .find(|leaf| leaf.leaf_type == LeafType::Chunk)
+        .unwrap();
+    chunk.item_name.push('x');
+    assert!(tampered.verify().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_blake3_dag() -> Result<()> {
+    use scionic_merkle_tree_rs::HashAlgorithm;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("file.txt"), "content")?;
+    fs::write(dir.join("sub").join("large.bin"), vec![7u8; 100])?;
+
+    let config = DagBuilderConfig::new()
+        .with_chunk_size(16)
+        .with_hash_algorithm(HashAlgorithm::Blake3);
+    let dag = create_dag_with_config(&dir, config)?;
+    dag.verify()?;
+
+    for leaf in dag.leaves.values() {
+        let cid = cid::Cid::try_from(leaf.hash.as_str()).unwrap();
+        assert_eq!(cid.hash().code(), 0x1e);
+        assert_eq!(leaf.hash_algorithm()?, HashAlgorithm::Blake3);
+    }
+
+    // The multihash code shows in the CID prefix
+    let sha256 = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(16))?;
+    assert_ne!(dag.root[..8], sha256.root[..8]);
+    assert_ne!(dag.root, sha256.root);
+
+    let mut tampered = dag.clone();
+    let chunk = tampered
+        .leaves
+        .values_mut()
+        .find(|leaf| leaf.leaf_type == LeafType::Chunk)
+        .unwrap();
+    chunk.content.as_mut().unwrap()[0] ^= 0xff;
+    assert!(tampered.verify().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_repair_derived_fields() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..3 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(dir.join("sub").join("nested.txt"), "nested")?;
+
+    let dag = create_dag(&dir, false)?.with_labels()?;
+    let mut stale = dag.clone();
+
+    // Stale parent hashes and labels don't affect verification
+    for leaf in stale.leaves.values_mut() {
+        leaf.parent_hash = Some(dag.root.clone());
+    }
+    let labels = stale.labels.as_mut().unwrap();
+    labels.insert("1".to_string(), dag.root.clone());
+    labels.insert("999".to_string(), dag.root.clone());
+
+    // A stripped Merkle root is recomputable
+    let sub_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "sub")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+    stale.leaves.get_mut(&sub_hash).unwrap().classic_merkle_root = None;
+
+    stale.repair()?;
+    stale.verify()?;
+    assert_eq!(stale.root, dag.root);
+    assert_eq!(stale.labels, dag.labels);
+    assert_eq!(
+        stale.leaves[&sub_hash].classic_merkle_root,
+        dag.leaves[&sub_hash].classic_merkle_root
+    );
+    for leaf in stale.leaves.values() {
+        match &leaf.parent_hash {
+            None => assert_eq!(leaf.hash, dag.root),
+            Some(parent) => assert!(stale.leaves[parent].has_link(&leaf.hash)),
+        }
+    }
+
+    // A link change that the CID doesn't cover is real corruption
+    let mut corrupt = dag.clone();
+    let root = corrupt.leaves.get_mut(&dag.root).unwrap();
+    root.links.retain(|link| *link != sub_hash);
+    root.current_link_count -= 1;
+    assert!(matches!(
+        corrupt.repair(),
+        Err(ScionicError::MerkleRootMismatch)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_link_is_reported() -> Result<()> {
+    use scionic_merkle_tree_rs::DagLeafBuilder;
+
+    let child = DagLeafBuilder::new("dir/a.txt")
+        .set_type(LeafType::File)
+        .set_data(b"content a".to_vec())
+        .build_leaf(None)?;
+
+    // The CID is computed over the duplicated links, so only the explicit
+    // check catches this
+    let dir = DagLeafBuilder::new("dir")
+        .set_type(LeafType::Directory)
+        .add_link(child.hash.clone())
+        .add_link(child.hash.clone())
+        .build_leaf(None)?;
+
+    match dir.verify_leaf() {
+        Err(ScionicError::InvalidLeaf(message)) => {
+            assert_eq!(message, format!("duplicate link: {}", child.hash));
+        }
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_item_names_are_reported() -> Result<()> {
+    use scionic_merkle_tree_rs::DagLeafBuilder;
+    use std::collections::HashMap;
+
+    // Two distinct files that would both be recreated as dir/same.txt
+    let first = DagLeafBuilder::new("dir/same.txt")
+        .set_type(LeafType::File)
+        .set_data(b"first".to_vec())
+        .build_leaf(None)?;
+    let second = DagLeafBuilder::new("dir/same.txt")
+        .set_type(LeafType::File)
+        .set_data(b"second".to_vec())
+        .build_leaf(None)?;
+
+    let dir = DagLeafBuilder::new("dir")
+        .set_type(LeafType::Directory)
+        .add_link(first.hash.clone())
+        .add_link(second.hash.clone())
+        .build_leaf(None)?;
+
+    let mut leaves = HashMap::new();
+    for leaf in [first, second, dir.clone()] {
+        leaves.insert(leaf.hash.clone(), leaf);
+    }
+    let root = DagLeafBuilder::new("root")
+        .set_type(LeafType::Directory)
+        .add_link(dir.hash.clone())
+        .build_root_leaf(&leaves, None)?;
+    leaves.insert(root.hash.clone(), root.clone());
+
+    let dag = Dag::from_parts(root.hash, leaves, None)?;
+
+    // Every leaf is valid on its own
+    dag.verify_with(VerifyOptions {
+        check_reachability: false,
+        ..VerifyOptions::default()
+    })?;
+
+    match dag.verify() {
+        Err(ScionicError::InvalidDag(message)) => {
+            assert!(message.contains("same.txt"));
+            assert!(message.contains(&dir.hash));
+        }
+        result => panic!("expected InvalidDag, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reordered_chunk_links_are_reported() -> Result<()> {
+    use scionic_merkle_tree_rs::DagLeafBuilder;
+
+    let temp_dir = TempDir::new()?;
+    let file_path = temp_dir.path().join("data.bin");
+    let content: Vec<u8> = (0..4_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&file_path, &content)?;
+
+    let dag = create_dag_with_config(&file_path, DagBuilderConfig::new().with_chunk_size(1000))?;
+    dag.verify_chunk_order()?;
+
+    // Rebuild the file root with two chunks swapped, so every CID is valid
+    let (root, mut leaves, _) = dag.into_parts();
+    let file = leaves.remove(&root).unwrap();
+    let mut links = file.links.clone();
+    links.swap(1, 2);
+    let swapped = links
+        .iter()
+        .fold(
+            DagLeafBuilder::new(file.item_name.clone()).set_type(LeafType::File),
+            |builder, link| builder.add_link(link.clone()),
+        )
+        .build_root_leaf(&leaves, None)?;
+    leaves.insert(swapped.hash.clone(), swapped.clone());
+    let dag = Dag::from_parts(swapped.hash.clone(), leaves, None)?;
+
+    dag.verify_with(VerifyOptions {
+        check_reachability: false,
+        check_sizes: false,
+        ..VerifyOptions::default()
+    })?;
+
+    match dag.verify() {
+        Err(ScionicError::InvalidDag(message)) => {
+            assert!(message.contains(&swapped.hash));
+            assert!(message.contains("data.bin/2 at position 1"));
+        }
+        result => panic!("expected InvalidDag, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_directory_with_content_hash_is_rejected() -> Result<()> {
+    use scionic_merkle_tree_rs::DagLeafBuilder;
+    use std::collections::HashMap;
+
+    let file = DagLeafBuilder::new("dir/a.txt")
+        .set_type(LeafType::File)
+        .set_data(b"a".to_vec())
+        .build_leaf(None)?;
+
+    // The content hash is part of the CID, so the directory still verifies
+    // on its own; only the content itself is dropped
+    let mut dir = DagLeafBuilder::new("dir")
+        .set_type(LeafType::Directory)
+        .set_data(b"bogus".to_vec())
+        .add_link(file.hash.clone())
+        .build_leaf(None)?;
+    dir.content = None;
+    dir.verify_leaf()?;
+
+    let mut leaves = HashMap::new();
+    for leaf in [file, dir.clone()] {
+        leaves.insert(leaf.hash.clone(), leaf);
+    }
+    let root = DagLeafBuilder::new("root")
+        .set_type(LeafType::Directory)
+        .add_link(dir.hash.clone())
+        .build_root_leaf(&leaves, None)?;
+    leaves.insert(root.hash.clone(), root.clone());
+    let dag = Dag::from_parts(root.hash, leaves, None)?;
+
+    match dag.verify() {
+        Err(ScionicError::InvalidLeaf(message)) => {
+            assert!(message.contains("directory has a content hash"));
+            assert!(message.contains(&dir.hash));
+        }
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_malformed_reserved_keys_are_rejected() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("script.sh");
+    fs::write(&file, "#!/bin/sh")?;
+
+    let build = |key: &str, value: &str| {
+        let config =
+            DagBuilderConfig::new().with_additional_data([(key.to_string(), value.to_string())]);
+        create_dag_with_config(&file, config)
+    };
+
+    // Well-formed values, and timestamped roots, pass
+    for (key, value) in [
+        ("unix_mode", "0755"),
+        ("mime", "text/x-shellscript"),
+        ("content_encoding", "gzip"),
+        ("timestamp", "2024-05-01T12:00:00+00:00"),
+        ("author", "anything goes"),
+    ] {
+        build(key, value)?.verify_reserved_keys()?;
+    }
+    create_dag(&file, true)?.verify_reserved_keys()?;
+
+    // A malformed unix_mode still hashes and verifies, but is rejected here
+    let dag = build("unix_mode", "rwxr-xr-x")?;
+    dag.verify()?;
+    match dag.verify_reserved_keys() {
+        Err(ScionicError::InvalidLeaf(message)) => assert!(message.contains("unix_mode")),
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    for (key, value) in [
+        ("unix_mode", "0989"),
+        ("unix_mode", "177777"),
+        ("timestamp", "yesterday"),
+        ("prev", "not-a-cid"),
+        ("mime", "text"),
+        ("content_encoding", "gzip, br"),
+    ] {
+        assert!(
+            build(key, value)?.verify_reserved_keys().is_err(),
+            "{} = {}",
+            key,
+            value
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_with_thread_limit() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..20 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content{}", i))?;
+    }
+    fs::write(dir.join("sub").join("large.bin"), vec![8u8; 500])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(32))?;
+    dag.verify()?;
+    dag.verify_parallel()?;
+    dag.verify_with_thread_limit(1)?;
+    dag.verify_with_thread_limit(0)?;
+
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "file7.txt")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+    let partial = dag.get_partial(std::slice::from_ref(&file_hash), false)?;
+    partial.verify_with_thread_limit(1)?;
+
+    // A corrupted leaf is caught the same way under constrained threads
+    let mut tampered = dag.clone();
+    tampered.leaves.get_mut(&file_hash).unwrap().item_name = "renamed.txt".to_string();
+    assert!(matches!(
+        tampered.verify(),
+        Err(ScionicError::HashMismatch { .. })
+    ));
+    assert!(matches!(
+        tampered.verify_with_thread_limit(1),
+        Err(ScionicError::HashMismatch { .. })
+    ));
+    assert!(matches!(
+        tampered.verify_with_thread_limit(2),
+        Err(ScionicError::HashMismatch { .. })
+    ));
+
+    Ok(())
+}
+
+/// Names of the single checks that reject `dag` when run on their own
+fn caught_by(dag: &Dag) -> Vec<&'static str> {
+    ["cids", "content", "reachability", "sizes", "proofs"]
+        .into_iter()
+        .filter(|check| {
+            let mut options = VerifyOptions::none();
+            match *check {
+                "cids" => options.check_cids = true,
+                "content" => options.check_content = true,
+                "reachability" => options.check_reachability = true,
+                "sizes" => options.check_sizes = true,
+                _ => options.check_proofs = true,
+            }
+            dag.verify_with(options).is_err()
+        })
+        .collect()
+}
+
+#[test]
+fn test_verify_options_select_checks() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    for i in 0..4 {
+        fs::write(
+            dir.join("a").join(format!("file{}.txt", i)),
+            format!("a{}", i),
+        )?;
+        fs::write(
+            dir.join("b").join(format!("file{}.txt", i)),
+            format!("b{}", i),
+        )?;
+    }
+    fs::write(dir.join("a").join("big.bin"), vec![1u8; 100])?;
+    fs::write(dir.join("b").join("big.bin"), vec![2u8; 100])?;
+
+    let dag = create_dag_with_config(&dir, DagBuilderConfig::new().with_chunk_size(32))?;
+    let find = |name: &str| {
+        dag.leaves
+            .values()
+            .find(|leaf| leaf.item_name == name)
+            .map(|leaf| leaf.hash.clone())
+            .unwrap()
+    };
+
+    dag.verify()?;
+    assert!(caught_by(&dag).is_empty());
+
+    // CIDs: a renamed leaf keeps its old hash (same length name, so the DAG
+    // size still adds up)
+    let mut tampered = dag.clone();
+    tampered
+        .leaves
+        .get_mut(&find("a/file0.txt"))
+        .unwrap()
+        .item_name = "a/file9.txt".to_string();
+    assert_eq!(caught_by(&tampered), ["cids"]);
+
+    // Content: same-length content that no longer matches its content hash
+    let mut tampered = dag.clone();
+    tampered
+        .leaves
+        .get_mut(&find("a/file1.txt"))
+        .unwrap()
+        .content
+        .as_mut()
+        .unwrap()[0] ^= 0xff;
+    assert_eq!(caught_by(&tampered), ["content"]);
+
+    // Sizes: a chunk swapped for another file's, so every link still
+    // resolves (the links no longer make up the file's merkle root either)
+    let mut tampered = dag.clone();
+    let other_chunk = dag.leaves[&find("b/big.bin")].links[0].clone();
+    let file = tampered.leaves.get_mut(&find("a/big.bin")).unwrap();
+    let replaced = std::mem::replace(&mut file.links[0], other_chunk);
+    tampered.leaves.remove(&replaced);
+    assert_eq!(caught_by(&tampered), ["cids", "sizes"]);
+    assert!(tampered.verify().is_err());
+
+    let partial = dag.partial_by_glob("a/file0.txt")?;
+    partial.verify()?;
+    assert!(caught_by(&partial).is_empty());
+
+    // Reachability: a valid leaf whose parent isn't in the partial DAG
+    let mut tampered = partial.clone();
+    let stray = dag.leaves[&find("b/file1.txt")].clone();
+    tampered.leaves.insert(stray.hash.clone(), stray);
+    assert_eq!(caught_by(&tampered), ["reachability"]);
+
+    // Proofs: corrupted siblings
+    let mut tampered = partial.clone();
+    let mut corrupted = 0;
+    for leaf in tampered.leaves.values_mut() {
+        for branch in leaf
+            .proofs
+            .iter_mut()
+            .flat_map(|proofs| proofs.values_mut())
+        {
+            branch.proof.siblings[0][0] ^= 0xff;
+            corrupted += 1;
+        }
+    }
+    assert!(corrupted > 0);
+    assert_eq!(caught_by(&tampered), ["proofs"]);
+    assert!(tampered.verify().is_err());
+
+    // Nothing enabled accepts anything
+    tampered.verify_with(VerifyOptions::none())?;
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_root_without_links_is_rejected() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a.txt"), "a")?;
+    fs::write(dir.join("b.txt"), "b")?;
+
+    let mut dag = create_dag(&dir, false)?;
+    let root_merkle_root = dag.leaves[&dag.root].classic_merkle_root.clone();
+    let file_hash = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "a.txt")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+    let file = dag.leaves.get_mut(&file_hash).unwrap();
+    file.classic_merkle_root = root_merkle_root;
+
+    match dag.verify() {
+        Err(ScionicError::InvalidLeaf(message)) => {
+            assert!(message.contains("merkle root without links"));
+            assert!(message.contains(&file_hash));
+        }
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    // And the reverse: links with the merkle root dropped
+    let mut dag = create_dag(&dir, false)?;
+    let root = dag.root.clone();
+    dag.leaves.get_mut(&root).unwrap().classic_merkle_root = None;
+    match dag.verify() {
+        Err(ScionicError::InvalidLeaf(message)) => {
+            assert!(message.contains("links without a merkle root"))
+        }
+        result => panic!("expected InvalidLeaf, got {:?}", result),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_sizes_recomputes_root_totals() -> Result<()> {
+    use scionic_merkle_tree_rs::DagLeafBuilder;
+
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("test");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("a.txt"), "some content")?;
+    fs::write(dir.join("b.txt"), "more content")?;
+
+    let dag = create_dag(&dir, false)?;
+    dag.verify_sizes()?;
+
+    // A consistent root, rebuilt over the real links, but claiming a
+    // different content size
+    let root = &dag.leaves[&dag.root];
+    let mut leaves = dag.leaves.clone();
+    leaves.remove(&dag.root);
+    let mut padded = leaves.clone();
+    padded.values_mut().next().unwrap().content = Some(vec![0; 100]);
+    let forged = root
+        .links
+        .iter()
+        .fold(
+            DagLeafBuilder::new(root.item_name.clone()).set_type(LeafType::Directory),
+            |builder, link| builder.add_link(link.clone()),
+        )
+        .build_root_leaf(&padded, None)?;
+    forged.verify_root_leaf()?;
+    assert_ne!(forged.content_size, root.content_size);
+
+    leaves.insert(forged.hash.clone(), forged.clone());
+    let forged_dag = Dag::from_parts(forged.hash.clone(), leaves, None)?;
+    assert!(matches!(
+        forged_dag.verify_sizes(),
+        Err(ScionicError::SizeMismatch { .. })
+    ));
+    assert!(forged_dag.verify().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_full_dag_rejects_dangling_links() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path().join("input");
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "a")?;
+    fs::write(dir.join("sub").join("b.txt"), "b")?;
+
+    let dag = create_dag(&dir, false)?;
+    let removed = dag
+        .leaves
+        .values()
+        .find(|leaf| leaf.item_name == "sub/b.txt")
+        .map(|leaf| leaf.hash.clone())
+        .unwrap();
+
+    // Still marked full, so the dangling link is an error
+    let mut truncated = dag.clone();
+    truncated.leaves.remove(&removed);
+    assert!(matches!(truncated.verify(), Err(ScionicError::MissingLink(hash)) if hash == removed));
+
+    // As loaded, with the leaf count adjusted so the DAG still reads as full
+    let mut loaded = Dag::from_cbor(&truncated.to_cbor()?)?;
+    let root = loaded.root.clone();
+    let count = loaded.leaves.len();
+    loaded.leaves.get_mut(&root).unwrap().leaf_count = Some(count);
+    assert!(!loaded.is_partial());
+    let result = loaded.verify_with(VerifyOptions {
+        check_cids: false,
+        check_sizes: false,
+        ..VerifyOptions::default()
+    });
+    assert!(matches!(result, Err(ScionicError::MissingLink(hash)) if hash == removed));
+
+    Ok(())
+}